@@ -0,0 +1,35 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_default();
+
+    // honor SOURCE_DATE_EPOCH for reproducible builds
+    let timestamp = match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch,
+        Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs().to_string())
+            .unwrap_or_default(),
+    };
+
+    println!("cargo:rustc-env=REPOCHECKER_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=REPOCHECKER_BUILD_TIMESTAMP={}", timestamp);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+
+    // HEAD only changes when switching branches, new commits update the branch it points to
+    if let Ok(head) = std::fs::read_to_string(".git/HEAD") {
+        if let Some(reference) = head.trim().strip_prefix("ref: ") {
+            println!("cargo:rerun-if-changed=.git/{}", reference);
+            println!("cargo:rerun-if-changed=.git/packed-refs");
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
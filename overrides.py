@@ -1,10 +1,11 @@
 #!/usr/bin/python3
 
 import argparse
+import datetime
 import json
 import os
 
-from typing import List
+from typing import List, Optional
 
 VALID_ARCHES = [
     "x86_64",
@@ -74,6 +75,13 @@ def main():
         default=[],
         help="packages to override missing dependencies for (singleton all has the special meaning)",
     )
+    insert_parser.add_argument(
+        "--user",
+        "-u",
+        required=False,
+        default=os.environ.get("USER"),
+        help="user who adds the override (defaults to $USER)",
+    )
 
     sortit_parser = parsers.add_parser(
         "sortit",
@@ -109,8 +117,9 @@ def main():
         arch = cli_args["arch"]
         dep = cli_args["dependency"]
         value = cli_args["packages"]
+        user = cli_args["user"]
 
-        return insert(path, release, arch, dep, value)
+        return insert(path, release, arch, dep, value, user)
 
     if action == "sortit":
         return sortit(path)
@@ -119,7 +128,7 @@ def main():
         return validate(path)
 
 
-def insert(path: str, release: str, arch: str, dep: str, values: List[str]) -> int:
+def insert(path: str, release: str, arch: str, dep: str, values: List[str], user: Optional[str]) -> int:
     with open(path) as file:
         overrides = json.loads(file.read())
 
@@ -127,6 +136,10 @@ def insert(path: str, release: str, arch: str, dep: str, values: List[str]) -> i
 
     current = overrides[release][arch]
     if dep in current.keys():
+        # overrides with information about who added them keep their packages in a "packages" field
+        if isinstance(current[dep], dict):
+            current, dep = current[dep], "packages"
+
         if current[dep] == "all":
             print(" → 'all' override subsumes individual overrides, this has no effect")
         elif values == ["all"]:
@@ -138,14 +151,20 @@ def insert(path: str, release: str, arch: str, dep: str, values: List[str]) -> i
             current[dep].extend(values)
             inserted = True
     else:
+        # new overrides record who added them and when
+        entry = {"added_on": datetime.date.today().isoformat()}
+        if user:
+            entry["added_by"] = user
+
         if values == ["all"]:
             print(f" → adding 'all' override for /{release}/{arch}/{dep}")
-            current[dep] = "all"
-            inserted = True
+            entry["packages"] = "all"
         else:
             print(f" → adding {len(values)} overrides for /{release}/{arch}/{dep}")
-            current[dep] = values
-            inserted = True
+            entry["packages"] = values
+
+        current[dep] = entry
+        inserted = True
 
     with open(path, "w") as file:
         file.write(json.dumps(overrides, indent=2, sort_keys=True))
@@ -172,6 +191,8 @@ def sortit(path: str):
                 break
 
             for dep, dep_item in arch_item.items():
+                if isinstance(dep_item, dict):
+                    dep_item = dep_item.get("packages")
                 if isinstance(dep_item, list):
                     dep_item.sort()
 
@@ -219,6 +240,18 @@ def validate(path: str):
                 break
 
             for dep, dep_item in arch_item.items():
+                if isinstance(dep_item, dict):
+                    for key in dep_item.keys():
+                        if key not in ("packages", "added_by", "added_on"):
+                            print(f" - /{release}/{arch}/{dep} contains invalid key '{key}'.")
+                            valid = False
+
+                    if "packages" not in dep_item:
+                        print(f" - /{release}/{arch}/{dep} does not contain a list of packages.")
+                        valid = False
+
+                    dep_item = dep_item.get("packages")
+
                 if isinstance(dep_item, str):
                     if dep_item == "all":
                         broad.append(f"/{release}/{arch}/{dep}")
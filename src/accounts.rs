@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use log::{debug, error};
+use serde::Deserialize;
+
+use crate::config::InactivityConfig;
+use crate::data::BrokenItem;
+use crate::http::Download;
+
+#[derive(Debug, Deserialize)]
+struct ActivityPage {
+    total: u64,
+    // incomplete
+}
+
+/// Checks whether a user has not caused any messages on the Fedora message bus within the configured threshold.
+async fn is_inactive(config: &InactivityConfig, user: &str, timeout: u64) -> Result<bool, String> {
+    let delta = (config.threshold * 24.0 * 60.0 * 60.0) as u64;
+
+    let url = match reqwest::Url::parse_with_params(
+        &config.url,
+        &[("user", user), ("delta", &delta.to_string()), ("rows_per_page", "1")],
+    ) {
+        Ok(url) => url,
+        Err(error) => return Err(error.to_string()),
+    };
+
+    // lookups are not retried, since the activity of every user is looked up separately (and failed lookups only mean
+    // that a user is not considered to be inactive)
+    let page: ActivityPage = Download {
+        retries: 0,
+        ..Download::new(url, timeout)
+    }
+    .fetch_json()
+    .await?;
+
+    Ok(page.total == 0)
+}
+
+/// Returns the subset of the given users that have not been active recently.
+///
+/// Users for which the activity lookup fails are not considered to be inactive.
+pub async fn get_inactive(config: &InactivityConfig, users: HashSet<String>, timeout: u64) -> HashSet<String> {
+    let mut inactive = HashSet::new();
+
+    for user in users {
+        match is_inactive(config, &user, timeout).await {
+            Ok(true) => {
+                debug!("User {} has not been active for {} days.", &user, config.threshold);
+                inactive.insert(user);
+            },
+            Ok(false) => {},
+            Err(error) => error!("Failed to determine recent activity of {}: {}", &user, error),
+        }
+    }
+
+    inactive
+}
+
+/// Flags broken packages whose main admin is in the given set of inactive users (and clears the flag for all others).
+///
+/// Returns whether any flags were changed.
+pub fn flag_inactive(items: &mut [BrokenItem], inactive: &HashSet<String>) -> bool {
+    let mut changed = false;
+
+    for item in items {
+        let flag = inactive.contains(&item.admin);
+        if item.admin_inactive != flag {
+            item.admin_inactive = flag;
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn flag_inactive() {
+        let mut items = vec![
+            BrokenItem {
+                admin: String::from("alice"),
+                admin_inactive: true,
+                ..Default::default()
+            },
+            BrokenItem {
+                admin: String::from("bob"),
+                ..Default::default()
+            },
+        ];
+
+        // users that are active again are no longer flagged
+        let inactive = HashSet::from([String::from("bob")]);
+        assert!(super::flag_inactive(&mut items, &inactive));
+        assert!(!items[0].admin_inactive);
+        assert!(items[1].admin_inactive);
+
+        assert!(!super::flag_inactive(&mut items, &inactive));
+    }
+}
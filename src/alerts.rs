@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::AlertRule;
+use crate::data::BrokenItem;
+
+/// Alert that was raised by a rule for the most recent results of a release.
+#[derive(Clone, Debug, Serialize)]
+pub struct Alert {
+    pub rule: String,
+    pub release: String,
+    pub message: String,
+    pub raised_at: DateTime<Utc>,
+}
+
+/// Evaluates all rules that apply to a release against its new (and previous, if available) results.
+///
+/// Every rule raises at most one alert, which lists all of its conditions that are met.
+pub fn evaluate(rules: &[AlertRule], release: &str, old: Option<&[BrokenItem]>, new: &[BrokenItem]) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+
+    for rule in rules {
+        if !rule.releases.is_empty() && !rule.releases.iter().any(|r| r == release) {
+            continue;
+        }
+
+        let mut reasons = Vec::new();
+
+        if let (Some(max_increase), Some(old)) = (rule.max_increase, old) {
+            let increase = new.len().saturating_sub(old.len());
+            if increase > max_increase {
+                reasons.push(format!(
+                    "number of broken packages increased by {} (from {} to {})",
+                    increase,
+                    old.len(),
+                    new.len()
+                ));
+            }
+        }
+
+        if let Some(max_broken) = rule.max_broken {
+            if new.len() > max_broken {
+                reasons.push(format!("{} packages are broken", new.len()));
+            }
+        }
+
+        if rule.critpath {
+            let mut critpath: Vec<&str> = new
+                .iter()
+                .filter(|item| item.critpath)
+                .map(|item| item.source.as_str())
+                .collect();
+            critpath.sort_unstable();
+            critpath.dedup();
+
+            if !critpath.is_empty() {
+                reasons.push(format!("critical path packages are broken: {}", critpath.join(", ")));
+            }
+        }
+
+        if !reasons.is_empty() {
+            alerts.push(Alert {
+                rule: rule.name.clone(),
+                release: release.to_string(),
+                message: reasons.join("; "),
+                raised_at: Utc::now(),
+            });
+        }
+    }
+
+    alerts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::data::broken_item;
+
+    fn item(source: &str, critpath: bool) -> BrokenItem {
+        BrokenItem {
+            critpath,
+            ..broken_item(source, &[])
+        }
+    }
+
+    #[test]
+    fn evaluate() {
+        let rules = vec![
+            AlertRule {
+                name: String::from("rawhide-increase"),
+                releases: vec![String::from("rawhide")],
+                max_increase: Some(1),
+                max_broken: None,
+                critpath: false,
+            },
+            AlertRule {
+                name: String::from("critpath"),
+                releases: Vec::new(),
+                max_increase: None,
+                max_broken: None,
+                critpath: true,
+            },
+        ];
+
+        let old = vec![item("foo", false)];
+        let new = vec![item("foo", false), item("bar", false), item("baz", true)];
+
+        let alerts = super::evaluate(&rules, "rawhide", Some(&old), &new);
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].rule, "rawhide-increase");
+        assert_eq!(
+            alerts[0].message,
+            "number of broken packages increased by 2 (from 1 to 3)"
+        );
+        assert_eq!(alerts[1].message, "critical path packages are broken: baz");
+
+        // rules only apply to their releases, and increases are not known without previous results
+        let alerts = super::evaluate(&rules, "41", Some(&old), &new[..2]);
+        assert!(alerts.is_empty());
+        let alerts = super::evaluate(&rules, "rawhide", None, &new[..2]);
+        assert!(alerts.is_empty());
+    }
+}
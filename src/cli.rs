@@ -0,0 +1,534 @@
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::data::BrokenItem;
+use crate::render::RenderArgs;
+use crate::reports::diff_items;
+use crate::utils::{get_frozen_path, get_json_path, read_frozen_from_file, read_json_from_file};
+
+// ANSI color codes for terminal output
+const RED: &str = "31";
+const GREEN: &str = "32";
+const YELLOW: &str = "33";
+
+const USAGE: &str = "\
+Usage:
+    repochecker [--fake-data]
+    repochecker query --release <release> [--product <product>] [--testing]
+                      [--maintainer <user>] [--package <name>] [--broken <dependency>] [--format table|json]
+    repochecker diff <old.json[.gz]> <new.json[.gz]> [--format text|json]
+    repochecker render [--input <data directory>] --out <directory>
+    repochecker snapshot --out <snapshot.json.gz>
+    repochecker restore <snapshot.json.gz>
+    repochecker import <data.json> --release <release>";
+
+/// Subcommand that was selected on the command line.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Serve { fake_data: bool },
+    Query(QueryArgs),
+    Diff(DiffArgs),
+    Render(RenderArgs),
+    Snapshot(PathBuf),
+    Restore(PathBuf),
+    Import(ImportArgs),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+impl OutputFormat {
+    fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            // plain text output is printed as a table
+            "table" | "text" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Unknown output format: {}", name)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct QueryArgs {
+    pub release: String,
+    pub product: Option<String>,
+    pub testing: bool,
+    pub maintainer: Option<String>,
+    pub package: Option<String>,
+    pub broken: Option<String>,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DiffArgs {
+    pub old: PathBuf,
+    pub new: PathBuf,
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ImportArgs {
+    pub path: PathBuf,
+    // key of the release as it is served (for example, "epel/9" or "41-testing")
+    pub release: String,
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    match args.next() {
+        Some(value) => Ok(value),
+        None => Err(format!("Missing value for {}.\n\n{}", flag, USAGE)),
+    }
+}
+
+fn parse_query_args(mut args: impl Iterator<Item = String>) -> Result<QueryArgs, String> {
+    let mut release = None;
+    let mut product = None;
+    let mut testing = false;
+    let mut maintainer = None;
+    let mut package = None;
+    let mut broken = None;
+    let mut format = OutputFormat::Table;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--release" => release = Some(next_value(&mut args, &arg)?),
+            "--product" => product = Some(next_value(&mut args, &arg)?),
+            "--testing" => testing = true,
+            "--maintainer" => maintainer = Some(next_value(&mut args, &arg)?),
+            "--package" => package = Some(next_value(&mut args, &arg)?),
+            "--broken" => broken = Some(next_value(&mut args, &arg)?),
+            "--format" => format = OutputFormat::from_name(&next_value(&mut args, &arg)?)?,
+            _ => return Err(format!("Unknown argument: {}\n\n{}", arg, USAGE)),
+        }
+    }
+
+    let release = match release {
+        // accept release names like "f40" in addition to "40"
+        Some(release) => match release.strip_prefix('f') {
+            Some(number) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => number.to_string(),
+            _ => release,
+        },
+        None => return Err(format!("Missing required argument: --release\n\n{}", USAGE)),
+    };
+
+    Ok(QueryArgs {
+        release,
+        product,
+        testing,
+        maintainer,
+        package,
+        broken,
+        format,
+    })
+}
+
+fn parse_diff_args(mut args: impl Iterator<Item = String>) -> Result<DiffArgs, String> {
+    let mut paths = Vec::new();
+    let mut format = OutputFormat::Table;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = OutputFormat::from_name(&next_value(&mut args, &arg)?)?,
+            _ if arg.starts_with("--") => return Err(format!("Unknown argument: {}\n\n{}", arg, USAGE)),
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    match <[PathBuf; 2]>::try_from(paths) {
+        Ok([old, new]) => Ok(DiffArgs { old, new, format }),
+        Err(_) => Err(format!("Expected exactly two data files.\n\n{}", USAGE)),
+    }
+}
+
+fn parse_render_args(mut args: impl Iterator<Item = String>) -> Result<RenderArgs, String> {
+    let mut input = PathBuf::from("data");
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = PathBuf::from(next_value(&mut args, &arg)?),
+            "--out" => output = Some(PathBuf::from(next_value(&mut args, &arg)?)),
+            _ => return Err(format!("Unknown argument: {}\n\n{}", arg, USAGE)),
+        }
+    }
+
+    match output {
+        Some(output) => Ok(RenderArgs { input, output }),
+        None => Err(format!("Missing required argument: --out\n\n{}", USAGE)),
+    }
+}
+
+fn parse_import_args(mut args: impl Iterator<Item = String>) -> Result<ImportArgs, String> {
+    let mut path = None;
+    let mut release = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--release" => release = Some(next_value(&mut args, &arg)?),
+            _ if arg.starts_with("--") || path.is_some() => {
+                return Err(format!("Unknown argument: {}\n\n{}", arg, USAGE))
+            },
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    match (path, release) {
+        (Some(path), Some(release)) => Ok(ImportArgs { path, release }),
+        (None, _) => Err(format!("Expected exactly one data file.\n\n{}", USAGE)),
+        (_, None) => Err(format!("Missing required argument: --release\n\n{}", USAGE)),
+    }
+}
+
+/// Parses command-line arguments (without the program name).
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter().peekable();
+
+    match args.peek().map(String::as_str) {
+        Some("query") => {
+            args.next();
+            Ok(Command::Query(parse_query_args(args)?))
+        },
+        Some("diff") => {
+            args.next();
+            Ok(Command::Diff(parse_diff_args(args)?))
+        },
+        Some("render") => {
+            args.next();
+            Ok(Command::Render(parse_render_args(args)?))
+        },
+        Some("snapshot") => {
+            args.next();
+            match (args.next().as_deref(), args.next(), args.next()) {
+                (Some("--out"), Some(path), None) => Ok(Command::Snapshot(PathBuf::from(path))),
+                _ => Err(format!(
+                    "Expected --out with the path of the snapshot file.\n\n{}",
+                    USAGE
+                )),
+            }
+        },
+        Some("import") => {
+            args.next();
+            Ok(Command::Import(parse_import_args(args)?))
+        },
+        Some("restore") => {
+            args.next();
+            match (args.next(), args.next()) {
+                (Some(path), None) if !path.starts_with("--") => Ok(Command::Restore(PathBuf::from(path))),
+                _ => Err(format!("Expected exactly one snapshot file.\n\n{}", USAGE)),
+            }
+        },
+        _ => {
+            let mut fake_data = false;
+
+            for arg in args {
+                match arg.as_str() {
+                    // development mode: serve synthetic data from fixture files instead of running dnf
+                    "--fake-data" if cfg!(feature = "test-backend") => fake_data = true,
+                    _ => return Err(format!("Unknown argument: {}\n\n{}", arg, USAGE)),
+                }
+            }
+
+            Ok(Command::Serve { fake_data })
+        },
+    }
+}
+
+/// Reads persisted data for a release from the data directory, including data of frozen releases.
+fn read_persisted(product: Option<&str>, release: &str, testing: bool) -> Result<Vec<BrokenItem>, String> {
+    let json_path = get_json_path(product, release, testing);
+    if json_path.exists() {
+        return read_json_from_file(&json_path);
+    }
+
+    let frozen_path = get_frozen_path(product, release, testing);
+    if frozen_path.exists() {
+        return read_frozen_from_file(&frozen_path).map(|frozen| frozen.items);
+    }
+
+    Err(format!("No data found for release {}.", release))
+}
+
+fn matches(item: &BrokenItem, args: &QueryArgs) -> bool {
+    if let Some(maintainer) = &args.maintainer {
+        if &item.admin != maintainer && !item.maintainers.contains(maintainer) {
+            return false;
+        }
+    }
+
+    if let Some(package) = &args.package {
+        if &item.package != package && &item.source != package {
+            return false;
+        }
+    }
+
+    if let Some(broken) = &args.broken {
+        if !item.broken.iter().any(|dep| dep.contains(broken.as_str())) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Writes output to stdout, treating a closed pipe (for example, when piping into `head`) as success.
+pub fn emit(output: &str) -> Result<(), String> {
+    match std::io::stdout().lock().write_all(output.as_bytes()) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Renders rows as a table with columns that are padded to the width of their widest cell.
+pub fn render_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| -> String {
+        cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut output = format_row(headers.to_vec());
+    output.push('\n');
+
+    for row in rows {
+        output.push_str(&format_row(row.iter().map(String::as_str).collect()));
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Prints persisted data for a release, filtered by the given arguments.
+pub fn query(args: &QueryArgs) -> Result<(), String> {
+    let values = read_persisted(args.product.as_deref(), &args.release, args.testing)?;
+    let filtered: Vec<&BrokenItem> = values.iter().filter(|item| matches(item, args)).collect();
+
+    match args.format {
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(&filtered).map_err(|error| error.to_string())?;
+            emit(&format!("{}\n", json))
+        },
+        OutputFormat::Table => {
+            let rows: Vec<Vec<String>> = filtered
+                .iter()
+                .map(|item| {
+                    vec![
+                        item.source.clone(),
+                        item.package.clone(),
+                        item.evr(),
+                        item.repo_arch.clone(),
+                        item.admin.clone(),
+                        item.broken.join(", "),
+                    ]
+                })
+                .collect();
+
+            emit(&render_table(
+                &["SOURCE", "PACKAGE", "EVR", "ARCH", "ADMIN", "BROKEN"],
+                &rows,
+            ))
+        },
+    }
+}
+
+/// Reads a data file in either the uncompressed or the compressed (frozen) format.
+fn read_data_file(path: &Path) -> Result<Vec<BrokenItem>, String> {
+    if path.extension().map(|extension| extension == "gz").unwrap_or(false) {
+        read_frozen_from_file(path).map(|frozen| frozen.items)
+    } else {
+        read_json_from_file(path)
+    }
+    .map_err(|error| format!("{}: {}", path.to_string_lossy(), error))
+}
+
+fn colored(text: &str, color: &str, enabled: bool) -> String {
+    if enabled {
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn describe(item: &BrokenItem) -> String {
+    format!(
+        "{}-{}.{} ({} / {})",
+        item.package,
+        item.evr(),
+        item.arch,
+        item.repo,
+        item.repo_arch
+    )
+}
+
+/// Prints items that were added, removed, or changed between two data files.
+pub fn diff(args: &DiffArgs) -> Result<(), String> {
+    let old = read_data_file(&args.old)?;
+    let new = read_data_file(&args.new)?;
+
+    let diff = diff_items(&old, &new);
+
+    if args.format == OutputFormat::Json {
+        let json = serde_json::to_string_pretty(&diff).map_err(|error| error.to_string())?;
+        return emit(&format!("{}\n", json));
+    }
+
+    // only use colors when writing to a terminal
+    let color = std::io::stdout().is_terminal();
+    let mut output = String::new();
+
+    for item in &diff.added {
+        let line = format!("+ {}: {}", describe(item), item.broken.join(", "));
+        output.push_str(&colored(&line, RED, color));
+        output.push('\n');
+    }
+
+    for item in &diff.removed {
+        let line = format!("- {}: {}", describe(item), item.broken.join(", "));
+        output.push_str(&colored(&line, GREEN, color));
+        output.push('\n');
+    }
+
+    for changed in &diff.changed {
+        let line = format!("~ {} -> {}", describe(&changed.old), changed.new.evr());
+        output.push_str(&colored(&line, YELLOW, color));
+        output.push('\n');
+
+        for dep in changed
+            .new
+            .broken
+            .iter()
+            .filter(|dep| !changed.old.broken.contains(dep))
+        {
+            output.push_str(&colored(&format!("    + {}", dep), RED, color));
+            output.push('\n');
+        }
+
+        for dep in changed
+            .old
+            .broken
+            .iter()
+            .filter(|dep| !changed.new.broken.contains(dep))
+        {
+            output.push_str(&colored(&format!("    - {}", dep), GREEN, color));
+            output.push('\n');
+        }
+    }
+
+    for change in &diff.maintainer_changed {
+        let line = format!(
+            "@ {} ({}, {}): {} -> {}",
+            change.package, change.repo, change.repo_arch, change.old_admin, change.new_admin
+        );
+        output.push_str(&colored(&line, YELLOW, color));
+        output.push('\n');
+    }
+
+    output.push_str(&format!(
+        "{} added, {} removed, {} changed, {} with new main admin\n",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len(),
+        diff.maintainer_changed.len()
+    ));
+
+    emit(&output)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_args() {
+        // fake data is only available with the test backend
+        assert_eq!(
+            super::parse_args(args(&["--fake-data"])).is_ok(),
+            cfg!(feature = "test-backend")
+        );
+
+        let command = super::parse_args(args(&[
+            "query",
+            "--release",
+            "f40",
+            "--maintainer",
+            "alice",
+            "--format",
+            "json",
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            command,
+            Command::Query(QueryArgs {
+                release: String::from("40"),
+                product: None,
+                testing: false,
+                maintainer: Some(String::from("alice")),
+                package: None,
+                broken: None,
+                format: OutputFormat::Json,
+            })
+        );
+
+        assert_eq!(
+            super::parse_args(args(&["diff", "old.json", "new.json", "--format", "json"])).unwrap(),
+            Command::Diff(DiffArgs {
+                old: PathBuf::from("old.json"),
+                new: PathBuf::from("new.json"),
+                format: OutputFormat::Json,
+            })
+        );
+
+        assert!(super::parse_args(args(&["diff", "old.json"])).is_err());
+
+        assert_eq!(
+            super::parse_args(args(&["render", "--out", "site"])).unwrap(),
+            Command::Render(RenderArgs {
+                input: PathBuf::from("data"),
+                output: PathBuf::from("site"),
+            })
+        );
+        assert_eq!(
+            super::parse_args(args(&["snapshot", "--out", "state.json.gz"])).unwrap(),
+            Command::Snapshot(PathBuf::from("state.json.gz"))
+        );
+        assert_eq!(
+            super::parse_args(args(&["restore", "state.json.gz"])).unwrap(),
+            Command::Restore(PathBuf::from("state.json.gz"))
+        );
+        assert!(super::parse_args(args(&["snapshot"])).is_err());
+
+        assert_eq!(
+            super::parse_args(args(&["import", "data.json", "--release", "epel/9"])).unwrap(),
+            Command::Import(ImportArgs {
+                path: PathBuf::from("data.json"),
+                release: String::from("epel/9"),
+            })
+        );
+        assert!(super::parse_args(args(&["import", "data.json"])).is_err());
+
+        assert!(super::parse_args(args(&["query", "--maintainer", "alice"])).is_err());
+        assert!(super::parse_args(args(&["query", "--release"])).is_err());
+    }
+}
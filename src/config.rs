@@ -1,51 +1,862 @@
+use std::collections::BTreeMap;
 use std::fs::read_to_string;
 use std::path::{Path, PathBuf};
 
 use log::info;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::data::BrokenItem;
+
 const CONFIG_FILENAME: &str = "repochecker.toml";
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct Config {
     pub repochecker: RepoCheckerConfig,
     pub repos: RepoConfig,
-    #[serde(rename = "arch")]
+    #[serde(default, rename = "arch", skip_serializing_if = "Vec::is_empty")]
     pub arches: Vec<ArchConfig>,
     #[serde(rename = "release")]
     pub releases: Vec<ReleaseConfig>,
+    #[serde(default, rename = "product")]
+    pub products: Vec<ProductConfig>,
+    #[serde(default)]
+    pub inactivity: Option<InactivityConfig>,
+    #[serde(default)]
+    pub pagure: PagureConfig,
+    #[serde(default)]
+    pub email: EmailConfig,
+    #[serde(default)]
+    pub critpath: Option<CritPathConfig>,
+    #[serde(default)]
+    pub urls: UrlConfig,
+    #[serde(default)]
+    pub signing: Option<SigningConfig>,
+    #[serde(default)]
+    pub publish: Option<PublishConfig>,
+    #[serde(default)]
+    pub filter: FilterConfig,
+    #[serde(default)]
+    pub admin: Option<AdminConfig>,
+    #[serde(default)]
+    pub mirrors: MirrorConfig,
+    #[serde(default)]
+    pub templates: Option<TemplateConfig>,
+    #[serde(default)]
+    pub branding: BrandingConfig,
+    #[serde(default)]
+    pub alerts: AlertConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub attribution: AttributionConfig,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct RepoCheckerConfig {
     pub interval: f64,
+    #[serde(default = "default_min_free_space")]
+    pub min_free_space: f64,
+    #[serde(default)]
+    pub retired_data: RetiredData,
+    // refresh interval (in hours) for package maintainers and user activity, independent of repository checks
+    #[serde(default)]
+    pub account_interval: Option<f64>,
+    // run time (in minutes) of checks for a single release after which a warning is logged
+    #[serde(default)]
+    pub run_time_warning: Option<f64>,
+    // refresh intervals that adapt to how often the data of each release changes (instead of a fixed interval)
+    #[serde(default)]
+    pub adaptive: Option<AdaptiveConfig>,
+    // maximum duration (in hours) of a refresh cycle, after which releases that are still being checked are cancelled
+    // and carried over to the next cycle
+    #[serde(default)]
+    pub max_cycle_duration: Option<f64>,
+    // number of threads for checking releases, separate from the threads that handle HTTP requests
+    #[serde(default = "default_worker_threads")]
+    pub worker_threads: usize,
+    // number of archived releases whose data is kept in memory, and time (in minutes) after which the data of an
+    // archived release that was not requested is dropped from memory again
+    #[serde(default = "default_archived_cache_size")]
+    pub archived_cache_size: usize,
+    #[serde(default = "default_archived_idle_time")]
+    pub archived_idle_time: f64,
+    // number of previous generations of the data of each release that are kept on disk
+    #[serde(default)]
+    pub history_size: usize,
+    // number of consecutive runs in which a package must be broken before it is published
+    #[serde(default)]
+    pub confirm_runs: Option<usize>,
+    // sanity checks of new results before they replace the data of a release
+    #[serde(default)]
+    pub validation: ValidationConfig,
+}
+
+/// Settings for adapting the refresh interval of each release: it is reset to the minimum when the data of a release
+/// changes, and doubled (up to the maximum) every time it does not change.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct AdaptiveConfig {
+    // shortest and longest refresh interval (in hours)
+    pub min_interval: f64,
+    pub max_interval: f64,
+}
+
+impl AdaptiveConfig {
+    /// Returns the refresh interval (in hours) after a run, based on the previous interval and whether the data changed.
+    pub fn next_interval(&self, previous: Option<f64>, changed: bool) -> f64 {
+        let interval = match previous {
+            Some(previous) if !changed => previous * 2.0,
+            _ => self.min_interval,
+        };
+
+        interval.min(self.max_interval).max(self.min_interval)
+    }
+}
+
+/// Settings for rejecting implausible results, which are usually caused by repositories that could not be downloaded
+/// from a broken mirror, instead of replacing the data of a release with them.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct ValidationConfig {
+    // largest fraction of broken packages that can disappear in a single run (1.0 disables this check)
+    #[serde(default = "default_max_drop")]
+    pub max_drop: f64,
+    // number of broken packages below which results are never rejected
+    #[serde(default = "default_min_previous")]
+    pub min_previous: usize,
+    // whether results are only rejected if dnf reported download or cache errors during the run
+    #[serde(default = "default_require_dnf_errors")]
+    pub require_dnf_errors: bool,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            max_drop: default_max_drop(),
+            min_previous: default_min_previous(),
+            require_dnf_errors: default_require_dnf_errors(),
+        }
+    }
+}
+
+fn default_max_drop() -> f64 {
+    0.9
+}
+
+fn default_min_previous() -> usize {
+    100
+}
+
+fn default_require_dnf_errors() -> bool {
+    true
+}
+
+impl ValidationConfig {
+    /// Checks whether the number of broken packages of a new result is plausible compared to the previous result.
+    pub fn validate(&self, previous: usize, current: usize, dnf_errors: usize) -> Result<(), String> {
+        if previous < self.min_previous || current >= previous {
+            return Ok(());
+        }
+
+        if self.require_dnf_errors && dnf_errors == 0 {
+            return Ok(());
+        }
+
+        let drop = (previous - current) as f64 / previous as f64;
+        if drop > self.max_drop {
+            return Err(format!(
+                "Number of broken packages dropped from {} to {} ({} dnf errors were reported)",
+                previous, current, dnf_errors
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Settings for the HTTP client that is used for all requests to external services.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct HttpConfig {
+    // time (in seconds) after which establishing a connection is aborted
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: f64,
+    // proxy for all requests (for example, "http://proxy.example.com:3128")
+    #[serde(default)]
+    pub proxy: Option<String>,
+    // User-Agent header (by default, based on the name and contact information of the instance)
+    #[serde(default)]
+    pub user_agent: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout: default_connect_timeout(),
+            proxy: None,
+            user_agent: None,
+        }
+    }
+}
+
+fn default_connect_timeout() -> f64 {
+    10.0
+}
+
+/// Settings for flagging broken packages whose main admin has not been active recently.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct InactivityConfig {
+    // number of days without any activity after which a user is considered to be inactive
+    pub threshold: f64,
+    #[serde(default = "default_activity_url")]
+    pub url: String,
+}
+
+fn default_activity_url() -> String {
+    String::from("https://apps.fedoraproject.org/datagrepper/raw")
+}
+
+/// Settings for looking up package maintainers.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct PagureConfig {
+    // whether the Pagure API is used if the "/extras/" JSON dumps are unavailable or stale
+    #[serde(default)]
+    pub api_fallback: bool,
+    // number of days after which the "/extras/" JSON dumps are considered to be stale
+    #[serde(default = "default_pagure_max_age")]
+    pub max_age: f64,
+    #[serde(default = "default_pagure_api_url")]
+    pub api_url: String,
+    // minimum number of packages that package maintainer data must contain to be used
+    #[serde(default = "default_pagure_min_packages")]
+    pub min_packages: usize,
+    // maximum fraction of packages that can disappear between two versions of the package maintainer dumps
+    #[serde(default = "default_pagure_max_shrink")]
+    pub max_shrink: f64,
+}
+
+impl Default for PagureConfig {
+    fn default() -> Self {
+        PagureConfig {
+            api_fallback: false,
+            max_age: default_pagure_max_age(),
+            api_url: default_pagure_api_url(),
+            min_packages: default_pagure_min_packages(),
+            max_shrink: default_pagure_max_shrink(),
+        }
+    }
+}
+
+fn default_pagure_max_age() -> f64 {
+    3.0
+}
+
+fn default_pagure_api_url() -> String {
+    String::from("https://src.fedoraproject.org/api/0/projects")
+}
+
+fn default_pagure_min_packages() -> usize {
+    1000
+}
+
+fn default_pagure_max_shrink() -> f64 {
+    0.9
+}
+
+/// Settings for attributing broken packages to their co-maintainers (in addition to their main admin) in every view.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct AttributionConfig {
+    // whether co-maintainers are listed in the tables of broken packages on HTML pages
+    #[serde(default = "default_attribute_co_maintainers")]
+    pub html: bool,
+    // whether maintainer feeds of co-maintainers include packages that they are not the main admin of
+    #[serde(default = "default_attribute_co_maintainers")]
+    pub feeds: bool,
+}
+
+impl Default for AttributionConfig {
+    fn default() -> Self {
+        AttributionConfig {
+            html: default_attribute_co_maintainers(),
+            feeds: default_attribute_co_maintainers(),
+        }
+    }
+}
+
+fn default_attribute_co_maintainers() -> bool {
+    true
+}
+
+/// Settings for resolving user names to email addresses.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct EmailConfig {
+    // domain of the email aliases that are used for users without an override
+    #[serde(default = "default_email_domain")]
+    pub domain: String,
+    // email addresses of users for which the default alias does not work
+    #[serde(default)]
+    pub overrides: BTreeMap<String, String>,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        EmailConfig {
+            domain: default_email_domain(),
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_email_domain() -> String {
+    String::from("fedoraproject.org")
+}
+
+impl EmailConfig {
+    /// Returns the email address of a user, either from the configured overrides, or the default alias.
+    pub fn address(&self, user: &str) -> String {
+        match self.overrides.get(user) {
+            Some(address) => address.clone(),
+            None => format!("{}@{}", user, self.domain),
+        }
+    }
+}
+
+/// Settings for marking broken packages that are part of the critical path.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct CritPathConfig {
+    // path or URL of a JSON file with a list of source packages, or with lists of source packages per release
+    pub source: String,
+}
+
+/// Settings for creating detached signatures of the persisted data of every release.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct SigningConfig {
+    // ID or fingerprint of the GnuPG key that is used for signing
+    pub key: String,
+    // GnuPG home directory that contains the key (defaults to the home directory of the current user)
+    #[serde(default)]
+    pub homedir: Option<String>,
+}
+
+/// Settings for the administrative endpoints under `/admin/`, which are disabled if this is not present.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct AdminConfig {
+    // file that contains the bearer token that requests to administrative endpoints must be authenticated with
+    pub token_file: String,
+    // maximum size (in MiB) of request bodies with snapshots or imported data (which is read when the service starts)
+    #[serde(default = "default_max_body_size")]
+    pub max_body_size: f64,
+}
+
+fn default_max_body_size() -> f64 {
+    512.0
+}
+
+impl AdminConfig {
+    /// Returns the maximum size of request bodies with snapshots or imported data in bytes.
+    pub fn max_body_bytes(admin: Option<&AdminConfig>) -> usize {
+        let size = admin
+            .map(|admin| admin.max_body_size)
+            .unwrap_or_else(default_max_body_size);
+        (size * 1024.0 * 1024.0) as usize
+    }
+
+    /// Reads the token from disk, so it can be rotated without restarting the service.
+    pub fn token(&self) -> Result<String, String> {
+        match read_to_string(&self.token_file) {
+            Ok(token) if !token.trim().is_empty() => Ok(token.trim().to_string()),
+            Ok(_) => Err(format!("Token file is empty: {}", &self.token_file)),
+            Err(error) => Err(format!("Failed to read token file {}: {}", &self.token_file, error)),
+        }
+    }
+}
+
+/// Settings for uploading generated data to an S3-compatible bucket after every run.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct PublishConfig {
+    pub bucket: String,
+    // prefix for all object keys (for example, "repochecker/")
+    #[serde(default)]
+    pub prefix: String,
+    // endpoint URL for S3-compatible services other than AWS
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+/// Settings for restricting which broken packages are reported (for example, for team-scoped deployments).
+///
+/// Packages are matched by binary or source package name, and maintainers are matched against the main admin and all
+/// maintainers of a package. If any `include_*` settings are non-empty, only packages that match at least one of them
+/// are reported. Packages that match any `exclude_*` settings are never reported.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+pub struct FilterConfig {
+    #[serde(default)]
+    pub include_maintainers: Vec<String>,
+    #[serde(default)]
+    pub include_packages: Vec<String>,
+    #[serde(default)]
+    pub exclude_maintainers: Vec<String>,
+    #[serde(default)]
+    pub exclude_packages: Vec<String>,
+}
+
+/// Settings for rendering HTML pages from templates that are loaded at runtime, instead of the built-in ones.
+///
+/// Templates are read from disk whenever a page is rendered, so they can be changed without restarting the service.
+/// Pages for which the directory does not contain a template are rendered with the built-in templates.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct TemplateConfig {
+    pub directory: String,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Settings for the name and appearance of this instance, which are shown on all HTML pages.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct BrandingConfig {
+    #[serde(default = "default_instance_name")]
+    pub name: String,
+    // name of the distribution that releases which are not part of a product belong to
+    #[serde(default = "default_distribution")]
+    pub distribution: String,
+    #[serde(default)]
+    pub logo_url: Option<String>,
+    // contact information (for example, an email address or chat channel) for questions about this instance
+    #[serde(default)]
+    pub contact: Option<String>,
+    #[serde(default, rename = "footer_link", skip_serializing_if = "Vec::is_empty")]
+    pub footer_links: Vec<FooterLink>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct FooterLink {
+    pub title: String,
+    pub url: String,
+}
+
+impl Default for BrandingConfig {
+    fn default() -> Self {
+        BrandingConfig {
+            name: default_instance_name(),
+            distribution: default_distribution(),
+            logo_url: None,
+            contact: None,
+            footer_links: Vec::new(),
+        }
+    }
+}
+
+fn default_instance_name() -> String {
+    String::from("repochecker")
+}
+
+fn default_distribution() -> String {
+    String::from("Fedora")
+}
+
+/// Rules for raising alerts based on the results of checking a release.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+pub struct AlertConfig {
+    #[serde(default, rename = "rule", skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<AlertRule>,
+}
+
+/// Rule that raises an alert if any of its conditions are met for one of its releases.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct AlertRule {
+    pub name: String,
+    // keys of the releases the rule applies to (all releases if empty)
+    #[serde(default)]
+    pub releases: Vec<String>,
+    // maximum increase of the number of broken packages from one run to the next
+    #[serde(default)]
+    pub max_increase: Option<usize>,
+    // maximum number of broken packages
+    #[serde(default)]
+    pub max_broken: Option<usize>,
+    // whether any broken package that is part of the critical path raises an alert
+    #[serde(default)]
+    pub critpath: bool,
+}
+
+impl AlertConfig {
+    fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            if rule.max_increase.is_none() && rule.max_broken.is_none() && !rule.critpath {
+                return Err(format!("Alert rule {} has no conditions.", rule.name));
+            }
+
+            if self.rules.iter().filter(|other| other.name == rule.name).count() > 1 {
+                return Err(format!("Alert rule {} is defined more than once.", rule.name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Settings for downloading repository metadata from specific mirrors instead of the ones selected by dnf.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+pub struct MirrorConfig {
+    // base URL of the preferred mirror, which repositories with a `path` are downloaded from
+    #[serde(default)]
+    pub preferred: Option<String>,
+    #[serde(default)]
+    pub repos: BTreeMap<String, RepoMirror>,
+}
+
+/// Source of the metadata of a repository (exactly one of these must be set).
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+pub struct RepoMirror {
+    #[serde(default)]
+    pub baseurl: Option<String>,
+    #[serde(default)]
+    pub metalink: Option<String>,
+    // path of the repository relative to the preferred mirror
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+impl MirrorConfig {
+    /// Returns the options that are passed to dnf for overriding the mirrors of repositories.
+    pub fn dnf_options(&self) -> Result<Vec<String>, String> {
+        let mut options = Vec::new();
+
+        for (repo, mirror) in &self.repos {
+            let baseurl = match (&mirror.baseurl, &mirror.metalink, &mirror.path) {
+                (Some(baseurl), None, None) => baseurl.clone(),
+                (None, Some(metalink), None) => {
+                    options.push(format!("--setopt={}.metalink={}", repo, metalink));
+                    continue;
+                },
+                (None, None, Some(path)) => match &self.preferred {
+                    Some(preferred) => format!("{}/{}", preferred.trim_end_matches('/'), path.trim_start_matches('/')),
+                    None => {
+                        return Err(format!(
+                            "Mirror path is set for {}, but no preferred mirror is set.",
+                            repo
+                        ))
+                    },
+                },
+                _ => {
+                    return Err(format!(
+                        "Exactly one of baseurl, metalink, or path must be set for the mirror of {}.",
+                        repo
+                    ))
+                },
+            };
+
+            options.extend(baseurl_options(repo, &baseurl));
+        }
+
+        Ok(options)
+    }
+}
+
+/// Returns the options that are passed to dnf for downloading a repository from a fixed base URL.
+fn baseurl_options(repo: &str, baseurl: &str) -> [String; 3] {
+    // a fixed base URL replaces any metalink or mirrorlist from the repository definition
+    [
+        format!("--setopt={}.baseurl={}", repo, baseurl),
+        format!("--setopt={}.metalink=", repo),
+        format!("--setopt={}.mirrorlist=", repo),
+    ]
+}
+
+// variants of ELN composes, which are matched against the last part of repository names (e.g. "eln-baseos")
+const COMPOSE_VARIANTS: &[&str] = &["BaseOS", "AppStream", "CRB"];
+
+/// Returns the location of a repository in a compose, or none if the repository is not part of a compose.
+///
+/// Source repositories are recognized by their "-source" suffix (e.g. "eln-baseos-source").
+pub fn compose_baseurl(compose: &str, repo: &str) -> Option<String> {
+    let (name, source) = match repo.strip_suffix("-source") {
+        Some(name) => (name, true),
+        None => (repo, false),
+    };
+
+    let suffix = name.rsplit('-').next().unwrap_or(name);
+    let variant = COMPOSE_VARIANTS
+        .iter()
+        .find(|variant| variant.eq_ignore_ascii_case(suffix))?;
+
+    let compose = compose.trim_end_matches('/');
+    if source {
+        Some(format!("{}/{}/source/tree/", compose, variant))
+    } else {
+        Some(format!("{}/{}/$basearch/os/", compose, variant))
+    }
+}
+
+impl FilterConfig {
+    fn has_maintainer(item: &BrokenItem, maintainers: &[String]) -> bool {
+        maintainers
+            .iter()
+            .any(|m| &item.admin == m || item.maintainers.contains(m))
+    }
+
+    fn has_package(item: &BrokenItem, packages: &[String]) -> bool {
+        packages.iter().any(|p| &item.package == p || &item.source == p)
+    }
+
+    /// Checks whether a broken item is reported with the current filter settings.
+    pub fn matches(&self, item: &BrokenItem) -> bool {
+        if Self::has_maintainer(item, &self.exclude_maintainers) || Self::has_package(item, &self.exclude_packages) {
+            return false;
+        }
+
+        if self.include_maintainers.is_empty() && self.include_packages.is_empty() {
+            return true;
+        }
+
+        Self::has_maintainer(item, &self.include_maintainers) || Self::has_package(item, &self.include_packages)
+    }
+}
+
+/// Templates for links to external services that are included with every broken item.
+///
+/// The placeholders `{source}`, `{package}`, `{epoch}`, `{version}`, `{release}`, and `{arch}` are replaced with the
+/// values of the broken item.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct UrlConfig {
+    #[serde(default = "default_distgit_url")]
+    pub distgit: String,
+    #[serde(default = "default_koji_url")]
+    pub koji: String,
+    #[serde(default = "default_bodhi_url")]
+    pub bodhi: String,
+}
+
+impl Default for UrlConfig {
+    fn default() -> Self {
+        UrlConfig {
+            distgit: default_distgit_url(),
+            koji: default_koji_url(),
+            bodhi: default_bodhi_url(),
+        }
+    }
+}
+
+fn default_distgit_url() -> String {
+    String::from("https://src.fedoraproject.org/rpms/{source}")
+}
+
+fn default_koji_url() -> String {
+    String::from("https://koji.fedoraproject.org/koji/search?type=build&match=exact&terms={source}-{version}-{release}")
+}
+
+fn default_bodhi_url() -> String {
+    String::from("https://bodhi.fedoraproject.org/updates/?packages={source}")
+}
+
+impl UrlConfig {
+    fn render(template: &str, item: &BrokenItem) -> String {
+        template
+            .replace("{source}", &item.source)
+            .replace("{package}", &item.package)
+            .replace("{epoch}", &item.epoch)
+            .replace("{version}", &item.version)
+            .replace("{release}", &item.release)
+            .replace("{arch}", &item.arch)
+    }
+
+    /// Fills in the links to dist-git, koji, and bodhi for a broken item.
+    pub fn apply(&self, item: &mut BrokenItem) {
+        item.distgit_url = Some(Self::render(&self.distgit, item));
+        item.koji_url = Some(Self::render(&self.koji, item));
+        item.bodhi_url = Some(Self::render(&self.bodhi, item));
+    }
+}
+
+/// What happens to data files of releases that were removed from the configuration.
+#[derive(Clone, Copy, Debug, Default, Deserialize, JsonSchema, PartialEq, Eq, Serialize)]
+pub enum RetiredData {
+    #[default]
+    #[serde(rename = "keep")]
+    Keep,
+    #[serde(rename = "archive")]
+    Archive,
+    #[serde(rename = "delete")]
+    Delete,
+}
+
+fn default_min_free_space() -> f64 {
+    5.0
+}
+
+fn default_worker_threads() -> usize {
+    4
+}
+
+fn default_archived_cache_size() -> usize {
+    4
+}
+
+fn default_archived_idle_time() -> f64 {
+    60.0
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct RepoConfig {
+    #[serde(default)]
     pub stable: Vec<String>,
+    #[serde(default)]
     pub updates: Vec<String>,
+    #[serde(default)]
     pub testing: Vec<String>,
+    #[serde(default)]
     pub rawhide: Vec<String>,
+    #[serde(default = "default_eln_repos")]
+    pub eln: Vec<String>,
+}
+
+fn default_eln_repos() -> Vec<String> {
+    ["eln-baseos", "eln-appstream", "eln-crb", "eln-extras"]
+        .into_iter()
+        .flat_map(|repo| [repo.to_string(), format!("{}-source", repo)])
+        .collect()
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Static list of architectures whose packages are considered to be installable on an architecture.
+///
+/// These are no longer supported (multilib is configured per release instead), and are only parsed to reject
+/// configuration files that still contain them, instead of silently ignoring their contents.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct ArchConfig {
     pub name: String,
     pub multiarch: Vec<String>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Secondary architecture whose packages can be installed on a primary architecture (multilib).
+struct CompatArch {
+    primary: &'static str,
+    secondary: &'static str,
+    // whether this is enabled for releases that do not configure multilib explicitly
+    default: bool,
+    // whether packages of the secondary architecture are only published in the repositories of the primary one (so
+    // they can not be checked as an architecture of their own)
+    content_only: bool,
+}
+
+const COMPAT_ARCHES: [CompatArch; 2] = [
+    CompatArch {
+        primary: "x86_64",
+        secondary: "i686",
+        default: true,
+        content_only: true,
+    },
+    CompatArch {
+        primary: "aarch64",
+        secondary: "armv7hl",
+        default: false,
+        content_only: false,
+    },
+];
+
+/// Returns the primary architecture of a secondary content architecture (like `x86_64` for `i686`), which packages of
+/// the secondary architecture are checked as part of.
+pub fn content_primary(arch: &str) -> Option<&'static str> {
+    COMPAT_ARCHES
+        .iter()
+        .find(|compat| compat.secondary == arch && compat.content_only)
+        .map(|compat| compat.primary)
+}
+
+// architectures that can be passed to dnf with --forcearch
+const KNOWN_ARCHES: [&str; 7] = ["x86_64", "i686", "aarch64", "armv7hl", "ppc64le", "s390x", "riscv64"];
+
+/// Returns the architectures whose packages are considered to be installable on an architecture: the architecture
+/// itself, `noarch`, `src`, and the enabled compatible architectures (or the ones that are enabled by default).
+///
+/// Secondary content architectures (like `i686` on Fedora) are rejected, because their packages are only available as
+/// multilib packages in the repositories of the primary architecture, and are checked as part of it instead (see
+/// [`content_primary`]).
+pub fn multi_arch(arch: &str, multilib: Option<&[String]>) -> Result<Vec<String>, String> {
+    if !KNOWN_ARCHES.contains(&arch) {
+        return Err(format!("Unknown architecture: {}", arch));
+    }
+
+    if let Some(primary) = content_primary(arch) {
+        return Err(format!(
+            "{} is not a primary architecture, its packages are checked as part of {} (multilib).",
+            arch, primary
+        ));
+    }
+
+    let mut multi_arch = vec![arch.to_string(), String::from("noarch"), String::from("src")];
+
+    for compat in COMPAT_ARCHES.iter().filter(|compat| compat.primary == arch) {
+        let enabled = match multilib {
+            Some(multilib) => multilib.iter().any(|secondary| secondary == compat.secondary),
+            None => compat.default,
+        };
+
+        if enabled {
+            multi_arch.push(compat.secondary.to_string());
+        }
+    }
+
+    Ok(multi_arch)
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct ReleaseConfig {
     pub name: String,
     #[serde(rename = "type")]
     pub rtype: ReleaseType,
     pub arches: Vec<String>,
     pub archived: bool,
+    // whether source packages that do not produce binaries on an architecture are skipped there
+    #[serde(default = "default_detect_exclude_arch")]
+    pub detect_exclude_arch: bool,
+    // repositories that are checked instead of the defaults for the release type (for stable releases, this only
+    // applies to the data without updates-testing)
+    #[serde(default)]
+    pub check: Option<Vec<String>>,
+    // globs of packages that are hidden from dnf (excludepkgs), or that are the only visible packages (includepkgs)
+    #[serde(default)]
+    pub excludepkgs: Vec<String>,
+    #[serde(default)]
+    pub includepkgs: Vec<String>,
+    // packages that must always be installable (for example, anaconda, kernel, or dnf)
+    #[serde(default)]
+    pub protected: Vec<String>,
+    // comps groups that must always be installable (for example, workstation-product)
+    #[serde(default)]
+    pub protected_groups: Vec<String>,
+    // how packages that are part of a module stream are handled (modules are not detected if this is not set)
+    #[serde(default)]
+    pub modular: Option<ModularMode>,
+    // compatible architectures whose packages are installable on the primary architectures of this release (for
+    // example, "i686" on "x86_64"); this defaults to ["i686"], and an empty list disables multilib
+    #[serde(default)]
+    pub multilib: Option<Vec<String>>,
+    // architectures on which updates-testing is checked for stable releases (all architectures if this is not set,
+    // and none if it is empty)
+    #[serde(default)]
+    pub testing_arches: Option<Vec<String>>,
+    // whether data for this release is imported from a checker that runs elsewhere, instead of being checked here
+    #[serde(default)]
+    pub external: bool,
+    // location of the compose that the repositories of an ELN release are downloaded from, instead of their
+    // definitions on the system (for example, the latest nightly compose)
+    #[serde(default)]
+    pub compose: Option<String>,
+}
+
+fn default_detect_exclude_arch() -> bool {
+    true
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Independent set of repositories and releases that is checked in addition to the top-level (Fedora) releases,
+/// with data served under `/products/:product/data/:release`.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct ProductConfig {
+    pub name: String,
+    pub overrides: String,
+    pub repos: RepoConfig,
+    #[serde(rename = "release")]
+    pub releases: Vec<ReleaseConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub enum ReleaseType {
     #[serde(rename = "rawhide")]
     Rawhide,
@@ -53,6 +864,39 @@ pub enum ReleaseType {
     PreRelease,
     #[serde(rename = "stable")]
     Stable,
+    #[serde(rename = "eln")]
+    Eln,
+}
+
+/// Orders releases from oldest to newest.
+///
+/// Rawhide is always the newest release. Other releases are ordered by their version (for example, "9" < "9.4" < "10"),
+/// and releases whose names are not versions are ordered by name, after all releases with versions.
+pub fn compare_releases(a: &ReleaseConfig, b: &ReleaseConfig) -> std::cmp::Ordering {
+    let version = |release: &ReleaseConfig| -> Option<Vec<u64>> {
+        release.name.split('.').map(|part| part.parse::<u64>().ok()).collect()
+    };
+
+    let rank = |release: &ReleaseConfig| match (&release.rtype, version(release)) {
+        (ReleaseType::Rawhide, _) => 2,
+        (_, None) => 1,
+        (_, Some(_)) => 0,
+    };
+
+    rank(a)
+        .cmp(&rank(b))
+        .then_with(|| version(a).cmp(&version(b)))
+        .then_with(|| a.name.cmp(&b.name))
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+pub enum ModularMode {
+    // broken modular packages are reported with the module stream they belong to
+    #[serde(rename = "annotate")]
+    Annotate,
+    // broken modular packages are not reported at all
+    #[serde(rename = "exclude")]
+    Exclude,
 }
 
 fn get_config_path() -> Result<Box<Path>, String> {
@@ -106,27 +950,187 @@ pub fn get_config() -> Result<Config, String> {
         Err(error) => return Err(error.to_string()),
     };
 
+    config.validate()?;
     Ok(config)
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Serialize)]
 pub struct MatrixEntry {
+    pub product: Option<String>,
     pub release: String,
     pub arches: Vec<Arch>,
     pub repos: Vec<String>,
     pub check: Vec<String>,
     pub with_testing: bool,
     pub archived: bool,
+    pub external: bool,
+    pub detect_exclude_arch: bool,
+    pub package_globs: PackageGlobs,
+    pub protected: Vec<String>,
+    pub protected_groups: Vec<String>,
+    pub modular: Option<ModularMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compose: Option<String>,
 }
 
-#[derive(Clone, Debug)]
+/// Package globs that are passed to dnf as `excludepkgs` and `includepkgs` settings.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PackageGlobs {
+    pub exclude: Vec<String>,
+    pub include: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct Arch {
     pub name: String,
     pub multi_arch: Vec<String>,
+    // secondary content architectures that are checked as part of this architecture (like `i686` on `x86_64`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub content_arches: Vec<String>,
+}
+
+/// Differences between the matrix entries of two configurations, identified by their keys.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct MatrixDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    // entries that are present in both configurations, but are checked differently
+    pub changed: Vec<String>,
+}
+
+/// Compares the matrix entries of the current configuration with the entries of a new one.
+pub fn diff_matrix(old: &[MatrixEntry], new: &[MatrixEntry]) -> MatrixDiff {
+    let mut diff = MatrixDiff::default();
+
+    for entry in new {
+        match old.iter().find(|old| old.key() == entry.key()) {
+            Some(old) if old != entry => diff.changed.push(entry.key()),
+            Some(_) => {},
+            None => diff.added.push(entry.key()),
+        }
+    }
+
+    for entry in old {
+        if !new.iter().any(|new| new.key() == entry.key()) {
+            diff.removed.push(entry.key());
+        }
+    }
+
+    diff
+}
+
+/// Returns the name under which data for a release is stored and served, which is prefixed with the
+/// product name for releases that do not belong to the top-level product.
+pub fn release_key(product: Option<&str>, release: &str) -> String {
+    match product {
+        Some(product) => format!("{}/{}", product, release),
+        None => release.to_string(),
+    }
+}
+
+impl MatrixEntry {
+    /// Name under which data for this matrix entry is stored and served (e.g. "41-testing" or "epel/9").
+    pub fn key(&self) -> String {
+        let suffix = if !self.with_testing { "" } else { "-testing" };
+        format!("{}{}", release_key(self.product.as_deref(), &self.release), suffix)
+    }
+
+    /// Returns the architecture that packages of the given architecture are checked as part of: the architecture
+    /// itself, or the primary architecture of a secondary content architecture of this release.
+    pub fn checked_arch(&self, arch: &str) -> Option<&Arch> {
+        self.arches
+            .iter()
+            .find(|other| other.name == arch || other.content_arches.iter().any(|content| content == arch))
+    }
+
+    /// Returns the options that are passed to dnf for downloading the repositories of this matrix entry from its
+    /// compose (if any), instead of the locations from their definitions on the system.
+    pub fn compose_options(&self) -> Vec<String> {
+        let compose = match &self.compose {
+            Some(compose) => compose,
+            None => return Vec::new(),
+        };
+
+        self.repos
+            .iter()
+            .filter_map(|repo| compose_baseurl(compose, repo).map(|baseurl| baseurl_options(repo, &baseurl)))
+            .flatten()
+            .collect()
+    }
+
+    /// Name of the directory that holds dnf caches for this matrix entry (e.g. "41-testing" or "epel-9").
+    ///
+    /// Data with and without updates-testing is checked against different sets of repositories, so they do not share
+    /// their caches.
+    pub fn cache_name(&self) -> String {
+        let suffix = if !self.with_testing { "" } else { "-testing" };
+        match &self.product {
+            Some(product) => format!("{}-{}{}", product, self.release, suffix),
+            None => format!("{}{}", self.release, suffix),
+        }
+    }
 }
 
 impl Config {
+    /// Checks that the configuration does not contain settings that are no longer supported, and that its mirrors,
+    /// alerts, and releases are valid.
+    ///
+    /// Configuration files are validated when they are loaded (see [`get_config`]), so an invalid configuration is
+    /// never applied.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.arches.is_empty() {
+            return Err(String::from(
+                "[[arch]] sections are no longer supported, use the multilib setting of releases instead.",
+            ));
+        }
+
+        self.mirrors.dnf_options()?;
+        self.alerts.validate()?;
+        self.to_matrix()?;
+
+        Ok(())
+    }
+
     pub fn to_matrix(&self) -> Result<Vec<MatrixEntry>, String> {
+        let mut matrix = self.product_matrix(None, &self.repos, &self.releases)?;
+
+        for product in &self.products {
+            matrix.extend(self.product_matrix(Some(&product.name), &product.repos, &product.releases)?);
+        }
+
+        Ok(matrix)
+    }
+
+    /// Returns the keys of the top-level releases and of the releases of each additional product, sorted from newest
+    /// to oldest (see [`compare_releases`]), excluding ELN and archived releases.
+    pub fn release_series(&self) -> Vec<Vec<String>> {
+        let series = |product: Option<&str>, releases: &[ReleaseConfig]| -> Vec<String> {
+            let mut releases: Vec<&ReleaseConfig> = releases
+                .iter()
+                .filter(|release| !release.archived && !matches!(release.rtype, ReleaseType::Eln))
+                .collect();
+            releases.sort_by(|a, b| compare_releases(b, a));
+
+            releases
+                .into_iter()
+                .map(|release| release_key(product, &release.name))
+                .collect()
+        };
+
+        let mut output = vec![series(None, &self.releases)];
+        for product in &self.products {
+            output.push(series(Some(&product.name), &product.releases));
+        }
+
+        output
+    }
+
+    fn product_matrix(
+        &self,
+        product: Option<&str>,
+        repo_config: &RepoConfig,
+        releases: &[ReleaseConfig],
+    ) -> Result<Vec<MatrixEntry>, String> {
         let mut matrix: Vec<MatrixEntry> = Vec::new();
 
         #[derive(Debug)]
@@ -136,27 +1140,32 @@ impl Config {
             with_testing: bool,
         }
 
-        for release in &self.releases {
-            let repos = match &release.rtype {
+        for release in releases {
+            let mut repos = match &release.rtype {
                 ReleaseType::Rawhide => vec![Repos {
-                    repos: self.repos.rawhide.clone(),
-                    check: self.repos.rawhide.clone(),
+                    repos: repo_config.rawhide.clone(),
+                    check: repo_config.rawhide.clone(),
+                    with_testing: false,
+                }],
+                ReleaseType::Eln => vec![Repos {
+                    repos: repo_config.eln.clone(),
+                    check: repo_config.eln.clone(),
                     with_testing: false,
                 }],
                 ReleaseType::PreRelease => vec![Repos {
-                    repos: self.repos.stable.clone(),
-                    check: self.repos.stable.clone(),
+                    repos: repo_config.stable.clone(),
+                    check: repo_config.stable.clone(),
                     with_testing: false,
                 }],
                 ReleaseType::Stable => {
                     let mut stable_repos = Vec::new();
-                    stable_repos.extend(self.repos.stable.clone());
-                    stable_repos.extend(self.repos.updates.clone());
+                    stable_repos.extend(repo_config.stable.clone());
+                    stable_repos.extend(repo_config.updates.clone());
 
                     let mut testing_repos = Vec::new();
-                    testing_repos.extend(self.repos.stable.clone());
-                    testing_repos.extend(self.repos.updates.clone());
-                    testing_repos.extend(self.repos.testing.clone());
+                    testing_repos.extend(repo_config.stable.clone());
+                    testing_repos.extend(repo_config.updates.clone());
+                    testing_repos.extend(repo_config.testing.clone());
 
                     vec![
                         Repos {
@@ -166,30 +1175,98 @@ impl Config {
                         },
                         Repos {
                             repos: testing_repos,
-                            check: self.repos.testing.clone(),
+                            check: repo_config.testing.clone(),
                             with_testing: true,
                         },
                     ]
                 },
             };
 
-            let mut arches: Vec<Arch> = Vec::new();
+            if let Some(check) = &release.check {
+                let primary = &mut repos[0];
 
-            for arch in &release.arches {
-                let mut multi_arch: Option<Vec<String>> = None;
+                for repo in check {
+                    if !primary.repos.contains(repo) {
+                        return Err(format!(
+                            "Repository {} is checked for {}, but is not enabled for it.",
+                            repo,
+                            release_key(product, &release.name)
+                        ));
+                    }
+                }
 
-                for arch_config in &self.arches {
-                    if &arch_config.name == arch {
-                        multi_arch = Some(arch_config.multiarch.clone());
+                primary.check = check.clone();
+            }
+
+            if let Some(multilib) = &release.multilib {
+                for secondary in multilib {
+                    let compatible = COMPAT_ARCHES.iter().any(|compat| {
+                        compat.secondary == secondary && release.arches.iter().any(|a| a == compat.primary)
+                    });
+
+                    if !compatible {
+                        return Err(format!(
+                            "Multilib architecture {} is not compatible with any architecture of {}.",
+                            secondary,
+                            release_key(product, &release.name)
+                        ));
                     }
                 }
+            }
 
-                let multi_arch = match multi_arch {
-                    Some(values) => values,
-                    None => {
+            if let Some(testing_arches) = &release.testing_arches {
+                if !matches!(release.rtype, ReleaseType::Stable) {
+                    return Err(format!(
+                        "Testing architectures are set for {}, which is not a stable release.",
+                        release_key(product, &release.name)
+                    ));
+                }
+
+                for arch in testing_arches {
+                    if !release.arches.contains(arch) {
+                        return Err(format!(
+                            "Testing architecture {} is not an architecture of {}.",
+                            arch,
+                            release_key(product, &release.name)
+                        ));
+                    }
+                }
+            }
+
+            if let Some(compose) = &release.compose {
+                if !matches!(release.rtype, ReleaseType::Eln) {
+                    return Err(format!(
+                        "A compose is set for {}, which is not an ELN release.",
+                        release_key(product, &release.name)
+                    ));
+                }
+
+                if !repos[0]
+                    .repos
+                    .iter()
+                    .any(|repo| compose_baseurl(compose, repo).is_some())
+                {
+                    return Err(format!(
+                        "None of the repositories of {} are part of its compose.",
+                        release_key(product, &release.name)
+                    ));
+                }
+            }
+
+            let mut arches: Vec<Arch> = Vec::new();
+
+            // secondary content architectures are checked as part of their primary architecture
+            let (content_arches, primary_arches): (Vec<&String>, Vec<&String>) =
+                release.arches.iter().partition(|arch| content_primary(arch).is_some());
+
+            for arch in primary_arches {
+                let multi_arch = match multi_arch(arch, release.multilib.as_deref()) {
+                    Ok(values) => values,
+                    Err(error) => {
                         return Err(format!(
-                            "Could not find multiarch configuration for {}/{}.",
-                            &release.name, &arch
+                            "Invalid architecture for {}: {}",
+                            release_key(product, &release.name),
+                            error
                         ))
                     },
                 };
@@ -197,17 +1274,80 @@ impl Config {
                 arches.push(Arch {
                     name: arch.clone(),
                     multi_arch,
+                    content_arches: Vec::new(),
                 });
             }
 
+            for content_arch in content_arches {
+                let primary = content_primary(content_arch).unwrap_or_default();
+
+                let arch = match arches.iter_mut().find(|arch| arch.name == primary) {
+                    Some(arch) => arch,
+                    None => {
+                        return Err(format!(
+                            "Invalid architecture for {}: {} is checked as part of {}, which is not an architecture of \
+                             this release.",
+                            release_key(product, &release.name),
+                            content_arch,
+                            primary
+                        ))
+                    },
+                };
+
+                if !arch.multi_arch.contains(content_arch) {
+                    return Err(format!(
+                        "Invalid architecture for {}: {} is checked as part of {} (multilib), but multilib is disabled \
+                         for it.",
+                        release_key(product, &release.name),
+                        content_arch,
+                        primary
+                    ));
+                }
+
+                arch.content_arches.push(content_arch.clone());
+            }
+
             for repo in repos {
+                let arches: Vec<Arch> = match &release.testing_arches {
+                    Some(testing_arches) if repo.with_testing => arches
+                        .iter()
+                        .filter(|arch| testing_arches.contains(&arch.name))
+                        .map(|arch| Arch {
+                            content_arches: arch
+                                .content_arches
+                                .iter()
+                                .filter(|content_arch| testing_arches.contains(content_arch))
+                                .cloned()
+                                .collect(),
+                            ..arch.clone()
+                        })
+                        .collect(),
+                    _ => arches.clone(),
+                };
+
+                // updates-testing is not checked at all if it is disabled for all architectures
+                if arches.is_empty() {
+                    continue;
+                }
+
                 matrix.push(MatrixEntry {
+                    product: product.map(String::from),
                     release: release.name.to_string(),
-                    arches: arches.clone(),
+                    arches,
                     repos: repo.repos,
                     check: repo.check,
                     with_testing: repo.with_testing,
                     archived: release.archived,
+                    external: release.external,
+                    detect_exclude_arch: release.detect_exclude_arch,
+                    package_globs: PackageGlobs {
+                        exclude: release.excludepkgs.clone(),
+                        include: release.includepkgs.clone(),
+                    },
+                    protected: release.protected.clone(),
+                    protected_groups: release.protected_groups.clone(),
+                    modular: release.modular,
+                    compose: release.compose.clone(),
                 });
             }
         }
@@ -215,3 +1355,324 @@ impl Config {
         Ok(matrix)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+[repochecker]
+interval = 4
+
+[[release]]
+name = "eln"
+type = "eln"
+arches = ["x86_64"]
+archived = false
+
+[[release]]
+name = "41"
+type = "stable"
+arches = ["x86_64"]
+archived = false
+check = ["updates"]
+
+[repos]
+stable = ["fedora"]
+updates = ["updates"]
+testing = ["updates-testing"]
+rawhide = ["rawhide"]
+
+[[product]]
+name = "epel"
+overrides = "overrides-epel.json"
+
+[product.repos]
+stable = ["epel"]
+
+[[product.release]]
+name = "9"
+type = "prerelease"
+arches = ["x86_64"]
+archived = false
+"#;
+
+    #[test]
+    fn next_interval() {
+        let adaptive = AdaptiveConfig {
+            min_interval: 1.0,
+            max_interval: 6.0,
+        };
+
+        assert_eq!(adaptive.next_interval(None, false), 1.0);
+        assert_eq!(adaptive.next_interval(Some(2.0), false), 4.0);
+        assert_eq!(adaptive.next_interval(Some(4.0), false), 6.0);
+        assert_eq!(adaptive.next_interval(Some(6.0), true), 1.0);
+    }
+
+    #[test]
+    fn validate() {
+        let validation = ValidationConfig::default();
+
+        assert!(validation.validate(5000, 0, 1).is_err());
+        assert!(validation.validate(5000, 0, 0).is_ok());
+        assert!(validation.validate(5000, 4000, 1).is_ok());
+        assert!(validation.validate(50, 0, 1).is_ok());
+    }
+
+    #[test]
+    fn arch_sections() {
+        let mut config: Config = basic_toml::from_str(CONFIG).unwrap();
+        assert!(config.validate().is_ok());
+
+        config.arches.push(ArchConfig {
+            name: String::from("x86_64"),
+            multiarch: vec![String::from("x86_64"), String::from("i686")],
+        });
+
+        // configurations with [[arch]] sections are rejected when they are loaded, not when releases are looked up
+        assert!(config.validate().is_err());
+        assert!(config.to_matrix().is_ok());
+    }
+
+    #[test]
+    fn diff_matrix() {
+        let old: Config = basic_toml::from_str(CONFIG).unwrap();
+
+        let mut new = old.clone();
+        new.releases.remove(0);
+        new.releases[0].arches.push(String::from("aarch64"));
+        new.products[0].releases[0].name = String::from("10");
+
+        let diff = super::diff_matrix(&old.to_matrix().unwrap(), &new.to_matrix().unwrap());
+
+        assert_eq!(diff.added, vec!["epel/10"]);
+        assert_eq!(diff.removed, vec!["eln", "epel/9"]);
+        assert_eq!(diff.changed, vec!["41", "41-testing"]);
+    }
+
+    #[test]
+    fn to_matrix() {
+        let config: Config = basic_toml::from_str(CONFIG).unwrap();
+        let matrix = config.to_matrix().unwrap();
+
+        assert_eq!(matrix.len(), 4);
+
+        assert_eq!(matrix[0].release, "eln");
+        assert_eq!(matrix[0].repos, default_eln_repos());
+        assert!(!matrix[0].with_testing);
+
+        assert_eq!(matrix[1].release, "41");
+        assert_eq!(matrix[1].repos, vec!["fedora", "updates"]);
+        assert_eq!(matrix[1].check, vec!["updates"]);
+        assert!(!matrix[1].with_testing);
+
+        assert_eq!(matrix[2].release, "41");
+        assert_eq!(matrix[2].check, vec!["updates-testing"]);
+        assert!(matrix[2].with_testing);
+        assert_eq!(matrix[2].key(), "41-testing");
+
+        assert_eq!(matrix[3].product.as_deref(), Some("epel"));
+        assert_eq!(matrix[3].check, vec!["epel"]);
+        assert_eq!(matrix[3].key(), "epel/9");
+        assert_eq!(matrix[3].cache_name(), "epel-9");
+        assert_eq!(matrix[1].cache_name(), "41");
+        assert_eq!(matrix[2].cache_name(), "41-testing");
+    }
+
+    #[test]
+    fn testing_arches() {
+        let mut config: Config = basic_toml::from_str(CONFIG).unwrap();
+        config.releases[1].arches = vec![String::from("x86_64"), String::from("s390x")];
+
+        config.releases[1].testing_arches = Some(vec![String::from("x86_64")]);
+        let matrix = config.to_matrix().unwrap();
+        assert_eq!(matrix[1].arches.len(), 2);
+        assert_eq!(matrix[2].key(), "41-testing");
+        assert_eq!(matrix[2].arches.len(), 1);
+        assert_eq!(matrix[2].arches[0].name, "x86_64");
+
+        config.releases[1].testing_arches = Some(Vec::new());
+        let matrix = config.to_matrix().unwrap();
+        assert_eq!(matrix.len(), 3);
+        assert!(matrix.iter().all(|entry| !entry.with_testing));
+
+        config.releases[1].testing_arches = Some(vec![String::from("ppc64le")]);
+        assert!(config.to_matrix().is_err());
+
+        config.releases[0].testing_arches = Some(vec![String::from("x86_64")]);
+        config.releases[1].testing_arches = None;
+        assert!(config.to_matrix().is_err());
+    }
+
+    #[test]
+    fn compose_options() {
+        let compose = "https://odcs.fedoraproject.org/composes/production/latest-Fedora-ELN/compose/";
+        let mut config: Config = basic_toml::from_str(CONFIG).unwrap();
+        config.releases[0].compose = Some(compose.to_string());
+
+        let matrix = config.to_matrix().unwrap();
+        let options = matrix[0].compose_options();
+
+        // eln-extras is not part of the compose, and is downloaded from its usual location
+        assert_eq!(options.len(), 6 * 3);
+        assert!(options.contains(&format!("--setopt=eln-baseos.baseurl={}BaseOS/$basearch/os/", compose)));
+        assert!(options.contains(&format!("--setopt=eln-crb-source.baseurl={}CRB/source/tree/", compose)));
+        assert!(options.contains(&String::from("--setopt=eln-appstream.metalink=")));
+        assert!(!options.iter().any(|option| option.starts_with("--setopt=eln-extras")));
+        assert!(matrix[1].compose_options().is_empty());
+
+        // composes are only supported for ELN
+        config.releases[1].compose = Some(compose.to_string());
+        assert!(config.to_matrix().is_err());
+    }
+
+    #[test]
+    fn mirror_options() {
+        let mut mirrors = MirrorConfig {
+            preferred: Some(String::from("https://mirror.example.com/fedora/")),
+            repos: BTreeMap::new(),
+        };
+        mirrors.repos.insert(
+            String::from("fedora"),
+            RepoMirror {
+                path: Some(String::from("releases/$releasever/Everything/$basearch/os/")),
+                ..Default::default()
+            },
+        );
+        mirrors.repos.insert(
+            String::from("updates"),
+            RepoMirror {
+                metalink: Some(String::from("https://mirrors.example.com/metalink")),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            mirrors.dnf_options().unwrap(),
+            vec![
+                "--setopt=fedora.baseurl=https://mirror.example.com/fedora/releases/$releasever/Everything/$basearch/os/",
+                "--setopt=fedora.metalink=",
+                "--setopt=fedora.mirrorlist=",
+                "--setopt=updates.metalink=https://mirrors.example.com/metalink",
+            ]
+        );
+
+        mirrors.preferred = None;
+        assert!(mirrors.dnf_options().is_err());
+    }
+
+    #[test]
+    fn multi_arch() {
+        assert_eq!(
+            super::multi_arch("x86_64", None).unwrap(),
+            vec!["x86_64", "noarch", "src", "i686"]
+        );
+        assert_eq!(
+            super::multi_arch("x86_64", Some(&[])).unwrap(),
+            vec!["x86_64", "noarch", "src"]
+        );
+        assert_eq!(
+            super::multi_arch("aarch64", None).unwrap(),
+            vec!["aarch64", "noarch", "src"]
+        );
+        assert_eq!(
+            super::multi_arch("aarch64", Some(&[String::from("armv7hl")])).unwrap(),
+            vec!["aarch64", "noarch", "src", "armv7hl"]
+        );
+        assert!(super::multi_arch("mips", None).is_err());
+        assert!(super::multi_arch("i686", None).is_err());
+
+        let mut config: Config = basic_toml::from_str(CONFIG).unwrap();
+        config.releases[0].multilib = Some(vec![String::from("armv7hl")]);
+        assert!(config.to_matrix().is_err());
+    }
+
+    #[test]
+    fn content_arches() {
+        let mut config: Config = basic_toml::from_str(CONFIG).unwrap();
+        config.releases[0].arches = vec![String::from("x86_64"), String::from("i686")];
+
+        // secondary content architectures are checked as part of their primary architecture
+        let matrix = config.to_matrix().unwrap();
+        assert_eq!(matrix[0].arches.len(), 1);
+        assert_eq!(matrix[0].arches[0].content_arches, vec!["i686"]);
+        assert_eq!(matrix[0].checked_arch("i686").unwrap().name, "x86_64");
+        assert!(matrix[0].checked_arch("aarch64").is_none());
+
+        // they can not be checked without their primary architecture, or without multilib
+        config.releases[0].multilib = Some(Vec::new());
+        assert!(config.to_matrix().is_err());
+
+        config.releases[0].multilib = None;
+        config.releases[0].arches = vec![String::from("aarch64"), String::from("i686")];
+        assert!(config.to_matrix().is_err());
+    }
+
+    #[test]
+    fn release_series() {
+        let mut config: Config = basic_toml::from_str(CONFIG).unwrap();
+
+        let release = |name: &str, rtype: ReleaseType, archived: bool| ReleaseConfig {
+            name: name.to_string(),
+            rtype,
+            archived,
+            ..config.releases[1].clone()
+        };
+
+        // releases are sorted by version, regardless of the order in the configuration file
+        config.releases = vec![
+            release("9", ReleaseType::Stable, false),
+            release("40", ReleaseType::Stable, true),
+            release("rawhide", ReleaseType::Rawhide, false),
+            release("eln", ReleaseType::Eln, false),
+            release("41", ReleaseType::Stable, false),
+            release("10", ReleaseType::PreRelease, false),
+        ];
+
+        let series = config.release_series();
+        assert_eq!(series[0], vec!["rawhide", "41", "10", "9"]);
+        assert_eq!(series[1], vec!["epel/9"]);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let config: Config = basic_toml::from_str(CONFIG).unwrap();
+        let serialized = basic_toml::to_string(&config).unwrap();
+        let deserialized: Config = basic_toml::from_str(&serialized).unwrap();
+
+        assert_eq!(deserialized.products.len(), 1);
+        assert_eq!(deserialized.products[0].releases.len(), 1);
+    }
+
+    #[test]
+    fn filter() {
+        let item = |package: &str, admin: &str| BrokenItem {
+            source: package.to_string(),
+            package: format!("{}-libs", package),
+            admin: admin.to_string(),
+            maintainers: vec![admin.to_string(), String::from("bot")],
+            ..Default::default()
+        };
+
+        let filter = FilterConfig::default();
+        assert!(filter.matches(&item("foo", "alice")));
+
+        let filter = FilterConfig {
+            exclude_maintainers: vec![String::from("bot")],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&item("foo", "alice")));
+
+        let filter = FilterConfig {
+            include_maintainers: vec![String::from("alice")],
+            exclude_packages: vec![String::from("bar-libs")],
+            ..Default::default()
+        };
+        assert!(filter.matches(&item("foo", "alice")));
+        assert!(!filter.matches(&item("bar", "alice")));
+        assert!(!filter.matches(&item("baz", "carol")));
+    }
+}
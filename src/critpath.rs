@@ -0,0 +1,111 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Deserialize;
+
+use crate::config::CritPathConfig;
+use crate::data::BrokenItem;
+use crate::http::Download;
+
+/// Source packages that are part of the critical path, either for all releases, or per release.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum CritPath {
+    All(BTreeSet<String>),
+    PerRelease(BTreeMap<String, BTreeSet<String>>),
+}
+
+impl CritPath {
+    /// Checks whether a source package is part of the critical path of a release.
+    pub fn contains(&self, release: &str, source: &str) -> bool {
+        match self {
+            CritPath::All(packages) => packages.contains(source),
+            CritPath::PerRelease(releases) => releases
+                .get(release)
+                .map(|packages| packages.contains(source))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Reads the list of critical path packages from the configured file or URL.
+pub async fn load_critpath(config: &CritPathConfig, timeout: u64) -> Result<CritPath, String> {
+    let contents = if config.source.starts_with("https://") || config.source.starts_with("http://") {
+        let downloaded = Download::new(&config.source, timeout).fetch().await?;
+        String::from_utf8(downloaded.body).map_err(|error| error.to_string())?
+    } else {
+        match std::fs::read_to_string(&config.source) {
+            Ok(string) => string,
+            Err(error) => return Err(format!("{}: {}", &config.source, error)),
+        }
+    };
+
+    match serde_json::from_str(&contents) {
+        Ok(critpath) => Ok(critpath),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Marks broken packages that are part of the critical path of a release.
+///
+/// If the list of critical path packages is not available, the flags are carried over from the previous data of the
+/// release instead.
+pub fn flag_critpath(items: &mut [BrokenItem], release: &str, critpath: Option<&CritPath>, previous: &[BrokenItem]) {
+    match critpath {
+        Some(critpath) => {
+            for item in items.iter_mut() {
+                item.critpath = critpath.contains(release, &item.source);
+            }
+        },
+        None => {
+            let flagged: BTreeSet<&str> = previous
+                .iter()
+                .filter(|item| item.critpath)
+                .map(|item| item.source.as_str())
+                .collect();
+
+            for item in items.iter_mut() {
+                item.critpath = flagged.contains(item.source.as_str());
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn contains() {
+        let all: CritPath = serde_json::from_str(r#"["bash", "dnf"]"#).unwrap();
+        assert!(all.contains("41", "bash"));
+        assert!(!all.contains("41", "foo"));
+
+        let per_release: CritPath = serde_json::from_str(r#"{ "rawhide": ["bash"] }"#).unwrap();
+        assert!(per_release.contains("rawhide", "bash"));
+        assert!(!per_release.contains("41", "bash"));
+    }
+
+    #[test]
+    fn flag_critpath() {
+        let mut items = vec![
+            crate::data::broken_item("bash", &["libfoo.so.1()(64bit)"]),
+            crate::data::broken_item("foo", &["libfoo.so.1()(64bit)"]),
+        ];
+
+        let critpath: CritPath = serde_json::from_str(r#"["bash"]"#).unwrap();
+        super::flag_critpath(&mut items, "41", Some(&critpath), &[]);
+        assert!(items[0].critpath);
+        assert!(!items[1].critpath);
+
+        // previous flags are kept if the list is not available
+        let previous = vec![BrokenItem {
+            critpath: true,
+            ..crate::data::broken_item("foo", &["libfoo.so.1()(64bit)"])
+        }];
+        super::flag_critpath(&mut items, "41", None, &previous);
+        assert!(!items[0].critpath);
+        assert!(items[1].critpath);
+    }
+}
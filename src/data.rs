@@ -1,17 +1,19 @@
+use std::collections::{BTreeMap, HashMap};
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Debug)]
 pub struct Package {
     pub name: String,
     pub source_name: String,
-    pub epoch: i32,
-    pub version: String,
-    pub release: String,
     pub arch: String,
+    pub summary: String,
+    pub license: String,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct BrokenItem {
     pub source: String,
     pub package: String,
@@ -20,10 +22,300 @@ pub struct BrokenItem {
     pub release: String,
     pub arch: String,
     pub admin: String,
+    #[serde(default)]
+    pub admin_inactive: bool,
+    // whether the package is part of the critical path of the release
+    #[serde(default)]
+    pub critpath: bool,
+    // comps groups that the package is a member of
+    #[serde(default)]
+    pub groups: Vec<String>,
+    // module stream ("name:stream") that the package belongs to, if it is a modular package
+    #[serde(default)]
+    pub module: Option<String>,
+    // main admin of the package before it last changed while the package was broken, and when that happened
+    #[serde(default)]
+    pub previous_admin: Option<String>,
+    #[serde(default)]
+    pub admin_changed: Option<DateTime<Utc>>,
     #[serde(default = "Vec::new")]
     pub maintainers: Vec<String>,
     pub repo: String,
     pub repo_arch: String,
     pub broken: Vec<String>,
+    // category of every unresolved dependency, in the same order as "broken"
+    #[serde(default)]
+    pub categories: Vec<DepCategory>,
     pub since: Option<DateTime<Utc>>,
+    // number of other packages that depend on this package (not available for source packages)
+    #[serde(default)]
+    pub dependents: Option<usize>,
+    #[serde(default)]
+    pub leaf: Option<bool>,
+    // hints about what happened to the packages that used to provide missing capabilities
+    #[serde(default)]
+    pub hints: Vec<ProvideHint>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub distgit_url: Option<String>,
+    #[serde(default)]
+    pub koji_url: Option<String>,
+    #[serde(default)]
+    pub bodhi_url: Option<String>,
+    // identifier of the worker run that generated this item
+    #[serde(default)]
+    pub generated_by_run: Option<Uuid>,
+    // version of repochecker that generated this data
+    #[serde(default)]
+    pub generated_by_version: Option<String>,
+}
+
+impl BrokenItem {
+    /// Returns the `[epoch:]version-release` string of the package, omitting a zero epoch.
+    pub fn evr(&self) -> String {
+        if self.epoch == "0" {
+            format!("{}-{}", self.version, self.release)
+        } else {
+            format!("{}:{}-{}", self.epoch, self.version, self.release)
+        }
+    }
+
+    /// Returns the maintainers of the package other than its main admin.
+    pub fn co_maintainers(&self) -> Vec<String> {
+        self.maintainers
+            .iter()
+            .filter(|maintainer| **maintainer != self.admin)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the `name-epoch:version-release.arch` string of the package, which always includes the epoch.
+    pub fn nevra(&self) -> String {
+        format!(
+            "{}-{}:{}-{}.{}",
+            self.package, self.epoch, self.version, self.release, self.arch
+        )
+    }
+
+    /// Returns the key under which the time since which the package has been broken is persisted.
+    pub fn since_key(&self) -> String {
+        format!("{}/{}/{}", self.package, self.repo, self.repo_arch)
+    }
+}
+
+/// Map from packages (see [`BrokenItem::since_key`]) to the time since which they have been broken, which is persisted
+/// separately from the data files, so it survives when they are lost or corrupted.
+pub type SinceIndex = BTreeMap<String, DateTime<Utc>>;
+
+/// Final results for an archived release, stored in compressed form once the release was frozen.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FrozenData {
+    pub frozen_at: DateTime<Utc>,
+    pub items: Vec<BrokenItem>,
+}
+
+/// Summary of the frozen data of an archived release, which can be read without keeping all items in memory.
+#[derive(Debug, Deserialize)]
+pub struct FrozenSummary {
+    pub frozen_at: DateTime<Utc>,
+    pub items: Vec<ItemSummary>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ItemSummary {
+    #[serde(default)]
+    pub critpath: bool,
+}
+
+/// Download of the metadata of a repository when refreshing the dnf cache.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RepoDownload {
+    pub repo: String,
+    pub arch: String,
+    // size of the downloaded files (in bytes) and time that the refresh took (in seconds)
+    pub bytes: u64,
+    pub seconds: f64,
+}
+
+/// Stage of checking a release, which is reported while the check is running.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum ProgressStage {
+    #[serde(rename = "repoquery")]
+    Repoquery,
+    #[serde(rename = "makecache")]
+    Makecache,
+    #[serde(rename = "closure")]
+    Closure,
+    #[serde(rename = "post-processing")]
+    PostProcessing,
+}
+
+/// Reason why checking a release failed, based on known error messages of dnf.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum FailureReason {
+    #[serde(rename = "metadata-download")]
+    MetadataDownload,
+    #[serde(rename = "cache-corruption")]
+    CacheCorruption,
+    #[serde(rename = "implausible-result")]
+    ImplausibleResult,
+    #[serde(rename = "other")]
+    Other,
+}
+
+/// Kind of capability that an unresolved dependency refers to.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Hash, Serialize)]
+pub enum DepCategory {
+    #[serde(rename = "shared-library")]
+    SharedLibrary,
+    #[serde(rename = "pkgconfig")]
+    PkgConfig,
+    #[serde(rename = "python")]
+    Python,
+    #[serde(rename = "perl")]
+    Perl,
+    #[serde(rename = "file")]
+    File,
+    #[serde(rename = "rich")]
+    Rich,
+    #[serde(rename = "package")]
+    Package,
+    #[serde(rename = "other")]
+    Other,
+}
+
+impl DepCategory {
+    pub const ALL: [DepCategory; 8] = [
+        DepCategory::SharedLibrary,
+        DepCategory::PkgConfig,
+        DepCategory::Python,
+        DepCategory::Perl,
+        DepCategory::File,
+        DepCategory::Rich,
+        DepCategory::Package,
+        DepCategory::Other,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        DepCategory::ALL.into_iter().find(|category| category.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DepCategory::SharedLibrary => "shared-library",
+            DepCategory::PkgConfig => "pkgconfig",
+            DepCategory::Python => "python",
+            DepCategory::Perl => "perl",
+            DepCategory::File => "file",
+            DepCategory::Rich => "rich",
+            DepCategory::Package => "package",
+            DepCategory::Other => "other",
+        }
+    }
+}
+
+/// Result of checking whether a package (or comps group) that must always be installable can be installed.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct InstallCheck {
+    // data that was written before comps groups were checked only has a "package" field
+    #[serde(default)]
+    pub kind: InstallKind,
+    #[serde(alias = "package")]
+    pub name: String,
+    pub arch: String,
+    pub installable: bool,
+    pub problems: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub enum InstallKind {
+    #[default]
+    #[serde(rename = "package")]
+    Package,
+    #[serde(rename = "group")]
+    Group,
+}
+
+/// Package that provided a capability.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Provider {
+    pub name: String,
+    pub source: String,
+    // version of the provided capability (if it is versioned)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evr: Option<String>,
+    // whether the capability is no longer provided (and this is the last known provider)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub previous: bool,
+}
+
+/// Map from capabilities to all packages that provided them, restricted to capabilities that are required by other
+/// packages, which is persisted between runs.
+pub type ProvideIndex = HashMap<String, Vec<Provider>>;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ProvideHint {
+    pub dep: String,
+    pub provider: String,
+    pub source: String,
+    pub change: ProvideChange,
+}
+
+/// Probable reason why a package that used to provide a capability does not provide it anymore.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub enum ProvideChange {
+    // the source package does not build any packages anymore
+    #[serde(rename = "removed")]
+    Removed,
+    // the source package still exists, but the binary package is gone (renamed, merged, or dropped subpackage)
+    #[serde(rename = "renamed")]
+    Renamed,
+    // the package still exists, but provides a different version of the shared library
+    #[serde(rename = "soname-bumped")]
+    SonameBumped,
+    // the package still exists, but stopped providing the capability
+    #[serde(rename = "dropped")]
+    Dropped,
+}
+
+/// Builds a broken package (with `alice` as its main admin) for tests.
+#[cfg(test)]
+pub fn broken_item(package: &str, broken: &[&str]) -> BrokenItem {
+    BrokenItem {
+        source: package.to_string(),
+        package: package.to_string(),
+        epoch: String::from("0"),
+        version: String::from("1.0"),
+        release: String::from("1.fc41"),
+        arch: String::from("x86_64"),
+        admin: String::from("alice"),
+        maintainers: vec![String::from("alice")],
+        repo: String::from("fedora"),
+        repo_arch: String::from("x86_64"),
+        broken: broken.iter().map(|dep| dep.to_string()).collect(),
+        categories: broken.iter().map(|dep| crate::parse::classify_dep(dep)).collect(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn install_check_previous_format() {
+        let check: InstallCheck = serde_json::from_str(
+            r#"{"package": "bash", "arch": "x86_64", "installable": false, "problems": ["nothing provides libfoo"]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(check.kind, InstallKind::Package);
+        assert_eq!(check.name, "bash");
+        assert!(!check.installable);
+    }
 }
@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::data::BrokenItem;
+
+/// Version of the feed schema, which is incremented for any incompatible change.
+pub const FEED_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize)]
+pub struct MaintainerFeed {
+    pub version: u32,
+    pub maintainer: String,
+    // email address of the maintainer, which is only included for authenticated requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub items: Vec<FeedItem>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct FeedItem {
+    pub release: String,
+    pub source: String,
+    pub package: String,
+    pub evr: String,
+    pub arch: String,
+    pub repo: String,
+    pub repo_arch: String,
+    pub broken: Vec<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub role: MaintainerRole,
+    // main admin (point of contact) and co-maintainers of the package
+    pub admin: String,
+    pub co_maintainers: Vec<String>,
+    // set if the main admin of the package changed while it was broken
+    pub maintainer_changed: Option<MaintainerChanged>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct MaintainerChanged {
+    pub previous_admin: String,
+    pub changed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum MaintainerRole {
+    #[serde(rename = "admin")]
+    Admin,
+    #[serde(rename = "maintainer")]
+    Maintainer,
+}
+
+/// Collects all broken items of the given releases that the maintainer is responsible for.
+///
+/// Packages that the maintainer is only a co-maintainer of are only included if `co_maintainers` is set.
+pub fn maintainer_feed<'a>(
+    maintainer: &str,
+    email: Option<String>,
+    releases: impl IntoIterator<Item = (&'a str, &'a [BrokenItem])>,
+    co_maintainers: bool,
+) -> MaintainerFeed {
+    let mut items = Vec::new();
+
+    for (release, values) in releases {
+        for item in values {
+            let role = if item.admin == maintainer {
+                MaintainerRole::Admin
+            } else if co_maintainers && item.maintainers.iter().any(|m| m == maintainer) {
+                MaintainerRole::Maintainer
+            } else {
+                continue;
+            };
+
+            items.push(FeedItem {
+                release: release.to_string(),
+                source: item.source.clone(),
+                package: item.package.clone(),
+                evr: item.evr(),
+                arch: item.arch.clone(),
+                repo: item.repo.clone(),
+                repo_arch: item.repo_arch.clone(),
+                broken: item.broken.clone(),
+                since: item.since,
+                role,
+                admin: item.admin.clone(),
+                co_maintainers: item.co_maintainers(),
+                maintainer_changed: item.previous_admin.as_ref().map(|previous_admin| MaintainerChanged {
+                    previous_admin: previous_admin.clone(),
+                    changed_at: item.admin_changed,
+                }),
+            });
+        }
+    }
+
+    items.sort_by(|a, b| {
+        (&a.release, &a.source, &a.package, &a.repo_arch).cmp(&(&b.release, &b.source, &b.package, &b.repo_arch))
+    });
+
+    MaintainerFeed {
+        version: FEED_VERSION,
+        maintainer: maintainer.to_string(),
+        email,
+        items,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::data::broken_item;
+
+    fn item(package: &str, admin: &str, maintainers: &[&str]) -> BrokenItem {
+        BrokenItem {
+            epoch: String::from("1"),
+            admin: admin.to_string(),
+            maintainers: maintainers.iter().map(|m| m.to_string()).collect(),
+            ..broken_item(package, &["libfoo.so.1()(64bit)"])
+        }
+    }
+
+    #[test]
+    fn maintainer_feed() {
+        let values = vec![
+            item("foo", "alice", &["alice", "bob"]),
+            item("bar", "bob", &["bob"]),
+            item("baz", "carol", &["carol"]),
+        ];
+
+        let feed = super::maintainer_feed(
+            "bob",
+            Some(String::from("bob@example.com")),
+            [("41", values.as_slice())],
+            true,
+        );
+
+        assert_eq!(feed.items.len(), 2);
+        assert_eq!(feed.items[0].package, "bar");
+        assert_eq!(feed.items[0].role, MaintainerRole::Admin);
+        assert_eq!(feed.items[0].evr, "1:1.0-1.fc41");
+        assert_eq!(feed.items[1].package, "foo");
+        assert_eq!(feed.items[1].role, MaintainerRole::Maintainer);
+        assert_eq!(feed.items[1].admin, "alice");
+        assert_eq!(feed.items[1].co_maintainers, vec![String::from("bob")]);
+
+        let feed = super::maintainer_feed("bob", None, [("41", values.as_slice())], false);
+
+        assert_eq!(feed.items.len(), 1);
+        assert_eq!(feed.items[0].package, "bar");
+    }
+}
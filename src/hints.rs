@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+
+use crate::data::{BrokenItem, DepCategory, ProvideChange, ProvideHint, ProvideIndex};
+
+/// Determines which packages used to provide the missing capabilities of a broken item in a previous run, and what
+/// probably happened to them, based on the names of binary and source packages that are currently available.
+pub fn get_hints(
+    item: &BrokenItem,
+    previous: &ProvideIndex,
+    names: &HashSet<&str>,
+    sources: &HashSet<&str>,
+) -> Vec<ProvideHint> {
+    let mut hints = Vec::new();
+
+    for (index, dep) in item.broken.iter().enumerate() {
+        // rich dependencies cannot be matched against a single provide
+        if dep.starts_with('(') {
+            continue;
+        }
+
+        // strip version constraints (e.g. "foo >= 1.0")
+        let capability = dep.split(' ').next().unwrap_or(dep);
+
+        for provider in previous.get(capability).into_iter().flatten() {
+            let change = if names.contains(provider.name.as_str()) {
+                if item.categories.get(index) == Some(&DepCategory::SharedLibrary) {
+                    ProvideChange::SonameBumped
+                } else {
+                    ProvideChange::Dropped
+                }
+            } else if sources.contains(provider.source.as_str()) {
+                ProvideChange::Renamed
+            } else {
+                ProvideChange::Removed
+            };
+
+            hints.push(ProvideHint {
+                dep: dep.clone(),
+                provider: provider.name.clone(),
+                source: provider.source.clone(),
+                change,
+            });
+        }
+    }
+
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Provider;
+    use crate::parse::classify_dep;
+
+    #[test]
+    fn get_hints() {
+        let broken = vec![
+            String::from("libfoo.so.1()(64bit)"),
+            String::from("bar >= 2.0"),
+            String::from("baz"),
+            String::from("qux(x86-64)"),
+            String::from("unknown"),
+        ];
+
+        let item = BrokenItem {
+            package: String::from("app"),
+            categories: broken.iter().map(|dep| classify_dep(dep)).collect(),
+            broken,
+            ..Default::default()
+        };
+
+        let provider = |name: &str, source: &str| {
+            vec![Provider {
+                name: name.to_string(),
+                source: source.to_string(),
+                evr: None,
+                previous: false,
+            }]
+        };
+
+        let previous = ProvideIndex::from([
+            (String::from("libfoo.so.1()(64bit)"), provider("libfoo", "foo")),
+            (String::from("bar"), provider("bar", "bar")),
+            (String::from("baz"), provider("baz", "baz")),
+            (String::from("qux(x86-64)"), provider("qux", "qux")),
+        ]);
+
+        let names = HashSet::from(["libfoo", "bar", "baz-ng"]);
+        let sources = HashSet::from(["foo", "bar", "baz"]);
+
+        let hints = super::get_hints(&item, &previous, &names, &sources);
+
+        let changes: Vec<ProvideChange> = hints.iter().map(|hint| hint.change).collect();
+        assert_eq!(
+            changes,
+            vec![
+                ProvideChange::SonameBumped,
+                ProvideChange::Dropped,
+                ProvideChange::Renamed,
+                ProvideChange::Removed
+            ]
+        );
+    }
+}
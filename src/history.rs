@@ -0,0 +1,199 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::data::BrokenItem;
+use crate::utils::{get_data_path, read_compressed_json, write_compressed_json};
+
+// format of the timestamps in the file names of generations, which sorts chronologically
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Generation of the data of a release that is kept in the history directory of the release.
+#[derive(Clone, Debug, Serialize)]
+pub struct Generation {
+    pub run: Uuid,
+    pub generated_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// Returns the directory in which previous generations of the data of a release are kept.
+pub fn get_history_path(product: Option<&str>, release: &str, testing: bool) -> PathBuf {
+    let mut path = get_data_path();
+    if let Some(product) = product {
+        path.push(product);
+    }
+
+    path.push("history/");
+    if !testing {
+        path.push(release);
+    } else {
+        path.push(format!("{}-testing", release));
+    }
+
+    path
+}
+
+fn parse_file_name(name: &str) -> Option<(DateTime<Utc>, Uuid)> {
+    let stem = name.strip_suffix(".json.gz")?;
+    let (timestamp, run) = stem.split_once('-')?;
+
+    let generated_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()?
+        .and_utc();
+    let run = Uuid::parse_str(run).ok()?;
+
+    Some((generated_at, run))
+}
+
+/// Lists all generations in a history directory, starting with the most recent one.
+pub fn list_generations(path: &Path) -> Result<Vec<Generation>, String> {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        // no generations were kept yet
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(format!("Failed to read history directory: {}", error)),
+    };
+
+    let mut generations: Vec<Generation> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let (generated_at, run) = parse_file_name(&entry.file_name().to_string_lossy())?;
+            Some(Generation {
+                run,
+                generated_at,
+                path: entry.path(),
+            })
+        })
+        .collect();
+
+    generations.sort_by_key(|generation| std::cmp::Reverse(generation.generated_at));
+    Ok(generations)
+}
+
+/// Writes a new generation of the data of a release, and removes all but the `keep` most recent generations.
+pub fn write_generation(
+    path: &Path,
+    run: Uuid,
+    generated_at: DateTime<Utc>,
+    items: &[BrokenItem],
+    keep: usize,
+) -> Result<(), String> {
+    let mut file = path.to_path_buf();
+    file.push(format!("{}-{}.json.gz", generated_at.format(TIMESTAMP_FORMAT), run));
+    write_compressed_json(&file, items)?;
+
+    for generation in list_generations(path)?.into_iter().skip(keep) {
+        if let Err(error) = std::fs::remove_file(&generation.path) {
+            return Err(format!(
+                "Failed to remove old generation {}: {}",
+                generation.path.to_string_lossy(),
+                error
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the most recent generation of the data of a release that was generated at or before the given time, which is
+/// the data that was published at that time.
+pub fn read_generation_at(path: &Path, at: DateTime<Utc>) -> Result<Option<(Generation, Vec<BrokenItem>)>, String> {
+    match list_generations(path)?
+        .into_iter()
+        .find(|generation| generation.generated_at <= at)
+    {
+        Some(generation) => {
+            let items = read_compressed_json(&generation.path, "Generation was removed while it was read.")?;
+            Ok(Some((generation, items)))
+        },
+        None => Ok(None),
+    }
+}
+
+/// Parses a timestamp in RFC 3339 format, where seconds (or the whole time of day) can be omitted, for example
+/// "2024-05-01T00:00Z" or "2024-05-01".
+pub fn parse_timestamp(string: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(string) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    if let Ok(timestamp) = DateTime::parse_from_str(string, "%Y-%m-%dT%H:%M%#z") {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    match NaiveDate::parse_from_str(string, "%Y-%m-%d") {
+        Ok(date) => Ok(date.and_time(NaiveTime::MIN).and_utc()),
+        Err(_) => Err(format!("Invalid timestamp: {}", string)),
+    }
+}
+
+/// Reads the generation of the data of a release that was written by the given run.
+pub fn read_generation(path: &Path, run: Uuid) -> Result<Option<Vec<BrokenItem>>, String> {
+    match list_generations(path)?
+        .into_iter()
+        .find(|generation| generation.run == run)
+    {
+        Some(generation) => Ok(Some(read_compressed_json(
+            &generation.path,
+            "Generation was removed while it was read.",
+        )?)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn rotate_generations() {
+        let path = std::env::temp_dir().join(format!("repochecker-history-{}", Uuid::new_v4()));
+
+        let runs: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        for (hours, run) in runs.iter().enumerate() {
+            let generated_at = Utc::now() + chrono::Duration::hours(hours as i64);
+            write_generation(&path, *run, generated_at, &[BrokenItem::default()], 2).unwrap();
+        }
+
+        let generations = list_generations(&path).unwrap();
+        assert_eq!(generations.len(), 2);
+        assert_eq!(generations[0].run, runs[2]);
+        assert_eq!(generations[1].run, runs[1]);
+
+        assert!(read_generation(&path, runs[0]).unwrap().is_none());
+        assert_eq!(read_generation(&path, runs[2]).unwrap().unwrap().len(), 1);
+
+        // the generation that was published at a given time is the most recent one that is not newer
+        let (generation, items) = read_generation_at(&path, generations[0].generated_at).unwrap().unwrap();
+        assert_eq!(generation.run, runs[2]);
+        assert_eq!(items.len(), 1);
+        let between = generations[1].generated_at + chrono::Duration::minutes(30);
+        assert_eq!(read_generation_at(&path, between).unwrap().unwrap().0.run, runs[1]);
+        assert!(
+            read_generation_at(&path, generations[1].generated_at - chrono::Duration::minutes(1))
+                .unwrap()
+                .is_none()
+        );
+
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn parse_timestamp() {
+        let expected = NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_time(NaiveTime::MIN)
+            .and_utc();
+
+        assert_eq!(super::parse_timestamp("2024-05-01T00:00:00Z").unwrap(), expected);
+        assert_eq!(super::parse_timestamp("2024-05-01T00:00Z").unwrap(), expected);
+        assert_eq!(super::parse_timestamp("2024-05-01T02:00+02:00").unwrap(), expected);
+        assert_eq!(super::parse_timestamp("2024-05-01").unwrap(), expected);
+        assert!(super::parse_timestamp("May 1st").is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use std::io::Read;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use flate2::read::GzDecoder;
+use log::{debug, warn};
+use reqwest::header::{HeaderMap, CONTENT_LENGTH};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+use crate::utils::{read_lock, write_lock};
+use crate::version::VERSION;
+
+// number of times that failed downloads are retried by default
+const DEFAULT_RETRIES: u32 = 2;
+
+// size limit (in bytes) of downloaded files by default
+const DEFAULT_MAX_SIZE: u64 = 256 * 1024 * 1024;
+
+// client that is shared by all requests to external services, which is replaced when the configuration changes
+static CLIENT: RwLock<Option<reqwest::Client>> = RwLock::new(None);
+
+/// Returns the User-Agent header that identifies requests of this instance.
+pub fn user_agent(config: &Config) -> String {
+    if let Some(user_agent) = &config.http.user_agent {
+        return user_agent.clone();
+    }
+
+    match &config.branding.contact {
+        Some(contact) => format!("repochecker/{} ({}; {})", VERSION, config.branding.name, contact),
+        None => format!("repochecker/{} ({})", VERSION, config.branding.name),
+    }
+}
+
+fn build_client(config: &Config) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::ClientBuilder::new()
+        .user_agent(user_agent(config))
+        .connect_timeout(Duration::from_secs_f64(config.http.connect_timeout));
+
+    if let Some(proxy) = &config.http.proxy {
+        let proxy = match reqwest::Proxy::all(proxy) {
+            Ok(proxy) => proxy,
+            Err(error) => return Err(format!("Invalid proxy {}: {}", proxy, error)),
+        };
+        builder = builder.proxy(proxy);
+    }
+
+    match builder.build() {
+        Ok(client) => Ok(client),
+        Err(error) => Err(format!("Failed to build HTTP client: {}", error)),
+    }
+}
+
+/// Creates the shared HTTP client for the given configuration (on startup, and after the configuration changed).
+pub fn configure(config: &Config) -> Result<(), String> {
+    let client = build_client(config)?;
+    debug!(
+        "Using User-Agent for requests to external services: {}",
+        user_agent(config)
+    );
+
+    *write_lock(&CLIENT) = Some(client);
+    Ok(())
+}
+
+/// Returns the shared HTTP client, which keeps connections to external services open between requests.
+///
+/// Timeouts for whole requests depend on the service and are set for each request.
+pub fn client() -> Result<reqwest::Client, String> {
+    if let Some(client) = read_lock(&CLIENT).as_ref() {
+        return Ok(client.clone());
+    }
+
+    // commands that do not start the service do not configure the client
+    let mut guard = write_lock(&CLIENT);
+    match guard.as_ref() {
+        Some(client) => Ok(client.clone()),
+        None => {
+            let client = match reqwest::ClientBuilder::new()
+                .user_agent(format!("repochecker/{}", VERSION))
+                .build()
+            {
+                Ok(client) => client,
+                Err(error) => return Err(format!("Failed to build HTTP client: {}", error)),
+            };
+            *guard = Some(client.clone());
+            Ok(client)
+        },
+    }
+}
+
+/// Download of a file from an external service, which is retried if it fails because of network errors, server
+/// errors, or checksum mismatches.
+#[derive(Clone, Debug)]
+pub struct Download {
+    pub url: String,
+    // timeout (in seconds) of every attempt
+    pub timeout: u64,
+    pub retries: u32,
+    // size limit (in bytes) of the response body (before decompression)
+    pub max_size: u64,
+    // expected SHA-256 checksum (hex-encoded) of the response body (before decompression)
+    pub sha256: Option<String>,
+    // whether the response body is gzip-compressed
+    pub gzip: bool,
+    pub headers: HeaderMap,
+}
+
+/// Response of a successful download.
+#[derive(Debug)]
+pub struct Downloaded {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    // empty if the file was not modified (see [`Download::headers`] for conditional requests)
+    pub body: Vec<u8>,
+}
+
+enum Failure {
+    // failures that are retried
+    Transient(String),
+    Permanent(String),
+}
+
+impl Download {
+    pub fn new(url: impl Into<String>, timeout: u64) -> Self {
+        Download {
+            url: url.into(),
+            timeout,
+            retries: DEFAULT_RETRIES,
+            max_size: DEFAULT_MAX_SIZE,
+            sha256: None,
+            gzip: false,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Downloads the file, and retries failed attempts with increasing delays.
+    pub async fn fetch(&self) -> Result<Downloaded, String> {
+        let client = client()?;
+        let mut attempt = 0;
+
+        loop {
+            match self.attempt(&client).await {
+                Ok(downloaded) => return Ok(downloaded),
+                Err(Failure::Transient(error)) if attempt < self.retries => {
+                    attempt += 1;
+                    warn!(
+                        "Failed to download {} (retrying, attempt {} of {}): {}",
+                        &self.url, attempt, self.retries, error
+                    );
+                    tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+                },
+                Err(Failure::Transient(error)) | Err(Failure::Permanent(error)) => {
+                    return Err(format!("Failed to download {}: {}", &self.url, error))
+                },
+            }
+        }
+    }
+
+    /// Downloads the file and deserializes it from JSON.
+    pub async fn fetch_json<T: DeserializeOwned>(&self) -> Result<T, String> {
+        let downloaded = self.fetch().await?;
+
+        match serde_json::from_slice(&downloaded.body) {
+            Ok(value) => Ok(value),
+            Err(error) => Err(format!("Failed to deserialize {}: {}", &self.url, error)),
+        }
+    }
+
+    async fn attempt(&self, client: &reqwest::Client) -> Result<Downloaded, Failure> {
+        let request = client
+            .get(&self.url)
+            .headers(self.headers.clone())
+            .timeout(Duration::from_secs(self.timeout));
+
+        let mut response = match request.send().await {
+            Ok(response) => response,
+            Err(error) => return Err(Failure::Transient(error.to_string())),
+        };
+
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(Downloaded {
+                status,
+                headers: response.headers().clone(),
+                body: Vec::new(),
+            });
+        }
+
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(Failure::Transient(format!("Server returned {}", status)));
+        } else if !status.is_success() {
+            return Err(Failure::Permanent(format!("Server returned {}", status)));
+        }
+
+        let too_large = || Failure::Permanent(format!("Response is larger than {} bytes", self.max_size));
+
+        let length = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if length.is_some_and(|length| length > self.max_size) {
+            return Err(too_large());
+        }
+
+        let headers = response.headers().clone();
+        let mut body = Vec::new();
+
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    if (body.len() + chunk.len()) as u64 > self.max_size {
+                        return Err(too_large());
+                    }
+                    body.extend_from_slice(&chunk);
+                },
+                Ok(None) => break,
+                Err(error) => return Err(Failure::Transient(error.to_string())),
+            }
+        }
+
+        if let Some(expected) = &self.sha256 {
+            verify_sha256(&body, expected).map_err(Failure::Transient)?;
+        }
+
+        if self.gzip {
+            body = decompress(&body).map_err(Failure::Permanent)?;
+        }
+
+        Ok(Downloaded { status, headers, body })
+    }
+}
+
+/// Checks that data matches a hex-encoded SHA-256 checksum.
+fn verify_sha256(data: &[u8], expected: &str) -> Result<(), String> {
+    let actual: String = Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(format!("Checksum mismatch (expected {}, got {})", expected, actual))
+    }
+}
+
+fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decompressed = Vec::new();
+    match GzDecoder::new(data).read_to_end(&mut decompressed) {
+        Ok(_) => Ok(decompressed),
+        Err(error) => Err(format!("Failed to decompress response: {}", error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    #[test]
+    fn verify_sha256() {
+        let expected = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+        assert!(super::verify_sha256(b"hello", expected).is_ok());
+        assert!(super::verify_sha256(b"hello", &expected.to_uppercase()).is_ok());
+        assert!(super::verify_sha256(b"world", expected).is_err());
+    }
+
+    #[test]
+    fn decompress() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"hello").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(super::decompress(&compressed).unwrap(), b"hello");
+        assert!(super::decompress(b"hello").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use log::info;
+use uuid::Uuid;
+
+use crate::config::{get_config, Config, MatrixEntry};
+use crate::data::{BrokenItem, SinceIndex};
+use crate::server::{annotate_results, persist_results, publish_results};
+use crate::utils::{get_json_path, get_since_path, read_json_from_file, read_since_from_file};
+
+/// Returns the matrix entry of a release that is configured to use imported data.
+pub fn external_entry(config: &Config, key: &str) -> Result<MatrixEntry, String> {
+    let matrix = config.to_matrix()?;
+
+    match matrix.into_iter().find(|entry| entry.key() == key) {
+        Some(entry) if entry.external => Ok(entry),
+        Some(_) => Err(format!(
+            "Release {} is checked by this instance, and its data can not be imported.",
+            key
+        )),
+        None => Err(format!("Release {} is not present in the configuration.", key)),
+    }
+}
+
+/// Checks that data which was generated elsewhere is complete and matches the configuration of the release.
+pub fn validate(entry: &MatrixEntry, items: &[BrokenItem]) -> Result<(), String> {
+    let arches: HashSet<&str> = entry.arches.iter().map(|arch| arch.name.as_str()).collect();
+
+    for item in items {
+        if item.source.is_empty() || item.package.is_empty() {
+            return Err(String::from("Broken package without package or source package name."));
+        }
+
+        if !arches.contains(item.repo_arch.as_str()) {
+            return Err(format!(
+                "Broken package {} is reported for architecture {}, which is not checked for {}.",
+                item.package,
+                item.repo_arch,
+                entry.key()
+            ));
+        }
+
+        if item.broken.is_empty() {
+            return Err(format!(
+                "Broken package {} has no unresolved dependencies.",
+                item.package
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates imported data and stores it as the data of the release in the data directory, in the same way as data
+/// that is imported via the administrative API: it is annotated (with "since" timestamps carried over from the
+/// previous data), signed, kept as a generation in the history, and published (if these are configured).
+///
+/// Quarantining packages and raising alerts depend on the state of the running service, and are skipped.
+pub async fn persist(config: &Config, entry: &MatrixEntry, mut items: Vec<BrokenItem>) -> Result<(), String> {
+    validate(entry, &items)?;
+
+    let old = read_json_from_file(&get_json_path(
+        entry.product.as_deref(),
+        &entry.release,
+        entry.with_testing,
+    ))
+    .ok();
+    let since_path = get_since_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+    let since_index = read_since_from_file(&since_path).unwrap_or_else(|_| SinceIndex::new());
+
+    // activity of users is only looked up by the service, so admins that were inactive before are still flagged (the
+    // flags are updated by the service on its next activity refresh)
+    let inactive: HashSet<String> = old
+        .iter()
+        .flatten()
+        .filter(|item| item.admin_inactive)
+        .map(|item| item.admin.clone())
+        .collect();
+
+    let run = Uuid::new_v4();
+    let since_index = annotate_results(&mut items, old.as_deref(), &since_index, &config.urls, &inactive, run);
+
+    let signature_path = persist_results(
+        entry,
+        run,
+        &items,
+        &since_index,
+        config.signing.as_ref(),
+        config.repochecker.history_size,
+    )
+    .await?;
+
+    if let Some(publish) = &config.publish {
+        publish_results(publish, entry, signature_path).await;
+    }
+
+    Ok(())
+}
+
+/// Imports data for a release from a file (while the service is not running, or before restarting it).
+pub async fn import(path: &Path, key: &str) -> Result<(), String> {
+    let items = read_json_from_file(path).map_err(|error| format!("{}: {}", path.to_string_lossy(), error))?;
+    let count = items.len();
+
+    let config = get_config()?;
+    let entry = external_entry(&config, key)?;
+    persist(&config, &entry, items).await?;
+
+    info!("Imported {} broken packages for {}.", count, key);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::{Arch, PackageGlobs};
+
+    #[test]
+    fn validate() {
+        let entry = MatrixEntry {
+            product: Some(String::from("epel")),
+            release: String::from("9"),
+            arches: vec![Arch {
+                name: String::from("x86_64"),
+                multi_arch: vec![String::from("x86_64"), String::from("noarch")],
+                content_arches: Vec::new(),
+            }],
+            repos: vec![String::from("epel")],
+            check: vec![String::from("epel")],
+            with_testing: false,
+            archived: false,
+            external: true,
+            detect_exclude_arch: true,
+            package_globs: PackageGlobs::default(),
+            protected: Vec::new(),
+            protected_groups: Vec::new(),
+            modular: None,
+            compose: None,
+        };
+
+        let item = BrokenItem {
+            source: String::from("foo"),
+            package: String::from("foo"),
+            repo_arch: String::from("x86_64"),
+            broken: vec![String::from("libfoo.so.1()(64bit)")],
+            ..Default::default()
+        };
+
+        assert!(super::validate(&entry, std::slice::from_ref(&item)).is_ok());
+
+        let wrong_arch = BrokenItem {
+            repo_arch: String::from("s390x"),
+            ..item.clone()
+        };
+        assert!(super::validate(&entry, &[wrong_arch]).is_err());
+
+        let not_broken = BrokenItem {
+            broken: Vec::new(),
+            ..item
+        };
+        assert!(super::validate(&entry, &[not_broken]).is_err());
+    }
+}
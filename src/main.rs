@@ -1,25 +1,74 @@
 #![warn(clippy::unwrap_used)]
 
+mod accounts;
+mod alerts;
+mod cli;
 mod config;
+mod critpath;
 mod data;
+mod feeds;
+mod hints;
+mod history;
+mod http;
+mod import;
 mod overrides;
 mod pagure;
 mod parse;
+mod publish;
+mod readiness;
+mod render;
 mod repo;
+mod reports;
 mod server;
+mod signing;
+mod snapshot;
 mod templates;
 mod utils;
+mod version;
 
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
 use std::time::{Duration, Instant};
 
 use chrono::Utc;
-use log::{error, info};
+use log::{error, info, warn};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
 
+use cli::Command;
 use config::get_config;
-use overrides::Overrides;
-use pagure::{get_admins, get_maintainers};
-use server::{GlobalState, State};
+use overrides::{load_product_overrides, Overrides};
+use pagure::get_accounts;
+use readiness::check_readiness;
+use server::{GlobalState, State, WorkerPool};
+use utils::{read_lock, write_lock};
+
+/// Installs signal handlers once, and returns a token that is cancelled when the service is asked to shut down (SIGINT
+/// or SIGTERM).
+///
+/// Signals that arrive while the service is not waiting for them (for example, between refresh cycles) are not lost.
+fn install_shutdown_signal() -> CancellationToken {
+    let shutdown = CancellationToken::new();
+
+    let mut terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+        Ok(signal) => signal,
+        Err(error) => {
+            error!("Failed to install signal handler: {}", error);
+            return shutdown;
+        },
+    };
+
+    let token = shutdown.clone();
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {},
+            _ = terminate.recv() => {},
+        }
+        token.cancel();
+    });
+
+    shutdown
+}
 
 #[tokio::main(worker_threads = 16)]
 async fn main() -> Result<(), String> {
@@ -28,55 +77,295 @@ async fn main() -> Result<(), String> {
         .parse_env("REPOCHECKER_LOG")
         .init();
 
+    let command = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        },
+    };
+
+    let fake_data = match command {
+        Command::Serve { fake_data } => fake_data,
+        Command::Query(args) => return cli::query(&args),
+        Command::Diff(args) => return cli::diff(&args),
+        Command::Render(args) => return render::render(&args),
+        Command::Snapshot(path) => return snapshot::snapshot(&path),
+        Command::Restore(path) => return snapshot::restore(&path).await,
+        Command::Import(args) => return import::import(&args.path, &args.release).await,
+    };
+
+    if fake_data {
+        info!("Using fake data from fixture files.");
+    }
+
     let config = get_config()?;
+    http::configure(&config)?;
+
+    // report missing tools and permissions on startup, instead of failing during the first refresh cycle
+    let readiness = check_readiness(&config, fake_data).await;
+    readiness.log();
+
     let overrides = Overrides::load_from_disk()?;
+    let product_overrides = load_product_overrides(&config.products)?;
 
     // fetch main admins and lists of maintainers concurrently
-    let (admins, maintainers) = tokio::join!(tokio::spawn(get_admins(15)), tokio::spawn(get_maintainers(15)),);
-    let admins = admins.map_err(|error| error.to_string())??;
-    let maintainers = maintainers.map_err(|error| error.to_string())??;
+    let (admins, maintainers, degraded) = if !fake_data {
+        match get_accounts(&config.pagure, 15).await {
+            Ok((Some(admins), Some(maintainers))) => (admins, maintainers, None),
+            // nothing has been fetched before, so results are only missing if they could not be fetched
+            Ok((admins, maintainers)) => {
+                let error = String::from("Failed to read either main admins or maintainers.");
+                warn!("{} Starting in degraded mode.", error);
+                (admins.unwrap_or_default(), maintainers.unwrap_or_default(), Some(error))
+            },
+            Err(error) => {
+                warn!(
+                    "Failed to read package maintainers, starting in degraded mode: {}",
+                    error
+                );
+                (HashMap::new(), HashMap::new(), Some(error))
+            },
+        }
+    } else {
+        (HashMap::new(), HashMap::new(), None)
+    };
+
+    // releases may have been removed from the configuration while the service was not running (data on disk is left
+    // alone when using fake data)
+    if !fake_data {
+        server::retire_unconfigured_releases(&config);
+    }
 
     // initialize global state
-    let state: GlobalState = Arc::new(RwLock::new(State::init(config, overrides, admins, maintainers)));
+    let state: GlobalState = Arc::new(RwLock::new(State::init(
+        config,
+        overrides,
+        product_overrides,
+        admins,
+        maintainers,
+        fake_data,
+    )));
+
+    // refresh schedules and releases that were carried over are kept across restarts
+    let matrix = read_lock(&state).config.to_matrix()?;
+    server::load_schedule(&state, &matrix);
+
+    // releases are checked on separate threads, so the server stays responsive during refresh cycles
+    let pool = WorkerPool::new(read_lock(&state).config.repochecker.worker_threads)?;
+
+    {
+        let mut guard = write_lock(&state);
+        guard.workers = Some(pool.handle());
+        guard.degraded = degraded;
+        guard.readiness = Some(readiness);
+    }
+
+    let shutdown = install_shutdown_signal();
 
     // spawn server thread
     tokio::spawn(server::server(state.clone()));
 
+    // spawn thread for refreshing package maintainers independently of repository checks (if configured)
+    tokio::spawn(server::account_refresher(state.clone()));
+
+    // spawn thread for dropping data of archived releases that is no longer requested from memory
+    tokio::spawn(server::archived_evictor(state.clone()));
+
+    // without package maintainers, only cached data is served until they can be read
+    if read_lock(&state).degraded.is_some() {
+        let matrix = read_lock(&state).config.to_matrix()?;
+        for entry in &matrix {
+            server::load_cached(&state, entry);
+        }
+
+        // changes of the configuration are applied while waiting (refresh cycles reload it themselves)
+        let reloader = tokio::spawn(server::config_reloader(state.clone()));
+
+        tokio::select! {
+            _ = server::recover_accounts(&state) => {},
+            _ = shutdown.cancelled() => {
+                info!("Shutting down.");
+                return Ok(());
+            },
+        }
+
+        reloader.abort();
+    }
+
     loop {
+        // no new refresh cycles are started while checks are paused
+        if read_lock(&state).paused {
+            tokio::select! {
+                _ = server::wait_until_resumed(&state) => {},
+                _ = shutdown.cancelled() => {
+                    info!("Shutting down.");
+                    return Ok(());
+                },
+            }
+        }
+
         let start = Instant::now();
 
         let config = {
-            let guard = state.read().expect("Found a poisoned lock.");
+            let guard = read_lock(&state);
             guard.config.clone()
         };
 
         let matrix = config.to_matrix()?;
 
-        // spawn worker threads
-        let handles: Vec<_> = matrix
+        // releases that were carried over from the previous cycle are always checked, and are allowed to finish even if
+        // this cycle exceeds its maximum duration again, so slow releases are not cancelled in every cycle
+        let carried_over = std::mem::take(&mut write_lock(&state).carried_over);
+
+        // with adaptive intervals, cycles run at the minimum interval and only check releases that are due
+        let releases = {
+            let now = Utc::now();
+            let guard = read_lock(&state);
+            let adaptive = config.repochecker.adaptive.is_some();
+
+            server::cycle_releases(matrix, &carried_over, |key| !adaptive || guard.is_due(key, now))
+        };
+
+        if config.repochecker.adaptive.is_some() {
+            info!(
+                "Releases due for checking: {}",
+                releases
+                    .iter()
+                    .map(|(entry, _)| entry.key())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        let cycle = Uuid::new_v4();
+        info!("Starting refresh cycle {}.", cycle);
+        {
+            let mut guard = write_lock(&state);
+            guard.cycle = Some(cycle);
+            guard.next_cycle = None;
+        }
+
+        // the list of critical path packages is shared by all releases that are checked in this cycle
+        server::refresh_critpath(&state).await;
+
+        if !carried_over.is_empty() {
+            info!(
+                "Releases carried over from the previous cycle: {}",
+                carried_over.iter().cloned().collect::<Vec<_>>().join(", ")
+            );
+        }
+
+        // workers are cancelled when the configuration changes or when shutdown is requested
+        let cancel = CancellationToken::new();
+        let monitor = tokio::spawn(server::config_monitor(config.clone(), cancel.clone()));
+
+        // workers that were not carried over are also cancelled when the cycle exceeds its maximum duration
+        let time_box = cancel.child_token();
+        let unfinished: Arc<Mutex<BTreeSet<String>>> = Arc::new(Mutex::new(
+            releases
+                .iter()
+                .filter(|(_, carried_over)| !carried_over)
+                .map(|(entry, _)| entry.key())
+                .collect(),
+        ));
+
+        // spawn worker threads (releases that were carried over are started first)
+        let handles: Vec<_> = releases
             .into_iter()
-            .map(|entry| tokio::spawn(server::worker(state.clone(), entry)))
+            .map(|(entry, carried_over)| {
+                let key = entry.key();
+                let token = if carried_over { cancel.clone() } else { time_box.clone() };
+
+                let state = state.clone();
+                let unfinished = unfinished.clone();
+                pool.spawn(async move {
+                    server::worker(state, entry, cycle, token).await;
+                    unfinished.lock().unwrap_or_else(PoisonError::into_inner).remove(&key);
+                })
+            })
             .collect();
 
-        // wait for worker threads
-        for handle in handles {
-            handle.await.map_err(|error| error.to_string())?;
+        // wait for worker threads; a panicking worker must not take down the whole service
+        let mut workers = tokio::spawn(async move {
+            for handle in handles {
+                if let Err(error) = handle.await {
+                    error!("Worker thread failed: {}", error);
+                }
+            }
+        });
+
+        let deadline = async {
+            match config.repochecker.max_cycle_duration {
+                Some(hours) => tokio::time::sleep(Duration::from_secs_f64(hours * 60.0 * 60.0)).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(deadline);
+        let mut timed_out = false;
+
+        loop {
+            tokio::select! {
+                _ = &mut workers => break,
+                _ = &mut deadline, if !timed_out => {
+                    timed_out = true;
+
+                    let unfinished = unfinished.lock().unwrap_or_else(PoisonError::into_inner).clone();
+                    if !unfinished.is_empty() {
+                        warn!(
+                            "Refresh cycle {} exceeded its maximum duration, carrying over: {}",
+                            cycle,
+                            unfinished.iter().cloned().collect::<Vec<_>>().join(", ")
+                        );
+                        write_lock(&state).carried_over = unfinished;
+                    }
+
+                    time_box.cancel();
+                },
+                _ = shutdown.cancelled() => {
+                    info!("Shutting down, cancelling workers.");
+                    cancel.cancel();
+                    let _ = workers.await;
+                    return Ok(());
+                },
+            }
         }
 
-        let interval = config.repochecker.interval;
+        monitor.abort();
+        server::persist_schedule(&state);
+
+        let interval = match &config.repochecker.adaptive {
+            Some(adaptive) => adaptive.min_interval,
+            None => config.repochecker.interval,
+        };
 
         let stop = Instant::now();
         let busy = stop - start;
 
         let wait = Duration::from_secs_f64(interval * 60.0 * 60.0).saturating_sub(busy);
 
-        if !wait.is_zero() {
+        if cancel.is_cancelled() {
+            info!("Refresh was cancelled, reloading configuration.");
+        } else if !wait.is_zero() {
             info!(
                 "Finished generating data. Refreshing in {:.1} hours.",
                 wait.as_secs_f64() / 3600.0
             );
-            state.write().expect("Found a poisoned lock.").date_refreshed = Some(Utc::now());
-            tokio::time::sleep(wait).await;
+            {
+                let mut guard = write_lock(&state);
+                let now = Utc::now();
+                guard.date_refreshed = Some(now);
+                guard.next_cycle = chrono::Duration::from_std(wait).ok().map(|wait| now + wait);
+            }
+            server::export_index(&state).await;
+
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {},
+                _ = shutdown.cancelled() => {
+                    info!("Shutting down.");
+                    return Ok(());
+                },
+            }
         }
 
         if tokio::spawn(server::watcher(state.clone())).await.is_err() {
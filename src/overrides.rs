@@ -1,57 +1,173 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::read_to_string;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
-use log::{debug, error, info};
+use chrono::NaiveDate;
+use log::{debug, error, info, warn};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::ProductConfig;
+use crate::parse::parse_rich_dep;
+use crate::utils::schema_for;
 
 const OVERRIDES_FILENAME: &str = "overrides.json";
 
+// prefix of override entries that match all capabilities that used to be provided by a package
+const PROVIDER_PREFIX: &str = "provider:";
+
+// prefix of override entries that match all unresolved dependencies of packages from a module stream
+const MODULE_PREFIX: &str = "module:";
+
 pub type OverrideValues = HashMap<String, ReleaseOverrides>;
 pub type ReleaseOverrides = HashMap<String, PackageOverrides>;
 pub type PackageOverrides = HashMap<String, OverrideEntry>;
 pub type OverrideStats = HashMap<String, u32>;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Packages that an override applies to: either all packages ("all"), or a list of packages.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 #[serde(untagged)]
-pub enum OverrideEntry {
+pub enum OverrideTarget {
+    #[schemars(extend("const" = "all"))]
     All(String),
     Packages(Vec<String>),
 }
 
+/// Override for an unresolved dependency, either only with the packages it applies to, or with information about who
+/// added it and when.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(untagged)]
+pub enum OverrideEntry {
+    Plain(OverrideTarget),
+    Annotated(AnnotatedOverride),
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnnotatedOverride {
+    pub packages: OverrideTarget,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added_on: Option<NaiveDate>,
+}
+
+impl OverrideEntry {
+    pub fn target(&self) -> &OverrideTarget {
+        match self {
+            OverrideEntry::Plain(target) => target,
+            OverrideEntry::Annotated(annotated) => &annotated.packages,
+        }
+    }
+
+    fn target_mut(&mut self) -> &mut OverrideTarget {
+        match self {
+            OverrideEntry::Plain(target) => target,
+            OverrideEntry::Annotated(annotated) => &mut annotated.packages,
+        }
+    }
+
+    fn into_target(self) -> OverrideTarget {
+        match self {
+            OverrideEntry::Plain(target) => target,
+            OverrideEntry::Annotated(annotated) => annotated.packages,
+        }
+    }
+}
+
+/// Override that applies to an unresolved dependency for a release and architecture, combined from all sections.
+#[derive(Debug, Serialize)]
+pub struct EffectiveOverride {
+    pub entry: OverrideTarget,
+    // "release/arch" paths of the sections that contribute to it, most specific first
+    pub sections: Vec<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Overrides {
     pub data: OverrideValues,
+    // number of matches per override path (blanket overrides are counted under their ".../all" path)
     pub stats: OverrideStats,
+    // number of matches of blanket overrides per package
+    pub package_stats: OverrideStats,
+    // sections that were looked up but are not present
+    missing: HashSet<String>,
 }
 
 impl Overrides {
     pub fn load_from_disk() -> Result<Self, String> {
-        let path = get_overrides_path()?;
+        Self::load_from_disk_named(OVERRIDES_FILENAME)
+    }
 
-        info!("Using overrides file: {}", path.to_string_lossy());
+    /// Loads overrides from the given file, merged with all JSON and TOML files in the corresponding ".d" directory
+    /// (for example, "overrides.d" for "overrides.json"), if it exists.
+    pub fn load_from_disk_named(filename: &str) -> Result<Self, String> {
+        let path = get_overrides_path(filename).ok();
+        let directory = get_overrides_path(&Path::new(filename).with_extension("d").to_string_lossy())
+            .ok()
+            .filter(|path| path.is_dir());
 
-        let contents = match read_to_string(path) {
-            Ok(string) => string,
-            Err(error) => return Err(error.to_string()),
-        };
+        if path.is_none() && directory.is_none() {
+            return Err(format!("No overrides file was found: {}", filename));
+        }
 
-        let overrides: OverrideValues = match serde_json::from_str(&contents) {
-            Ok(overrides) => overrides,
-            Err(error) => return Err(error.to_string()),
-        };
+        let mut files = Vec::new();
+        if let Some(path) = path {
+            files.push(path.to_path_buf());
+        }
+
+        if let Some(directory) = directory {
+            let entries = match std::fs::read_dir(&directory) {
+                Ok(entries) => entries,
+                Err(error) => return Err(error.to_string()),
+            };
+
+            let mut paths: Vec<PathBuf> = entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let supported = matches!(path.extension().and_then(|e| e.to_str()), Some("json" | "toml"));
+                    if !supported {
+                        debug!("Skipping unsupported overrides file: {}", path.to_string_lossy());
+                    }
+                    supported
+                })
+                .collect();
+            paths.sort();
+            files.extend(paths);
+        }
 
+        let mut merged = OverrideValues::new();
+        let mut origins = HashMap::new();
+        let mut conflicts = Vec::new();
+
+        for file in files {
+            info!("Using overrides file: {}", file.to_string_lossy());
+            let values = read_values(&file)?;
+            conflicts.extend(merge_values(&mut merged, &mut origins, values, &file.to_string_lossy()));
+        }
+
+        if !conflicts.is_empty() {
+            return Err(format!("Conflicting overrides: {}", conflicts.join("; ")));
+        }
+
+        Ok(Self::from_values(merged))
+    }
+
+    pub fn from_values(overrides: OverrideValues) -> Self {
         // initialize usage count for every override path with 0
         let mut stats: OverrideStats = HashMap::new();
         for (release, ros) in &overrides {
             for (arch, aos) in ros {
                 for (broken, bos) in aos {
-                    match bos {
-                        OverrideEntry::All(_) => {
+                    match bos.target() {
+                        OverrideTarget::All(_) => {
                             stats.insert(opath_to_str(release, arch, broken, "all"), 0);
                         },
-                        OverrideEntry::Packages(entries) => {
+                        OverrideTarget::Packages(entries) => {
                             for entry in entries {
                                 stats.insert(opath_to_str(release, arch, broken, entry), 0);
                             }
@@ -61,170 +177,284 @@ impl Overrides {
             }
         }
 
-        Ok(Overrides { data: overrides, stats })
-    }
-
-    pub fn lookup(&mut self, release: &str, arch: &str, package: &str, broken: &str) -> bool {
-        // extract and validate release- and / or arch-specific and unspecific overrides
-
-        let all_release = match self.data.get("all") {
-            Some(overrides) => overrides,
-            None => {
-                error!("Overrides configuration invalid or incomplete for release 'all'.");
-                return false;
-            },
-        };
-
-        let all_release_all_arch = match all_release.get("all") {
-            Some(overrides) => overrides,
-            None => {
-                error!("Overrides configuration invalid or incomplete for 'all/all'.");
-                return false;
-            },
+        let overrides = Overrides {
+            data: overrides,
+            stats,
+            package_stats: HashMap::new(),
+            missing: HashSet::new(),
         };
 
-        let all_release_per_arch = match all_release.get(arch) {
-            Some(overrides) => overrides,
-            None => {
-                error!("Overrides configuration invalid or incomplete for 'all/{}'.", arch);
-                return false;
-            },
-        };
-
-        let per_release = match self.data.get(release) {
-            Some(overrides) => overrides,
-            None => {
-                error!(
-                    "Overrides configuration is invalid or incomplete for release '{}'.",
-                    release
-                );
-                return false;
-            },
-        };
+        info!("Loaded override sections: {}", overrides.sections().join(", "));
 
-        let per_release_all_arch = match per_release.get("all") {
-            Some(overrides) => overrides,
-            None => {
-                error!("Overrides configuration invalid or incomplete for '{}/all'.", release);
-                return false;
-            },
-        };
-
-        let per_release_per_arch = match per_release.get(arch) {
-            Some(overrides) => overrides,
-            None => {
-                error!(
-                    "Overrides configuration invalid or incomplete for '{}/{}'.",
-                    release, arch
-                );
-                return false;
-            },
-        };
+        overrides
+    }
 
-        // check arguments against overrides (most specific overrides first)
+    /// Returns the "release/arch" paths of all sections that are present, sorted by name.
+    pub fn sections(&self) -> Vec<String> {
+        let mut sections: Vec<String> = self
+            .data
+            .iter()
+            .flat_map(|(release, ros)| ros.keys().map(move |arch| format!("{}/{}", release, arch)))
+            .collect();
+        sections.sort();
+        sections
+    }
 
-        // check release- and arch-specific overrides
-        if let Some(entry) = per_release_per_arch.get(broken) {
-            let matched = match entry {
-                OverrideEntry::All(_) => true,
-                OverrideEntry::Packages(packages) => packages.contains(&package.to_owned()),
-            };
+    /// Checks whether an unresolved dependency of a package is overridden.
+    ///
+    /// If the package that used to provide the dependency is known, overrides for `provider:<name>` match as well, so
+    /// a single entry covers all capabilities that a removed package used to provide. Similarly, overrides for
+    /// `module:<name>:<stream>` match all unresolved dependencies of packages that belong to the module stream.
+    pub fn lookup(
+        &mut self,
+        release: &str,
+        arch: &str,
+        package: &str,
+        broken: &str,
+        provider: Option<&str>,
+        module: Option<&str>,
+    ) -> bool {
+        if self.lookup_exact(release, arch, package, broken) {
+            return true;
+        }
 
-            if matched {
-                let path = opath_to_str(release, arch, broken, package);
-                self.stats
-                    .entry(path.to_owned())
-                    .and_modify(|count| *count += 1)
-                    .or_insert_with(|| {
-                        error!("Failed to match override path in stats: {}", path);
-                        1
-                    });
-
-                debug!(
-                    "Matched override for {} / {} / {} / {}.",
-                    release, arch, broken, package
-                );
+        if let Some(provider) = provider {
+            if self.lookup_exact(release, arch, package, &format!("{}{}", PROVIDER_PREFIX, provider)) {
                 return true;
             }
         }
 
-        // check release-specific overrides
-        if let Some(entry) = per_release_all_arch.get(broken) {
-            let matched = match entry {
-                OverrideEntry::All(_) => true,
-                OverrideEntry::Packages(packages) => packages.contains(&package.to_owned()),
-            };
-
-            if matched {
-                let path = opath_to_str(release, "all", broken, package);
-                self.stats
-                    .entry(path.to_owned())
-                    .and_modify(|count| *count += 1)
-                    .or_insert_with(|| {
-                        error!("Failed to match override path in stats: {}", path);
-                        1
-                    });
-
-                debug!(
-                    "Matched override for {} / {} / {} / {}.",
-                    release, "all", broken, package
-                );
+        if let Some(module) = module {
+            if self.lookup_exact(release, arch, package, &format!("{}{}", MODULE_PREFIX, module)) {
                 return true;
             }
         }
 
-        // check arch-specific overrides
-        if let Some(entry) = all_release_per_arch.get(broken) {
-            let matched = match entry {
-                OverrideEntry::All(_) => true,
-                OverrideEntry::Packages(packages) => packages.contains(&package.to_owned()),
+        // rich dependencies are also matched if overrides exist for all of their required operands
+        if broken.starts_with('(') {
+            if let Ok(rich) = parse_rich_dep(broken) {
+                return rich.is_covered(&mut |operand| self.lookup_exact(release, arch, package, operand));
+            }
+        }
+
+        false
+    }
+
+    /// Resolves the overrides that apply to a release and architecture (see [`Overrides::lookup`] for the order in
+    /// which sections are checked): an override for all packages in any section wins, and otherwise the package lists
+    /// of all sections are combined.
+    pub fn effective(&self, release: &str, arch: &str) -> BTreeMap<String, EffectiveOverride> {
+        let scopes = [(release, arch), (release, "all"), ("all", arch), ("all", "all")];
+        let mut effective: BTreeMap<String, EffectiveOverride> = BTreeMap::new();
+
+        for (scope_release, scope_arch) in scopes {
+            let overrides = match self.data.get(scope_release).and_then(|section| section.get(scope_arch)) {
+                Some(overrides) => overrides,
+                None => continue,
             };
 
-            if matched {
-                let path = opath_to_str("all", arch, broken, package);
-                self.stats
-                    .entry(path.to_owned())
-                    .and_modify(|count| *count += 1)
-                    .or_insert_with(|| {
-                        error!("Failed to match override path in stats: {}", path);
-                        1
-                    });
-
-                debug!("Matched override for {} / {} / {} / {}.", "all", arch, broken, package);
-                return true;
+            let section = format!("{}/{}", scope_release, scope_arch);
+
+            for (broken, entry) in overrides {
+                let current = effective.entry(broken.clone()).or_insert_with(|| EffectiveOverride {
+                    entry: OverrideTarget::Packages(Vec::new()),
+                    sections: Vec::new(),
+                });
+
+                // the same section can be checked twice if the release or architecture is "all"
+                if current.sections.contains(&section) {
+                    continue;
+                }
+                current.sections.push(section.clone());
+
+                match (&mut current.entry, entry.target()) {
+                    (OverrideTarget::All(_), _) => {},
+                    (_, OverrideTarget::All(value)) => current.entry = OverrideTarget::All(value.clone()),
+                    (OverrideTarget::Packages(current), OverrideTarget::Packages(packages)) => {
+                        current.extend(packages.iter().cloned());
+                        current.sort();
+                        current.dedup();
+                    },
+                }
             }
         }
 
-        // check generic overrides
-        if let Some(entry) = all_release_all_arch.get(broken) {
-            let matched = match entry {
-                OverrideEntry::All(_) => true,
-                OverrideEntry::Packages(packages) => packages.contains(&package.to_owned()),
+        effective
+    }
+
+    fn lookup_exact(&mut self, release: &str, arch: &str, package: &str, broken: &str) -> bool {
+        // check arguments against overrides (most specific overrides first):
+        // release- and arch-specific, release-specific, arch-specific, and generic overrides
+        let scopes = [(release, arch), (release, "all"), ("all", arch), ("all", "all")];
+
+        for (scope_release, scope_arch) in scopes {
+            // missing sections are valid and treated like empty ones
+            let overrides = match self.data.get(scope_release).and_then(|section| section.get(scope_arch)) {
+                Some(overrides) => overrides,
+                None => {
+                    let section = format!("{}/{}", scope_release, scope_arch);
+                    if self.missing.is_empty() {
+                        // only warn once per load, because minimal overrides files are valid
+                        warn!(
+                            "No overrides for {}, treating missing sections as empty (available sections: {}).",
+                            section,
+                            self.sections().join(", ")
+                        );
+                    } else if !self.missing.contains(&section) {
+                        debug!("No overrides for {}, treating it as empty.", section);
+                    }
+                    self.missing.insert(section);
+                    continue;
+                },
             };
 
-            if matched {
-                let path = opath_to_str("all", "all", broken, package);
-                self.stats
-                    .entry(path.to_owned())
-                    .and_modify(|count| *count += 1)
-                    .or_insert_with(|| {
-                        error!("Failed to match override path in stats: {}", path);
-                        1
-                    });
-
-                debug!("Matched override for {} / {} / {} / {}.", "all", "all", broken, package);
-                return true;
+            let entry = match overrides.get(broken) {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            let path = match entry.target() {
+                OverrideTarget::All(_) => opath_to_str(scope_release, scope_arch, broken, "all"),
+                OverrideTarget::Packages(packages) if packages.iter().any(|p| p == package) => {
+                    opath_to_str(scope_release, scope_arch, broken, package)
+                },
+                OverrideTarget::Packages(_) => continue,
+            };
+
+            self.stats
+                .entry(path.clone())
+                .and_modify(|count| *count += 1)
+                .or_insert_with(|| {
+                    error!("Failed to match override path in stats: {}", path);
+                    1
+                });
+
+            // hits of blanket overrides are also counted for every package they matched
+            if let OverrideTarget::All(_) = entry.target() {
+                *self
+                    .package_stats
+                    .entry(opath_to_str(scope_release, scope_arch, broken, package))
+                    .or_insert(0) += 1;
             }
+
+            debug!(
+                "Matched override for {} / {} / {} / {}.",
+                scope_release, scope_arch, broken, package
+            );
+            return true;
         }
 
         false
     }
 }
 
-fn get_overrides_path() -> Result<Box<Path>, String> {
+/// Loads the separate overrides files for all configured products.
+pub fn load_product_overrides(products: &[ProductConfig]) -> Result<HashMap<String, Arc<RwLock<Overrides>>>, String> {
+    let mut overrides = HashMap::new();
+
+    for product in products {
+        let values = Overrides::load_from_disk_named(&product.overrides)
+            .map_err(|error| format!("Failed to load overrides for {}: {}", &product.name, error))?;
+        overrides.insert(product.name.clone(), Arc::new(RwLock::new(values)));
+    }
+
+    Ok(overrides)
+}
+
+/// Reads overrides from a JSON or TOML file (based on its extension).
+fn read_values(path: &Path) -> Result<OverrideValues, String> {
+    let contents = match read_to_string(path) {
+        Ok(string) => string,
+        Err(error) => return Err(format!("Failed to read {}: {}", path.to_string_lossy(), error)),
+    };
+
+    let result = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => basic_toml::from_str(&contents).map_err(|error| error.to_string()),
+        _ => serde_json::from_str(&contents).map_err(|error| error.to_string()),
+    };
+
+    match result {
+        Ok(values) => Ok(values),
+        Err(error) => Err(format!("Failed to parse {}: {}", path.to_string_lossy(), error)),
+    }
+}
+
+/// Merges overrides from one file into the overrides from all previous files, and returns conflicts between them.
+///
+/// Lists of packages for the same unresolved dependency are combined, but the same package can only be listed in one
+/// file, and an override for all packages can not be combined with any other override for the same dependency. The
+/// file that each override path came from is tracked in `origins`. If package lists are combined, the information
+/// about who added them is kept from the first file.
+fn merge_values(
+    merged: &mut OverrideValues,
+    origins: &mut HashMap<String, String>,
+    values: OverrideValues,
+    source: &str,
+) -> Vec<String> {
+    let mut conflicts = Vec::new();
+
+    for (release, ros) in values {
+        for (arch, aos) in ros {
+            for (broken, entry) in aos {
+                let section = merged
+                    .entry(release.clone())
+                    .or_default()
+                    .entry(arch.clone())
+                    .or_default();
+
+                let existing = match section.get_mut(&broken) {
+                    Some(existing) => existing,
+                    None => {
+                        let packages = match entry.target() {
+                            OverrideTarget::All(_) => vec![String::from("all")],
+                            OverrideTarget::Packages(packages) => packages.clone(),
+                        };
+                        for package in packages {
+                            origins.insert(opath_to_str(&release, &arch, &broken, &package), source.to_string());
+                        }
+                        section.insert(broken, entry);
+                        continue;
+                    },
+                };
+
+                let prefix = opath_to_str(&release, &arch, &broken, "");
+                let previous = origins
+                    .iter()
+                    .find(|(path, _)| path.starts_with(&prefix))
+                    .map(|(_, origin)| origin.clone())
+                    .unwrap_or_default();
+
+                match (existing.target_mut(), entry.into_target()) {
+                    (OverrideTarget::Packages(existing), OverrideTarget::Packages(packages)) => {
+                        for package in packages {
+                            let path = opath_to_str(&release, &arch, &broken, &package);
+                            match origins.get(&path) {
+                                Some(origin) => {
+                                    conflicts.push(format!("{} is defined in {} and {}", path, origin, source))
+                                },
+                                None => {
+                                    origins.insert(path, source.to_string());
+                                    existing.push(package);
+                                },
+                            }
+                        }
+                    },
+                    _ => conflicts.push(format!(
+                        "{}/{}/{} is overridden for all packages in {} or {}, which conflicts with other overrides",
+                        release, arch, broken, previous, source
+                    )),
+                }
+            }
+        }
+    }
+
+    conflicts
+}
+
+fn get_overrides_path(filename: &str) -> Result<Box<Path>, String> {
     let local = {
         let mut path = std::env::current_dir().map_err(|error| error.to_string())?;
-        path.push(OVERRIDES_FILENAME);
+        path.push(filename);
         path
     };
 
@@ -235,7 +465,7 @@ fn get_overrides_path() -> Result<Box<Path>, String> {
     let site = {
         let mut path = PathBuf::new();
         path.push("/etc/repochecker/");
-        path.push(OVERRIDES_FILENAME);
+        path.push(filename);
         path
     };
 
@@ -246,7 +476,7 @@ fn get_overrides_path() -> Result<Box<Path>, String> {
     let default = {
         let mut path = PathBuf::new();
         path.push("/usr/share/repochecker/");
-        path.push(OVERRIDES_FILENAME);
+        path.push(filename);
         path
     };
 
@@ -254,9 +484,174 @@ fn get_overrides_path() -> Result<Box<Path>, String> {
         return Ok(default.into_boxed_path());
     }
 
-    Err(String::from("No overrides file was found."))
+    Err(format!("No overrides file was found: {}", filename))
 }
 
 fn opath_to_str(release: &str, arch: &str, broken: &str, package: &str) -> String {
     format!("{}/{}/{}/{}", release, arch, broken, package)
 }
+
+/// Returns a JSON Schema for overrides files.
+pub fn schema(comment: &str) -> Value {
+    schema_for::<OverrideValues>("Overrides", comment)
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    const OVERRIDES: &str = r#"{
+        "all": {
+            "all": { "libfoo.so.1()(64bit)": "all" },
+            "x86_64": {}
+        },
+        "41": {
+            "all": { "perl(Bar)": ["bar", "baz"] },
+            "x86_64": {}
+        }
+    }"#;
+
+    #[test]
+    fn lookup_stats() {
+        let mut overrides = Overrides::from_values(serde_json::from_str(OVERRIDES).unwrap());
+
+        assert!(overrides.lookup("41", "x86_64", "foo", "libfoo.so.1()(64bit)", None, None));
+        assert!(overrides.lookup("41", "x86_64", "qux", "libfoo.so.1()(64bit)", None, None));
+        assert!(overrides.lookup("41", "x86_64", "bar", "perl(Bar)", None, None));
+        assert!(!overrides.lookup("41", "x86_64", "qux", "perl(Bar)", None, None));
+
+        assert_eq!(overrides.stats["all/all/libfoo.so.1()(64bit)/all"], 2);
+        assert_eq!(overrides.stats["41/all/perl(Bar)/bar"], 1);
+        assert_eq!(overrides.stats["41/all/perl(Bar)/baz"], 0);
+        assert_eq!(overrides.stats.len(), 3);
+
+        assert_eq!(overrides.package_stats["all/all/libfoo.so.1()(64bit)/foo"], 1);
+        assert_eq!(overrides.package_stats["all/all/libfoo.so.1()(64bit)/qux"], 1);
+        assert_eq!(overrides.package_stats.len(), 2);
+    }
+
+    #[test]
+    fn merge_values() {
+        let mut merged = OverrideValues::new();
+        let mut origins = HashMap::new();
+
+        let conflicts = super::merge_values(
+            &mut merged,
+            &mut origins,
+            serde_json::from_str(OVERRIDES).unwrap(),
+            "overrides.json",
+        );
+        assert!(conflicts.is_empty());
+
+        let toml = r#"
+            [41.all]
+            "perl(Bar)" = ["qux"]
+
+            [41.x86_64]
+            "perl(Foo)" = "all"
+        "#;
+        let conflicts = super::merge_values(
+            &mut merged,
+            &mut origins,
+            basic_toml::from_str(toml).unwrap(),
+            "perl.toml",
+        );
+        assert!(conflicts.is_empty());
+
+        let mut overrides = Overrides::from_values(merged.clone());
+        assert!(overrides.lookup("41", "x86_64", "qux", "perl(Bar)", None, None));
+        assert!(overrides.lookup("41", "x86_64", "qux", "perl(Foo)", None, None));
+
+        let conflicting = r#"{ "41": { "all": { "perl(Bar)": ["bar"] }, "x86_64": { "perl(Foo)": ["foo"] } } }"#;
+        let conflicts = super::merge_values(
+            &mut merged,
+            &mut origins,
+            serde_json::from_str(conflicting).unwrap(),
+            "other.json",
+        );
+        assert_eq!(conflicts.len(), 2);
+    }
+
+    #[test]
+    fn effective() {
+        let values = r#"{
+            "all": { "all": { "perl(Bar)": ["qux"], "perl(Foo)": ["foo"] } },
+            "41": { "x86_64": { "perl(Bar)": ["bar"], "perl(Foo)": "all" } }
+        }"#;
+        let overrides = Overrides::from_values(serde_json::from_str(values).unwrap());
+
+        let effective = overrides.effective("41", "x86_64");
+        assert!(
+            matches!(&effective["perl(Bar)"].entry, OverrideTarget::Packages(packages) if packages == &["bar", "qux"])
+        );
+        assert!(matches!(effective["perl(Foo)"].entry, OverrideTarget::All(_)));
+        assert_eq!(effective["perl(Foo)"].sections, vec!["41/x86_64", "all/all"]);
+
+        let effective = overrides.effective("41", "aarch64");
+        assert!(matches!(&effective["perl(Foo)"].entry, OverrideTarget::Packages(packages) if packages == &["foo"]));
+    }
+
+    #[test]
+    fn lookup_annotated() {
+        let values = r#"{
+            "all": { "all": {
+                "perl(Bar)": { "packages": ["bar"], "added_by": "alice", "added_on": "2024-05-01" },
+                "perl(Foo)": { "packages": "all" }
+            } }
+        }"#;
+        let mut overrides = Overrides::from_values(serde_json::from_str(values).unwrap());
+
+        assert!(overrides.lookup("41", "x86_64", "bar", "perl(Bar)", None, None));
+        assert!(!overrides.lookup("41", "x86_64", "baz", "perl(Bar)", None, None));
+        assert!(overrides.lookup("41", "x86_64", "baz", "perl(Foo)", None, None));
+        assert_eq!(overrides.stats["all/all/perl(Bar)/bar"], 1);
+
+        // provenance is kept when overrides are served again
+        let json = serde_json::to_value(&overrides.data).unwrap();
+        assert_eq!(json["all"]["all"]["perl(Bar)"]["added_by"], "alice");
+        assert_eq!(json["all"]["all"]["perl(Bar)"]["added_on"], "2024-05-01");
+        assert!(json["all"]["all"]["perl(Foo)"].get("added_by").is_none());
+
+        // typos in annotated overrides are rejected instead of being ignored
+        let typo = r#"{ "all": { "all": { "perl(Bar)": { "packages": ["bar"], "addedby": "alice" } } } }"#;
+        assert!(serde_json::from_str::<OverrideValues>(typo).is_err());
+    }
+
+    #[test]
+    fn lookup_missing_sections() {
+        let mut overrides =
+            Overrides::from_values(serde_json::from_str(r#"{ "41": { "all": { "perl(Bar)": "all" } } }"#).unwrap());
+
+        assert!(overrides.lookup("41", "aarch64", "bar", "perl(Bar)", None, None));
+        assert!(!overrides.lookup("40", "aarch64", "bar", "perl(Bar)", None, None));
+
+        assert_eq!(overrides.sections(), vec![String::from("41/all")]);
+    }
+
+    #[test]
+    fn lookup_provider() {
+        let mut overrides = Overrides::from_values(
+            serde_json::from_str(r#"{ "all": { "all": { "provider:libfoo": ["bar"] } } }"#).unwrap(),
+        );
+
+        assert!(overrides.lookup("41", "x86_64", "bar", "libfoo.so.1()(64bit)", Some("libfoo"), None));
+        assert!(overrides.lookup("41", "x86_64", "bar", "libfoo(x86-64)", Some("libfoo"), None));
+        assert!(!overrides.lookup("41", "x86_64", "bar", "libfoo.so.1()(64bit)", None, None));
+        assert!(!overrides.lookup("41", "x86_64", "baz", "libfoo.so.1()(64bit)", Some("libfoo"), None));
+
+        assert_eq!(overrides.stats["all/all/provider:libfoo/bar"], 2);
+    }
+
+    #[test]
+    fn lookup_module() {
+        let mut overrides = Overrides::from_values(
+            serde_json::from_str(r#"{ "all": { "all": { "module:nodejs:12": "all" } } }"#).unwrap(),
+        );
+
+        assert!(overrides.lookup("32", "x86_64", "nodejs", "libuv.so.1()(64bit)", None, Some("nodejs:12")));
+        assert!(!overrides.lookup("32", "x86_64", "nodejs", "libuv.so.1()(64bit)", None, Some("nodejs:14")));
+        assert!(!overrides.lookup("32", "x86_64", "nodejs", "libuv.so.1()(64bit)", None, None));
+    }
+}
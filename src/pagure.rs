@@ -1,6 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, PoisonError};
 
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::config::PagureConfig;
+use crate::http::Download;
+
+// user that orphaned packages are assigned to
+const ORPHAN: &str = "orphan";
+
+pub const POC_URL: &str = "https://src.fedoraproject.org/extras/pagure_poc.json";
+pub const BZ_URL: &str = "https://src.fedoraproject.org/extras/pagure_bz.json";
 
 #[derive(Debug, Deserialize)]
 struct PocPage {
@@ -20,54 +34,467 @@ struct MaintainerPage {
     // incomplete
 }
 
-pub async fn get_admins(timeout: u64) -> Result<HashMap<String, String>, String> {
-    let url = "https://src.fedoraproject.org/extras/pagure_poc.json";
+#[derive(Debug, Deserialize)]
+struct ProjectPage {
+    projects: Vec<Project>,
+    pagination: Pagination,
+    // incomplete
+}
 
-    let client: reqwest::Client = match reqwest::ClientBuilder::new()
-        .timeout(std::time::Duration::from_secs(timeout))
-        .build()
-    {
-        Ok(client) => client,
-        Err(error) => return Err(error.to_string()),
-    };
+#[derive(Debug, Deserialize)]
+struct Project {
+    name: String,
+    access_users: AccessUsers,
+    // incomplete
+}
 
-    let response = match client.get(url).send().await {
-        Ok(response) => response,
-        Err(error) => return Err(error.to_string()),
+#[derive(Debug, Deserialize)]
+struct AccessUsers {
+    #[serde(default)]
+    owner: Vec<String>,
+    #[serde(default)]
+    admin: Vec<String>,
+    #[serde(default)]
+    commit: Vec<String>,
+    #[serde(default)]
+    collaborator: Vec<String>,
+    // incomplete
+}
+
+#[derive(Debug, Deserialize)]
+struct Pagination {
+    next: Option<String>,
+    // incomplete
+}
+
+/// Changes of package maintainers between two refreshes, as a sanity check for the dumps and for reports.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AccountChurn {
+    pub refreshed_at: DateTime<Utc>,
+    // packages whose main admin changed (including packages that were orphaned)
+    pub admin_changed: usize,
+    pub orphaned: usize,
+    // packages whose list of maintainers changed
+    pub maintainers_changed: usize,
+    // packages that appeared in or disappeared from the dumps
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl AccountChurn {
+    pub fn is_empty(&self) -> bool {
+        self.admin_changed == 0 && self.maintainers_changed == 0 && self.added == 0 && self.removed == 0
+    }
+}
+
+/// Compares main admins and maintainers of all packages before and after a refresh.
+pub fn account_churn(
+    old_admins: &HashMap<String, String>,
+    new_admins: &HashMap<String, String>,
+    old_maintainers: &HashMap<String, Vec<String>>,
+    new_maintainers: &HashMap<String, Vec<String>>,
+) -> AccountChurn {
+    let mut churn = AccountChurn {
+        refreshed_at: Utc::now(),
+        admin_changed: 0,
+        orphaned: 0,
+        maintainers_changed: 0,
+        added: 0,
+        removed: 0,
     };
 
-    let pocs: PocPage = match serde_json::from_str(&response.text().await.map_err(|error| error.to_string())?) {
-        Ok(pocs) => pocs,
-        Err(error) => return Err(error.to_string()),
+    for (package, admin) in new_admins {
+        match old_admins.get(package) {
+            Some(old) if old != admin => {
+                churn.admin_changed += 1;
+                if admin == ORPHAN {
+                    churn.orphaned += 1;
+                }
+            },
+            Some(_) => {},
+            None => churn.added += 1,
+        }
+    }
+
+    churn.removed = old_admins
+        .keys()
+        .filter(|package| !new_admins.contains_key(*package))
+        .count();
+
+    // the order of maintainers is not significant
+    churn.maintainers_changed = new_maintainers
+        .iter()
+        .filter(|(package, maintainers)| match old_maintainers.get(*package) {
+            Some(old) => {
+                let old: HashSet<&String> = old.iter().collect();
+                let new: HashSet<&String> = maintainers.iter().collect();
+                old != new
+            },
+            None => false,
+        })
+        .count();
+
+    churn
+}
+
+/// Cache validators that were returned with the last response that was successfully processed.
+#[derive(Clone, Debug, Default)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    // number of packages in the last processed response
+    packages: Option<usize>,
+}
+
+impl Validators {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+
+        Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            packages: None,
+        }
+    }
+}
+
+/// Checks that package maintainer data is plausible before it replaces the data that is currently used.
+///
+/// Truncated or otherwise broken dumps would otherwise result in missing maintainers for most packages until the
+/// next refresh.
+fn check_plausible(
+    source: &str,
+    packages: usize,
+    previous: Option<usize>,
+    config: &PagureConfig,
+) -> Result<(), String> {
+    if packages < config.min_packages {
+        return Err(format!(
+            "Package maintainer data from {} is implausible: only {} packages (expected at least {}).",
+            source, packages, config.min_packages
+        ));
+    }
+
+    if let Some(previous) = previous {
+        if packages < previous && (previous - packages) as f64 >= previous as f64 * config.max_shrink {
+            return Err(format!(
+                "Package maintainer data from {} is implausible: shrank from {} to {} packages.",
+                source, previous, packages
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// validators of the last processed responses for every URL
+static VALIDATORS: Mutex<BTreeMap<&str, Validators>> = Mutex::new(BTreeMap::new());
+
+/// Checks whether a response is older than the given maximum age (in days), based on its `Last-Modified` header.
+fn is_stale(last_modified: Option<&str>, max_age: f64) -> bool {
+    let last_modified = match last_modified.and_then(|value| DateTime::parse_from_rfc2822(value).ok()) {
+        Some(last_modified) => last_modified.with_timezone(&Utc),
+        None => return false,
     };
 
-    Ok(pocs
-        .rpms
-        .into_iter()
-        .map(|(source, users)| (source, users.admin))
-        .collect())
+    (Utc::now() - last_modified).num_seconds() as f64 > max_age * 24.0 * 60.0 * 60.0
 }
 
-pub async fn get_maintainers(timeout: u64) -> Result<HashMap<String, Vec<String>>, String> {
-    let url = "https://src.fedoraproject.org/extras/pagure_bz.json";
+/// Fetches the contents of a URL, unless it has not been modified since the last time it was processed.
+///
+/// If a maximum age (in days) is given, contents that were last modified before that are treated as an error.
+async fn fetch_if_modified(
+    url: &str,
+    timeout: u64,
+    max_age: Option<f64>,
+) -> Result<Option<(String, Validators)>, String> {
+    let previous = VALIDATORS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(url)
+        .cloned()
+        .unwrap_or_default();
 
-    let client: reqwest::Client = match reqwest::ClientBuilder::new()
-        .timeout(std::time::Duration::from_secs(timeout))
-        .build()
+    let mut download = Download::new(url, timeout);
+    if let Some(etag) = previous
+        .etag
+        .as_deref()
+        .and_then(|etag| HeaderValue::from_str(etag).ok())
     {
-        Ok(client) => client,
-        Err(error) => return Err(error.to_string()),
+        download.headers.insert(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = previous
+        .last_modified
+        .as_deref()
+        .and_then(|last_modified| HeaderValue::from_str(last_modified).ok())
+    {
+        download.headers.insert(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let downloaded = download.fetch().await?;
+    let not_modified = downloaded.status == StatusCode::NOT_MODIFIED;
+
+    let validators = if not_modified {
+        previous
+    } else {
+        Validators {
+            packages: previous.packages,
+            ..Validators::from_headers(&downloaded.headers)
+        }
     };
 
-    let response = match client.get(url).send().await {
-        Ok(response) => response,
+    if let Some(max_age) = max_age {
+        if is_stale(validators.last_modified.as_deref(), max_age) {
+            return Err(format!(
+                "Contents of {} are stale (last modified: {}).",
+                url,
+                validators.last_modified.unwrap_or_default()
+            ));
+        }
+    }
+
+    if not_modified {
+        debug!("Skipping {}, which has not been modified.", url);
+        return Ok(None);
+    }
+
+    let text = String::from_utf8(downloaded.body).map_err(|error| error.to_string())?;
+
+    Ok(Some((text, validators)))
+}
+
+/// Stores validators for a URL after its contents were processed successfully.
+fn remember(url: &'static str, validators: Validators, packages: usize) {
+    VALIDATORS.lock().unwrap_or_else(PoisonError::into_inner).insert(
+        url,
+        Validators {
+            packages: Some(packages),
+            ..validators
+        },
+    );
+}
+
+/// Returns the main admins of all packages, or `None` if they have not changed since they were last fetched.
+async fn get_admins(
+    config: &PagureConfig,
+    timeout: u64,
+    max_age: Option<f64>,
+) -> Result<Option<HashMap<String, String>>, String> {
+    let (text, validators) = match fetch_if_modified(POC_URL, timeout, max_age).await? {
+        Some(fetched) => fetched,
+        None => return Ok(None),
+    };
+
+    let pocs: PocPage = match serde_json::from_str(&text) {
+        Ok(pocs) => pocs,
         Err(error) => return Err(error.to_string()),
     };
 
-    let page: MaintainerPage = match serde_json::from_str(&response.text().await.map_err(|error| error.to_string())?) {
+    // rejected dumps are not remembered, so they are fetched and checked again on the next refresh
+    let packages = pocs.rpms.len();
+    check_plausible(POC_URL, packages, validators.packages, config)?;
+    remember(POC_URL, validators, packages);
+
+    Ok(Some(
+        pocs.rpms
+            .into_iter()
+            .map(|(source, users)| (source, users.admin))
+            .collect(),
+    ))
+}
+
+/// Returns the maintainers of all packages, or `None` if they have not changed since they were last fetched.
+async fn get_maintainers(
+    config: &PagureConfig,
+    timeout: u64,
+    max_age: Option<f64>,
+) -> Result<Option<HashMap<String, Vec<String>>>, String> {
+    let (text, validators) = match fetch_if_modified(BZ_URL, timeout, max_age).await? {
+        Some(fetched) => fetched,
+        None => return Ok(None),
+    };
+
+    let page: MaintainerPage = match serde_json::from_str(&text) {
         Ok(page) => page,
         Err(error) => return Err(error.to_string()),
     };
 
-    Ok(page.rpms)
+    let packages = page.rpms.len();
+    check_plausible(BZ_URL, packages, validators.packages, config)?;
+    remember(BZ_URL, validators, packages);
+
+    Ok(Some(page.rpms))
+}
+
+/// Pages through all projects in the `rpms` namespace with the Pagure API, and returns their main admins and
+/// maintainers (in the same form as the `/extras/` dumps).
+async fn get_projects(
+    url: &str,
+    timeout: u64,
+) -> Result<(HashMap<String, String>, HashMap<String, Vec<String>>), String> {
+    let mut admins = HashMap::new();
+    let mut maintainers = HashMap::new();
+
+    let mut next = Some(format!("{}?namespace=rpms&per_page=100&fork=false", url));
+
+    while let Some(url) = next {
+        debug!("Fetching {}", &url);
+
+        let page: ProjectPage = Download::new(url, timeout).fetch_json().await?;
+
+        for project in page.projects {
+            let users = project.access_users;
+
+            if let Some(owner) = users.owner.first() {
+                admins.insert(project.name.clone(), owner.clone());
+            }
+
+            let mut all: Vec<String> = Vec::new();
+            for user in users
+                .owner
+                .into_iter()
+                .chain(users.admin)
+                .chain(users.commit)
+                .chain(users.collaborator)
+            {
+                if !all.contains(&user) {
+                    all.push(user);
+                }
+            }
+
+            maintainers.insert(project.name, all);
+        }
+
+        next = page.pagination.next;
+    }
+
+    Ok((admins, maintainers))
+}
+
+/// Returns the main admins and maintainers of all packages, or `None` for either of them if they have not changed
+/// since they were last fetched.
+///
+/// If the fallback to the Pagure API is enabled, it is used if the `/extras/` dumps are not available, or if they
+/// are older than the configured maximum age.
+#[allow(clippy::type_complexity)]
+pub async fn get_accounts(
+    config: &PagureConfig,
+    timeout: u64,
+) -> Result<(Option<HashMap<String, String>>, Option<HashMap<String, Vec<String>>>), String> {
+    let max_age = if config.api_fallback {
+        Some(config.max_age)
+    } else {
+        None
+    };
+
+    let (admins, maintainers) = tokio::join!(
+        get_admins(config, timeout, max_age),
+        get_maintainers(config, timeout, max_age)
+    );
+
+    match (admins, maintainers) {
+        (Err(error), _) | (_, Err(error)) if config.api_fallback => {
+            warn!("Falling back to the Pagure API for package maintainers: {}", error);
+            let (admins, maintainers) = get_projects(&config.api_url, timeout).await?;
+            check_plausible(&config.api_url, admins.len(), None, config)?;
+            Ok((Some(admins), Some(maintainers)))
+        },
+        (admins, maintainers) => combine_dumps(admins, maintainers),
+    }
+}
+
+/// Combines the results of fetching both dumps. If only one of them could be fetched, it is still returned, and the
+/// previous value is kept for the other one (it is returned as `None`, like a dump that has not changed).
+#[allow(clippy::type_complexity)]
+fn combine_dumps<A, M>(
+    admins: Result<Option<A>, String>,
+    maintainers: Result<Option<M>, String>,
+) -> Result<(Option<A>, Option<M>), String> {
+    match (admins, maintainers) {
+        (Ok(admins), Ok(maintainers)) => Ok((admins, maintainers)),
+        (Ok(admins), Err(error)) => {
+            warn!("Failed to fetch maintainers, keeping previous data: {}", error);
+            Ok((admins, None))
+        },
+        (Err(error), Ok(maintainers)) => {
+            warn!("Failed to fetch main admins, keeping previous data: {}", error);
+            Ok((None, maintainers))
+        },
+        (Err(error), Err(_)) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn account_churn() {
+        let map = |entries: &[(&str, &str)]| -> HashMap<String, String> {
+            entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        let lists = |entries: &[(&str, &[&str])]| -> HashMap<String, Vec<String>> {
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.iter().map(|user| user.to_string()).collect()))
+                .collect()
+        };
+
+        let churn = super::account_churn(
+            &map(&[("foo", "alice"), ("bar", "bob"), ("baz", "carol")]),
+            &map(&[("foo", "alice"), ("bar", "orphan"), ("qux", "dave")]),
+            &lists(&[("foo", &["alice", "bob"]), ("bar", &["bob"])]),
+            &lists(&[("foo", &["bob", "alice"]), ("bar", &["orphan"])]),
+        );
+
+        assert_eq!(churn.admin_changed, 1);
+        assert_eq!(churn.orphaned, 1);
+        assert_eq!(churn.maintainers_changed, 1);
+        assert_eq!(churn.added, 1);
+        assert_eq!(churn.removed, 1);
+        assert!(!churn.is_empty());
+    }
+
+    #[test]
+    fn combine_dumps() {
+        let admins: Result<Option<u32>, String> = Ok(Some(1));
+        let maintainers: Result<Option<u32>, String> = Err(String::from("timeout"));
+        assert_eq!(super::combine_dumps(admins, maintainers), Ok((Some(1), None)));
+
+        let admins: Result<Option<u32>, String> = Err(String::from("timeout"));
+        let maintainers: Result<Option<u32>, String> = Ok(Some(2));
+        assert_eq!(super::combine_dumps(admins, maintainers), Ok((None, Some(2))));
+
+        let admins: Result<Option<u32>, String> = Err(String::from("timeout"));
+        let maintainers: Result<Option<u32>, String> = Err(String::from("timeout"));
+        assert!(super::combine_dumps(admins, maintainers).is_err());
+    }
+
+    #[test]
+    fn check_plausible() {
+        let config = PagureConfig {
+            min_packages: 100,
+            ..Default::default()
+        };
+
+        assert!(super::check_plausible("test", 1000, None, &config).is_ok());
+        assert!(super::check_plausible("test", 50, None, &config).is_err());
+        assert!(super::check_plausible("test", 200, Some(1000), &config).is_ok());
+        assert!(super::check_plausible("test", 100, Some(1000), &config).is_err());
+    }
+
+    #[test]
+    fn is_stale() {
+        let recent = Utc::now().to_rfc2822();
+
+        assert!(super::is_stale(Some("Wed, 21 Oct 2015 07:28:00 GMT"), 3.0));
+        assert!(!super::is_stale(Some(&recent), 3.0));
+        assert!(!super::is_stale(Some("invalid"), 3.0));
+        assert!(!super::is_stale(None, 3.0));
+    }
 }
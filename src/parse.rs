@@ -1,4 +1,9 @@
-use crate::data::Package;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::data::{DepCategory, FailureReason, Package};
 
 #[derive(Debug, PartialEq)]
 pub(crate) struct ClosureItem {
@@ -51,9 +56,10 @@ pub(crate) fn parse_repoquery(string: &str) -> Result<Vec<Package>, String> {
 
     let mut packages: Vec<Package> = Vec::new();
     for line in lines {
-        let mut split = line.split(' ');
+        // fields are separated by tabs, since summary and license can contain spaces
+        let mut split = line.split('\t');
 
-        // match only exactly 6 components
+        // match only exactly 8 components
         match (
             split.next(),
             split.next(),
@@ -62,18 +68,17 @@ pub(crate) fn parse_repoquery(string: &str) -> Result<Vec<Package>, String> {
             split.next(),
             split.next(),
             split.next(),
+            split.next(),
+            split.next(),
         ) {
-            (Some(name), Some(source), Some(epoch), Some(version), Some(release), Some(arch), None) => {
+            // versions of available packages are not needed (broken packages are reported by repoclosure)
+            (Some(name), Some(source), Some(_), Some(_), Some(_), Some(arch), Some(summary), Some(license), None) => {
                 packages.push(Package {
                     name: name.to_string(),
                     source_name: source.to_string(),
-                    epoch: match epoch.parse() {
-                        Ok(value) => value,
-                        Err(error) => return Err(format!("Failed to parse Epoch value: {}", error)),
-                    },
-                    version: version.to_string(),
-                    release: release.to_string(),
                     arch: arch.to_string(),
+                    summary: summary.to_string(),
+                    license: license.to_string(),
                 })
             },
             _ => return Err(format!("Failed to parse line: {}", line)),
@@ -83,6 +88,54 @@ pub(crate) fn parse_repoquery(string: &str) -> Result<Vec<Package>, String> {
     Ok(packages)
 }
 
+/// Package in the machine-readable output of `dnf repoquery --json` (dnf5).
+#[derive(Debug, Deserialize)]
+struct JsonPackage {
+    name: String,
+    arch: String,
+    // either the name of the source package, or the file name of the source RPM (depending on the dnf version)
+    #[serde(default)]
+    source_name: Option<String>,
+    #[serde(default)]
+    sourcerpm: Option<String>,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    license: String,
+}
+
+pub(crate) fn parse_repoquery_json(string: &str) -> Result<Vec<Package>, String> {
+    let values: Vec<JsonPackage> = match serde_json::from_str(string) {
+        Ok(values) => values,
+        Err(error) => return Err(format!("Failed to parse JSON output: {}", error)),
+    };
+
+    let mut packages: Vec<Package> = Vec::new();
+    for value in values {
+        let source_name = match (value.source_name, value.sourcerpm) {
+            (Some(source_name), _) if !source_name.is_empty() => source_name,
+            (_, Some(sourcerpm)) if !sourcerpm.is_empty() => {
+                let nevra = sourcerpm.trim_end_matches(".rpm");
+                let (n, _, _, _, _) = parse_nevra(nevra)?;
+                n.to_string()
+            },
+            // source packages do not have a source package
+            _ if value.arch == "src" => value.name.clone(),
+            _ => return Err(format!("Failed to determine source package of {}", value.name)),
+        };
+
+        packages.push(Package {
+            name: value.name,
+            source_name,
+            arch: value.arch,
+            summary: value.summary,
+            license: value.license,
+        });
+    }
+
+    Ok(packages)
+}
+
 #[allow(clippy::many_single_char_names)]
 pub(crate) fn parse_repoclosure(string: &str) -> Result<Vec<ClosureItem>, String> {
     let lines = string.split('\n');
@@ -148,11 +201,760 @@ pub(crate) fn parse_repoclosure(string: &str) -> Result<Vec<ClosureItem>, String
     Ok(closure_items)
 }
 
+/// Returns the causes of an error that dnf reported on a line of its output (for example, "Failed to download metadata
+/// for repo 'fedora'" and "Curl error (6)"), or nothing if the line is not an error line.
+///
+/// dnf prefixes errors with "Error: " (which can be preceded by context added by repochecker), and lists errors of
+/// individual repositories with "- " (dnf) or ">>> " (dnf5). Causes are chained with ": ".
+fn dnf_error_causes(line: &str) -> Option<std::str::Split<'_, &'static str>> {
+    let line = line.trim();
+
+    let message = if let Some(message) = line.strip_prefix(">>> ").or_else(|| line.strip_prefix("- ")) {
+        message
+    } else if let Some(message) = line.strip_prefix("Error: ") {
+        message
+    } else {
+        line.split_once(": Error: ")?.1
+    };
+
+    Some(message.split(": "))
+}
+
+/// Classifies an error message of dnf by known failure patterns.
+///
+/// Patterns only match at the start of a cause of an error line, so that package names or dependencies that are
+/// mentioned in other output (for example, a package that is called "corrupt") are not mistaken for failures of dnf.
+pub(crate) fn classify_dnf_error(message: &str) -> FailureReason {
+    const METADATA_DOWNLOAD: [&str; 4] = [
+        "Failed to download metadata",
+        "Cannot download repomd.xml",
+        "Curl error",
+        "Librepo error",
+    ];
+    const CACHE_CORRUPTION: [&str; 4] = [
+        "Failed to load expired repos cache",
+        "Downloading successful, but checksum doesn't match",
+        "Checksum mismatch",
+        "Damaged repository",
+    ];
+
+    let causes: Vec<&str> = message.lines().filter_map(dnf_error_causes).flatten().collect();
+    let matches = |patterns: &[&str]| {
+        causes
+            .iter()
+            .any(|cause| patterns.iter().any(|pattern| cause.starts_with(pattern)))
+    };
+
+    if matches(&METADATA_DOWNLOAD) {
+        FailureReason::MetadataDownload
+    } else if matches(&CACHE_CORRUPTION) {
+        FailureReason::CacheCorruption
+    } else {
+        FailureReason::Other
+    }
+}
+
+/// Classifies an unresolved dependency string by the kind of capability it refers to.
+pub(crate) fn classify_dep(dep: &str) -> DepCategory {
+    let dep = dep.trim();
+
+    if dep.starts_with('(') {
+        return DepCategory::Rich;
+    }
+
+    // strip version constraints (e.g. "foo >= 1.0")
+    let name = dep.split(' ').next().unwrap_or(dep);
+
+    if name.starts_with('/') {
+        DepCategory::File
+    } else if name.contains(".so") && (name.starts_with("lib") || name.contains("()")) {
+        DepCategory::SharedLibrary
+    } else if name.starts_with("pkgconfig(") {
+        DepCategory::PkgConfig
+    } else if name.starts_with("python(") || (name.starts_with("python") && name.contains("dist(")) {
+        DepCategory::Python
+    } else if name.starts_with("perl(") {
+        // package names like "perl-Foo-Bar" are plain package dependencies, only the provides namespace is classified
+        DepCategory::Perl
+    } else if name.contains('(') {
+        DepCategory::Other
+    } else {
+        DepCategory::Package
+    }
+}
+
+/// Structured representation of RPM rich (boolean) dependencies, e.g. "(foo >= 1.0 if bar)".
+#[derive(Debug, PartialEq)]
+pub(crate) enum RichDep {
+    Capability(String),
+    And(Vec<RichDep>),
+    Or(Vec<RichDep>),
+    With(Vec<RichDep>),
+    Without(Box<RichDep>, Box<RichDep>),
+    If {
+        then: Box<RichDep>,
+        condition: Box<RichDep>,
+        otherwise: Option<Box<RichDep>>,
+    },
+    Unless {
+        then: Box<RichDep>,
+        condition: Box<RichDep>,
+        otherwise: Option<Box<RichDep>>,
+    },
+}
+
+const RICH_OPERATORS: [&str; 7] = ["and", "or", "if", "unless", "else", "with", "without"];
+
+impl RichDep {
+    /// Returns the names of all capabilities that can satisfy this dependency (ignoring version constraints and
+    /// conditions of "if" / "unless" expressions).
+    pub(crate) fn alternatives(&self) -> Vec<&str> {
+        match self {
+            RichDep::Capability(capability) => vec![capability.split(' ').next().unwrap_or(capability)],
+            RichDep::And(operands) | RichDep::Or(operands) | RichDep::With(operands) => {
+                operands.iter().flat_map(|operand| operand.alternatives()).collect()
+            },
+            RichDep::Without(operand, _) => operand.alternatives(),
+            RichDep::If { then, otherwise, .. } | RichDep::Unless { then, otherwise, .. } => {
+                let mut alternatives = then.alternatives();
+                if let Some(otherwise) = otherwise {
+                    alternatives.extend(otherwise.alternatives());
+                }
+                alternatives
+            },
+        }
+    }
+
+    /// Checks whether this dependency is fully covered by capabilities that match (ignoring version constraints): all
+    /// operands of "and" / "with" expressions need to match, while any operand of "or" expressions is enough. Both
+    /// branches of "if" / "unless" expressions need to match, since it is not known which of them applies.
+    pub(crate) fn is_covered(&self, matches: &mut impl FnMut(&str) -> bool) -> bool {
+        match self {
+            RichDep::Capability(capability) => matches(capability.split(' ').next().unwrap_or(capability)),
+            RichDep::And(operands) | RichDep::With(operands) => {
+                operands.iter().all(|operand| operand.is_covered(matches))
+            },
+            RichDep::Or(operands) => operands.iter().any(|operand| operand.is_covered(matches)),
+            RichDep::Without(operand, _) => operand.is_covered(matches),
+            RichDep::If { then, otherwise, .. } | RichDep::Unless { then, otherwise, .. } => {
+                then.is_covered(matches) && otherwise.as_ref().is_none_or(|otherwise| otherwise.is_covered(matches))
+            },
+        }
+    }
+}
+
+struct RichParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> RichParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.input[self.position..].starts_with(' ') {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            Ok(())
+        } else {
+            Err(format!(
+                "Expected '{}' at position {} in rich dependency: {}",
+                c, self.position, self.input
+            ))
+        }
+    }
+
+    /// Reads a single whitespace-delimited word, keeping balanced parentheses (e.g. in "pkgconfig(foo)") intact.
+    fn word(&mut self) -> &'a str {
+        let start = self.position;
+        let mut depth = 0;
+
+        for c in self.input[start..].chars() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => break,
+                ')' => depth -= 1,
+                ' ' if depth == 0 => break,
+                _ => {},
+            }
+            self.position += c.len_utf8();
+        }
+
+        &self.input[start..self.position]
+    }
+
+    fn operator(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let start = self.position;
+        let word = self.word();
+
+        if RICH_OPERATORS.contains(&word) {
+            Some(word)
+        } else {
+            self.position = start;
+            None
+        }
+    }
+
+    fn term(&mut self) -> Result<RichDep, String> {
+        self.skip_whitespace();
+
+        if self.peek() == Some('(') {
+            return self.expression();
+        }
+
+        let name = self.word();
+        if name.is_empty() {
+            return Err(format!("Missing capability in rich dependency: {}", self.input));
+        }
+
+        // check for a version constraint
+        let start = self.position;
+        self.skip_whitespace();
+        let comparison = self.word();
+
+        if ["<", "<=", "=", ">=", ">"].contains(&comparison) {
+            self.skip_whitespace();
+            let version = self.word();
+            Ok(RichDep::Capability(format!("{} {} {}", name, comparison, version)))
+        } else {
+            self.position = start;
+            Ok(RichDep::Capability(name.to_string()))
+        }
+    }
+
+    fn expression(&mut self) -> Result<RichDep, String> {
+        self.expect('(')?;
+
+        let first = self.term()?;
+
+        let dep = match self.operator() {
+            None => first,
+            Some(operator @ ("and" | "or" | "with")) => {
+                let mut operands = vec![first, self.term()?];
+
+                while let Some(next) = self.operator() {
+                    if next != operator {
+                        return Err(format!("Mixed operators in rich dependency: {}", self.input));
+                    }
+                    operands.push(self.term()?);
+                }
+
+                match operator {
+                    "and" => RichDep::And(operands),
+                    "or" => RichDep::Or(operands),
+                    _ => RichDep::With(operands),
+                }
+            },
+            Some("without") => RichDep::Without(Box::new(first), Box::new(self.term()?)),
+            Some(operator @ ("if" | "unless")) => {
+                let then = Box::new(first);
+                let condition = Box::new(self.term()?);
+
+                let otherwise = match self.operator() {
+                    Some("else") => Some(Box::new(self.term()?)),
+                    Some(_) => return Err(format!("Unexpected operator in rich dependency: {}", self.input)),
+                    None => None,
+                };
+
+                if operator == "if" {
+                    RichDep::If {
+                        then,
+                        condition,
+                        otherwise,
+                    }
+                } else {
+                    RichDep::Unless {
+                        then,
+                        condition,
+                        otherwise,
+                    }
+                }
+            },
+            Some(_) => return Err(format!("Unexpected operator in rich dependency: {}", self.input)),
+        };
+
+        self.expect(')')?;
+        Ok(dep)
+    }
+}
+
+/// Parses a rich (boolean) dependency into its structured representation.
+pub(crate) fn parse_rich_dep(dep: &str) -> Result<RichDep, String> {
+    let dep = dep.trim();
+
+    if !dep.starts_with('(') {
+        return Err(format!("Not a rich dependency: {}", dep));
+    }
+
+    let mut parser = RichParser {
+        input: dep,
+        position: 0,
+    };
+    let parsed = parser.expression()?;
+
+    parser.skip_whitespace();
+    if parser.position != dep.len() {
+        return Err(format!("Trailing characters after rich dependency: {}", dep));
+    }
+
+    Ok(parsed)
+}
+
+/// Returns the individual capabilities a dependency can be satisfied by: the alternatives of a rich dependency that can
+/// be parsed, or the dependency itself otherwise.
+pub(crate) fn expand_alternatives(dep: &str) -> Vec<String> {
+    match parse_rich_dep(dep) {
+        Ok(rich) => rich.alternatives().into_iter().map(String::from).collect(),
+        Err(_) => vec![dep.to_string()],
+    }
+}
+
+/// Dependency information of a single package from a repoquery dump.
+#[derive(Debug, PartialEq)]
+pub(crate) struct DependencyRecord<'a> {
+    pub name: &'a str,
+    pub source: &'a str,
+    pub provides: Vec<&'a str>,
+    // versions of provided capabilities that are versioned (for example, "1.0-1.fc41" for "foo = 1.0-1.fc41")
+    pub provide_evrs: HashMap<&'a str, &'a str>,
+    pub requires: Vec<&'a str>,
+}
+
+/// Parses repoquery output produced with the "\x1e%{name}\x1f%{source_name}\x1f%{provides}\x1f%{requires}" query format, i.e. records
+/// separated by ASCII record separators, fields separated by ASCII unit separators, and one capability per line.
+///
+/// Only capability names are returned (version constraints are stripped), except for versions of provides.
+pub(crate) fn parse_dependency_dump(string: &str) -> Result<Vec<DependencyRecord<'_>>, String> {
+    fn capabilities(field: &str) -> Vec<&str> {
+        field
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                if line.starts_with('(') {
+                    line
+                } else {
+                    line.split(' ').next().unwrap_or(line)
+                }
+            })
+            .collect()
+    }
+
+    let mut records = Vec::new();
+
+    for record in string.split('\x1e') {
+        if record.trim().is_empty() {
+            continue;
+        }
+
+        let mut split = record.split('\x1f');
+
+        match (split.next(), split.next(), split.next(), split.next(), split.next()) {
+            (Some(name), Some(source), Some(provides), Some(requires), None) => records.push(DependencyRecord {
+                name: name.trim(),
+                source: source.trim(),
+                provides: capabilities(provides),
+                provide_evrs: provides
+                    .lines()
+                    .filter_map(|line| line.trim().split_once(" = "))
+                    .collect(),
+                requires: capabilities(requires),
+            }),
+            _ => return Err(format!("Failed to parse dependency record: {}", record)),
+        }
+    }
+
+    Ok(records)
+}
+
+/// Compares two version strings like rpm does (rpmvercmp): alphanumeric segments are compared one by one (numeric
+/// segments numerically, and newer than alphabetic ones), "~" sorts before anything, and "^" sorts after the end of a
+/// version, but before any other segment.
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+    let separator = |c: char| !c.is_ascii_alphanumeric() && c != '~' && c != '^';
+
+    let (mut a, mut b) = (a, b);
+
+    loop {
+        a = a.trim_start_matches(separator);
+        b = b.trim_start_matches(separator);
+
+        match (a.strip_prefix('~'), b.strip_prefix('~')) {
+            (Some(rest_a), Some(rest_b)) => {
+                (a, b) = (rest_a, rest_b);
+                continue;
+            },
+            (Some(_), None) => return Ordering::Less,
+            (None, Some(_)) => return Ordering::Greater,
+            (None, None) => {},
+        }
+
+        match (a.strip_prefix('^'), b.strip_prefix('^')) {
+            (Some(rest_a), Some(rest_b)) => {
+                (a, b) = (rest_a, rest_b);
+                continue;
+            },
+            (Some(_), None) if b.is_empty() => return Ordering::Greater,
+            (Some(_), None) => return Ordering::Less,
+            (None, Some(_)) if a.is_empty() => return Ordering::Less,
+            (None, Some(_)) => return Ordering::Greater,
+            (None, None) => {},
+        }
+
+        if a.is_empty() || b.is_empty() {
+            return a.len().cmp(&b.len());
+        }
+
+        let numeric = a.starts_with(|c: char| c.is_ascii_digit());
+        let segment = |s: &'_ str| -> usize {
+            s.find(|c: char| {
+                if numeric {
+                    !c.is_ascii_digit()
+                } else {
+                    !c.is_ascii_alphabetic()
+                }
+            })
+            .unwrap_or(s.len())
+        };
+
+        let (end_a, end_b) = (segment(a), segment(b));
+
+        // segments of different types: numeric segments are newer
+        if end_b == 0 {
+            return if numeric { Ordering::Greater } else { Ordering::Less };
+        }
+
+        let (segment_a, segment_b) = (&a[..end_a], &b[..end_b]);
+
+        let ordering = if numeric {
+            let segment_a = segment_a.trim_start_matches('0');
+            let segment_b = segment_b.trim_start_matches('0');
+            segment_a.len().cmp(&segment_b.len()).then(segment_a.cmp(segment_b))
+        } else {
+            segment_a.cmp(segment_b)
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+
+        (a, b) = (&a[end_a..], &b[end_b..]);
+    }
+}
+
+/// Compares two `[epoch:]version[-release]` strings like rpm does. The release is only compared if both have one.
+pub(crate) fn compare_evrs(a: &str, b: &str) -> Ordering {
+    fn split(evr: &str) -> (u64, &str, Option<&str>) {
+        let (epoch, rest) = match evr.split_once(':') {
+            Some((epoch, rest)) => (epoch.parse().unwrap_or_default(), rest),
+            None => (0, evr),
+        };
+
+        match rest.rsplit_once('-') {
+            Some((version, release)) => (epoch, version, Some(release)),
+            None => (epoch, rest, None),
+        }
+    }
+
+    let (epoch_a, version_a, release_a) = split(a);
+    let (epoch_b, version_b, release_b) = split(b);
+
+    epoch_a
+        .cmp(&epoch_b)
+        .then_with(|| compare_versions(version_a, version_b))
+        .then_with(|| match (release_a, release_b) {
+            (Some(release_a), Some(release_b)) => compare_versions(release_a, release_b),
+            _ => Ordering::Equal,
+        })
+}
+
+/// Checks whether a provided capability with the given version (if it is versioned) satisfies a versioned dependency
+/// (for example, "foo >= 2.0"). Dependencies without version constraints are satisfied by all versions, and so are all
+/// dependencies by unversioned provides.
+pub(crate) fn satisfies(provided: Option<&str>, dep: &str) -> bool {
+    let mut parts = dep.split(' ');
+
+    let (operator, required) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(_), Some(operator), Some(required)) => (operator, required),
+        _ => return true,
+    };
+
+    let provided = match provided {
+        Some(provided) => provided,
+        None => return true,
+    };
+
+    let ordering = compare_evrs(provided, required);
+
+    match operator {
+        "=" | "==" => ordering == Ordering::Equal,
+        "<" => ordering == Ordering::Less,
+        "<=" => ordering != Ordering::Greater,
+        ">" => ordering == Ordering::Greater,
+        ">=" => ordering != Ordering::Less,
+        _ => false,
+    }
+}
+
+/// Splits a shared library dependency (e.g. "libfoo.so.1()(64bit)") into the library basename ("libfoo.so") and its
+/// soname ("libfoo.so.1").
+pub(crate) fn parse_soname(dep: &str) -> Option<(&str, &str)> {
+    let soname = dep.trim().split(['(', ' ']).next()?;
+
+    let index = soname.find(".so")?;
+    let (basename, rest) = soname.split_at(index + 3);
+
+    if !rest.is_empty() && !rest.starts_with('.') {
+        return None;
+    }
+
+    Some((basename, soname))
+}
+
+/// Parses the output of `dnf repoquery --groupmember` into a map from package names to the IDs of the comps groups
+/// they belong to.
+///
+/// Every package is printed as a NEVRA on its own line, followed by indented lines with the group IDs.
+pub(crate) fn parse_groupmember(string: &str) -> Result<HashMap<String, Vec<String>>, String> {
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    let mut current: Option<&str> = None;
+
+    for line in string.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            let package = match current {
+                Some(package) => package,
+                None => return Err(format!("Unexpected group without package: {}", line)),
+            };
+
+            let group = line.trim().trim_start_matches('@');
+            groups.entry(package.to_string()).or_default().push(group.to_string());
+        } else {
+            let (name, _, _, _, _) = parse_nevra(line.trim())?;
+            current = Some(name);
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Parses the output of `dnf module info` into a map from the NEVRAs of module artifacts to the module stream
+/// ("name:stream") they belong to.
+pub(crate) fn parse_module_info(string: &str) -> HashMap<String, String> {
+    let mut artifacts = HashMap::new();
+
+    let mut name: Option<&str> = None;
+    let mut stream: Option<&str> = None;
+    let mut key: Option<&str> = None;
+
+    for line in string.lines() {
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field.trim(), value.trim()),
+            None => {
+                // empty lines separate modules
+                name = None;
+                stream = None;
+                key = None;
+                continue;
+            },
+        };
+
+        // continuation lines have an empty field name
+        if !field.is_empty() {
+            key = Some(field);
+        }
+
+        match key {
+            Some("Name") => {
+                // a new module block starts, even if it was not separated by an empty line
+                name = Some(value);
+                stream = None;
+            },
+            // default and enabled streams are marked with "[d]" and "[e]"
+            Some("Stream") => stream = value.split_whitespace().next(),
+            Some("Artifacts") => {
+                if let (Some(name), Some(stream), false) = (name, stream, value.is_empty()) {
+                    artifacts.insert(value.to_string(), format!("{}:{}", name, stream));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    artifacts
+}
+
+/// Extracts the problems that prevented a transaction from being resolved from the error output of `dnf install`.
+///
+/// An empty list is returned if the transaction was resolved successfully (and was only aborted because of
+/// `--assumeno`).
+pub(crate) fn parse_install_problems(stderr: &str) -> Vec<String> {
+    if !stderr.lines().any(|line| line.starts_with("Error:")) {
+        return Vec::new();
+    }
+
+    let mut problems: Vec<String> = stderr
+        .lines()
+        .map(str::trim)
+        .filter_map(|line| {
+            if let Some(problem) = line.strip_prefix("- ") {
+                Some(problem.to_string())
+            } else if line.starts_with("No match for argument") {
+                Some(line.to_string())
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if problems.is_empty() {
+        problems.extend(
+            stderr
+                .lines()
+                .filter(|line| line.starts_with("Error:"))
+                .map(|line| line.trim_start_matches("Error:").trim().to_string())
+                .filter(|line| !line.is_empty()),
+        );
+    }
+
+    problems
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unwrap_used)]
 
     use super::ClosureItem;
+    use crate::data::DepCategory;
+
+    #[test]
+    fn parse_repoquery_json() {
+        let output = r#"[
+  {
+    "name": "foo",
+    "epoch": "1",
+    "version": "1.0",
+    "release": "1.fc42",
+    "arch": "x86_64",
+    "sourcerpm": "foo-1.0-1.fc42.src.rpm",
+    "summary": "Foo library",
+    "license": "MIT"
+  },
+  {
+    "name": "foo",
+    "epoch": 1,
+    "version": "1.0",
+    "release": "1.fc42",
+    "arch": "src",
+    "sourcerpm": ""
+  }
+]"#;
+
+        let packages = super::parse_repoquery_json(output).unwrap();
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].source_name, "foo");
+        assert_eq!(packages[0].license, "MIT");
+        assert_eq!(packages[1].source_name, "foo");
+
+        assert!(super::parse_repoquery_json(
+            r#"[{"name": "bar", "epoch": 0, "version": "1", "release": "1", "arch": "noarch"}]"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_groupmember() {
+        let output = "\
+plasma-desktop-0:6.2.0-1.fc42.x86_64
+  @kde-desktop
+  @kde-desktop-environment
+foo-0:1.0-1.fc42.noarch
+bash-0:5.2.32-1.fc42.x86_64
+  @core
+";
+
+        let groups = super::parse_groupmember(output).unwrap();
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["plasma-desktop"], vec!["kde-desktop", "kde-desktop-environment"]);
+        assert_eq!(groups["bash"], vec!["core"]);
+    }
+
+    #[test]
+    fn parse_module_info() {
+        let output = "\
+Name             : nodejs
+Stream           : 12 [d]
+Version          : 3220200227173315
+Context          : f27b74a8
+Architecture     : x86_64
+Repo             : fedora-modular
+Artifacts        : nodejs-1:12.16.1-1.module_f32+8148+cd4ec1e9.src
+                 : nodejs-1:12.16.1-1.module_f32+8148+cd4ec1e9.x86_64
+
+Name             : perl
+Stream           : 5.30
+Artifacts        : perl-4:5.30.1-452.module_f32+7763+7b2bb8d8.x86_64
+
+Name             : broken
+Artifacts        : broken-1.0-1.module_f32+1+00000000.x86_64
+Name             : ruby
+Artifacts        : ruby-2.7.0-1.module_f32+2+00000000.x86_64
+Stream           : 2.7
+
+Artifacts        : orphan-1.0-1.module_f32+3+00000000.x86_64
+
+Hint: [d]efault, [e]nabled, [x]disabled, [i]nstalled
+";
+
+        let artifacts = super::parse_module_info(output);
+
+        // name and stream are not carried over from previous modules
+        assert_eq!(artifacts.len(), 3);
+        assert_eq!(
+            artifacts["nodejs-1:12.16.1-1.module_f32+8148+cd4ec1e9.x86_64"],
+            "nodejs:12"
+        );
+        assert_eq!(
+            artifacts["perl-4:5.30.1-452.module_f32+7763+7b2bb8d8.x86_64"],
+            "perl:5.30"
+        );
+    }
+
+    #[test]
+    fn parse_install_problems() {
+        let failed = "\
+Error: 
+ Problem: conflicting requests
+  - nothing provides libfoo.so.1()(64bit) needed by bar-1.0-1.fc41.x86_64 from fedora
+(try to add '--skip-broken' to skip uninstallable packages)";
+
+        assert_eq!(
+            super::parse_install_problems(failed),
+            vec!["nothing provides libfoo.so.1()(64bit) needed by bar-1.0-1.fc41.x86_64 from fedora"]
+        );
+
+        let missing = "No match for argument: baz\nError: Unable to find a match: baz";
+        assert_eq!(
+            super::parse_install_problems(missing),
+            vec!["No match for argument: baz"]
+        );
+
+        assert!(super::parse_install_problems("Operation aborted.").is_empty());
+    }
 
     #[test]
     fn parse_repoclosure() {
@@ -203,4 +1005,215 @@ package: asterisk-ices-17.3.0-1.fc32.x86_64 from fedora
 
         assert_eq!(super::parse_repoclosure(output).unwrap(), expected);
     }
+
+    #[test]
+    fn parse_repoquery() {
+        let output = "\
+python3-foo\tpython-foo\t0\t1.2.3\t1.fc41\tnoarch\tFoo library for Python 3\tMIT AND Apache-2.0
+foo\tfoo\t2\t0.1\t3.fc41\tsrc\tThe foo program\tGPL-3.0-or-later";
+
+        let packages = super::parse_repoquery(output).unwrap();
+
+        assert_eq!(packages.len(), 2);
+
+        assert_eq!(packages[0].name, "python3-foo");
+        assert_eq!(packages[0].source_name, "python-foo");
+        assert_eq!(packages[0].summary, "Foo library for Python 3");
+        assert_eq!(packages[0].license, "MIT AND Apache-2.0");
+
+        assert_eq!(packages[1].arch, "src");
+    }
+
+    #[test]
+    fn classify_dnf_error() {
+        use crate::data::FailureReason;
+
+        let cases = [
+            (
+                "Error: Failed to download metadata for repo 'fedora': Cannot download repomd.xml: Curl error (6)",
+                FailureReason::MetadataDownload,
+            ),
+            (
+                "Error: Failed to load expired repos cache: Damaged repository",
+                FailureReason::CacheCorruption,
+            ),
+            (
+                "Error: Repoclosure ended with unresolved dependencies.",
+                FailureReason::Other,
+            ),
+            // errors of individual repositories (dnf and dnf5)
+            (
+                "Errors during downloading metadata for repository 'fedora':\n  - Curl error (6): Couldn't resolve host",
+                FailureReason::MetadataDownload,
+            ),
+            (
+                ">>> Curl error (6): Couldn't resolve host name",
+                FailureReason::MetadataDownload,
+            ),
+            // errors that were passed on with additional context
+            (
+                "dnf repoclosure for 41 / x86_64 exited with an error code: Error: Failed to load expired repos cache",
+                FailureReason::CacheCorruption,
+            ),
+            // patterns are not matched in other output
+            (
+                "Warning: skipped corrupt-foo-1.0-1.fc41.noarch, Curl error in changelog",
+                FailureReason::Other,
+            ),
+            (
+                "Error: Problem: package foo requires Curl error handling, but none of the providers can be installed",
+                FailureReason::Other,
+            ),
+        ];
+
+        for (message, reason) in cases {
+            assert_eq!(super::classify_dnf_error(message), reason, "{}", message);
+        }
+    }
+
+    #[test]
+    fn classify_dep() {
+        let cases = [
+            ("libfoo.so.1()(64bit)", DepCategory::SharedLibrary),
+            ("libbar.so.2(LIBBAR_2.0)(64bit)", DepCategory::SharedLibrary),
+            ("pkgconfig(glib-2.0)", DepCategory::PkgConfig),
+            ("python3.13dist(requests) >= 2.0", DepCategory::Python),
+            ("python(abi) = 3.12", DepCategory::Python),
+            ("perl(Foo::Bar)", DepCategory::Perl),
+            ("perl(:MODULE_COMPAT_5.40.0)", DepCategory::Perl),
+            ("perl-Foo-Bar", DepCategory::Package),
+            ("/usr/bin/foo", DepCategory::File),
+            ("(foo if bar)", DepCategory::Rich),
+            ("gstreamer-plugins-good", DepCategory::Package),
+            ("foo = 1.0-1.fc41", DepCategory::Package),
+            ("mvn(net.iharder:base64)", DepCategory::Other),
+        ];
+
+        for (dep, expected) in cases {
+            assert_eq!(super::classify_dep(dep), expected, "{}", dep);
+        }
+    }
+
+    #[test]
+    fn compare_evrs() {
+        use std::cmp::Ordering::*;
+
+        let cases = [
+            ("1.0", "1.0", Equal),
+            ("1.0", "1.0.1", Less),
+            ("1.10", "1.9", Greater),
+            ("1.010", "1.10", Equal),
+            ("1.0a", "1.0", Greater),
+            ("1.0", "1.a", Greater),
+            ("1.0~rc1", "1.0", Less),
+            ("1.0~rc1", "1.0~rc2", Less),
+            ("1.0^git1", "1.0", Greater),
+            ("1.0^git1", "1.0.1", Less),
+            ("1:1.0-1", "2.0-1", Greater),
+            ("1.0-2.fc41", "1.0-10.fc41", Less),
+            // the release is only compared if both have one
+            ("1.0-2.fc41", "1.0", Equal),
+        ];
+
+        for (a, b, ordering) in cases {
+            assert_eq!(super::compare_evrs(a, b), ordering, "{} <=> {}", a, b);
+        }
+    }
+
+    #[test]
+    fn satisfies() {
+        assert!(super::satisfies(Some("2.1-1.fc41"), "foo >= 2.0"));
+        assert!(!super::satisfies(Some("1.9-1.fc41"), "foo >= 2.0"));
+        assert!(super::satisfies(Some("2.0-1.fc41"), "foo = 2.0"));
+        assert!(!super::satisfies(Some("2.0-1.fc41"), "foo < 2.0"));
+        assert!(super::satisfies(Some("1.0"), "foo"));
+        assert!(super::satisfies(None, "foo >= 2.0"));
+    }
+
+    #[test]
+    fn parse_soname() {
+        assert_eq!(
+            super::parse_soname("libfoo.so.1()(64bit)"),
+            Some(("libfoo.so", "libfoo.so.1"))
+        );
+        assert_eq!(
+            super::parse_soname("libbar.so.2.1(LIBBAR_2.0)(64bit)"),
+            Some(("libbar.so", "libbar.so.2.1"))
+        );
+        assert_eq!(
+            super::parse_soname("libbaz-1.2.so"),
+            Some(("libbaz-1.2.so", "libbaz-1.2.so"))
+        );
+        assert_eq!(super::parse_soname("libsomething.source"), None);
+        assert_eq!(super::parse_soname("gstreamer-plugins-good"), None);
+    }
+
+    #[test]
+    fn parse_dependency_dump() {
+        let output = "\
+\x1efoo\x1ffoo\x1ffoo = 1.0-1.fc41\nlibfoo.so.1()(64bit)\x1fglibc
+\x1efoo-tools\x1ffoo\x1ffoo-tools = 1.0-1.fc41\x1ffoo = 1.0-1.fc41\nlibfoo.so.1()(64bit)
+";
+
+        let records = super::parse_dependency_dump(output).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].name, "foo");
+        assert_eq!(records[0].provides, vec!["foo", "libfoo.so.1()(64bit)"]);
+        assert_eq!(
+            records[0].provide_evrs,
+            std::collections::HashMap::from([("foo", "1.0-1.fc41")])
+        );
+        assert_eq!(records[0].requires, vec!["glibc"]);
+        assert_eq!(records[1].name, "foo-tools");
+        assert_eq!(records[1].source, "foo");
+        assert_eq!(records[1].requires, vec!["foo", "libfoo.so.1()(64bit)"]);
+    }
+
+    #[test]
+    fn parse_rich_dep() {
+        use super::RichDep::*;
+
+        let cap = |name: &str| Box::new(Capability(name.to_string()));
+
+        assert_eq!(
+            super::parse_rich_dep("(foo >= 1.0 if bar)").unwrap(),
+            If {
+                then: cap("foo >= 1.0"),
+                condition: cap("bar"),
+                otherwise: None,
+            }
+        );
+
+        assert_eq!(
+            super::parse_rich_dep("(pkgconfig(foo) or libfoo.so.1()(64bit) or (baz and qux))").unwrap(),
+            Or(vec![
+                Capability(String::from("pkgconfig(foo)")),
+                Capability(String::from("libfoo.so.1()(64bit)")),
+                And(vec![Capability(String::from("baz")), Capability(String::from("qux"))]),
+            ])
+        );
+
+        assert_eq!(
+            super::parse_rich_dep("(python3-foo unless python3-bar else python3-baz)")
+                .unwrap()
+                .alternatives(),
+            vec!["python3-foo", "python3-baz"]
+        );
+
+        let overridden = ["foo", "bar"];
+        let is_covered = |dep: &str| {
+            super::parse_rich_dep(dep)
+                .unwrap()
+                .is_covered(&mut |name| overridden.contains(&name))
+        };
+        assert!(is_covered("(foo and bar >= 1.0)"));
+        assert!(!is_covered("(foo and baz)"));
+        assert!(is_covered("(baz or (foo with bar))"));
+        assert!(!is_covered("(foo if qux else baz)"));
+
+        assert!(super::parse_rich_dep("(foo and bar or baz)").is_err());
+        assert!(super::parse_rich_dep("(foo if)").is_err());
+        assert!(super::parse_rich_dep("foo").is_err());
+    }
 }
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use log::debug;
+use tokio::process::Command;
+
+use crate::config::PublishConfig;
+use crate::utils::get_data_path;
+
+/// Returns the object key for a file in the data directory, which mirrors the layout of static exports.
+pub fn data_key(path: &Path) -> Result<String, String> {
+    match path.strip_prefix(get_data_path()) {
+        Ok(relative) => Ok(format!("data/{}", relative.to_string_lossy())),
+        Err(_) => Err(format!("Not a data file: {}", path.to_string_lossy())),
+    }
+}
+
+/// Uploads a file to the configured bucket with the AWS CLI.
+///
+/// The configured prefix is prepended to the given object key.
+pub async fn publish_file(config: &PublishConfig, path: &Path, key: &str, content_type: &str) -> Result<(), String> {
+    let target = format!("s3://{}/{}{}", config.bucket, config.prefix, key);
+
+    let mut aws = Command::new("aws");
+
+    aws.arg("s3").arg("cp").arg(path).arg(&target);
+    aws.arg("--content-type").arg(content_type);
+    aws.arg("--only-show-errors");
+
+    if let Some(endpoint) = &config.endpoint {
+        aws.arg("--endpoint-url").arg(endpoint);
+    }
+
+    let output = aws.output().await.map_err(|error| error.to_string())?;
+
+    if !output.status.success() {
+        debug!(
+            "{}",
+            match String::from_utf8(output.stderr) {
+                Ok(string) => string,
+                Err(error) => format!("Failed to decode aws output: {}", error),
+            }
+        );
+
+        return Err(format!("Failed to upload {} to {}.", path.to_string_lossy(), target));
+    }
+
+    Ok(())
+}
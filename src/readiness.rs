@@ -0,0 +1,229 @@
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::Serialize;
+use tokio::process::Command;
+
+use crate::config::{Config, SigningConfig};
+use crate::http;
+use crate::pagure::{BZ_URL, POC_URL};
+use crate::repo::get_cache_root;
+use crate::utils::get_data_path;
+use crate::version::get_dnf_version;
+
+/// Timeout (in seconds) for checking whether an external endpoint is reachable.
+const ENDPOINT_TIMEOUT: u64 = 15;
+
+/// Result of checking one requirement of the service.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReadinessCheck {
+    pub name: String,
+    pub ready: bool,
+    pub detail: String,
+}
+
+/// Results of checking the requirements of the service on startup.
+#[derive(Clone, Debug, Serialize)]
+pub struct Readiness {
+    pub checked_at: DateTime<Utc>,
+    pub ready: bool,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+impl ReadinessCheck {
+    fn new(name: impl Into<String>, result: Result<String, String>) -> Self {
+        let (ready, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+
+        ReadinessCheck {
+            name: name.into(),
+            ready,
+            detail,
+        }
+    }
+}
+
+impl Readiness {
+    pub fn log(&self) {
+        for check in &self.checks {
+            if check.ready {
+                info!("Self-check passed for {}: {}", &check.name, &check.detail);
+            } else {
+                error!("Self-check failed for {}: {}", &check.name, &check.detail);
+            }
+        }
+
+        let failed = self.checks.iter().filter(|check| !check.ready).count();
+        if failed == 0 {
+            info!("All {} self-checks passed.", self.checks.len());
+        } else {
+            warn!(
+                "{} of {} self-checks failed, checking releases will probably fail.",
+                failed,
+                self.checks.len()
+            );
+        }
+    }
+}
+
+async fn check_dnf() -> Result<String, String> {
+    match get_dnf_version().await {
+        Some(version) => Ok(format!("dnf {}", version)),
+        None => Err(String::from("dnf is not installed or could not be run")),
+    }
+}
+
+/// Checks that gpg can be run and that the secret key that is used for signing data is available.
+async fn check_gpg(config: &SigningConfig) -> Result<String, String> {
+    let mut gpg = Command::new("gpg");
+
+    if let Some(homedir) = &config.homedir {
+        gpg.arg("--homedir").arg(homedir);
+    }
+
+    gpg.arg("--batch").arg("--list-secret-keys").arg(&config.key);
+
+    match gpg.output().await {
+        Ok(output) if output.status.success() => Ok(format!("secret key {} is available", &config.key)),
+        Ok(_) => Err(format!("secret key {} is not available", &config.key)),
+        Err(error) => Err(format!("gpg is not installed or could not be run: {}", error)),
+    }
+}
+
+/// Checks that the AWS CLI (which is used for publishing data) can be run.
+async fn check_aws() -> Result<String, String> {
+    match Command::new("aws").arg("--version").output().await {
+        Ok(output) if output.status.success() => {
+            // older versions print their version to stderr
+            let version = if output.stdout.is_empty() {
+                output.stderr
+            } else {
+                output.stdout
+            };
+            Ok(String::from_utf8_lossy(&version).trim().to_string())
+        },
+        Ok(_) => Err(String::from("aws exited with an error code")),
+        Err(error) => Err(format!("aws is not installed or could not be run: {}", error)),
+    }
+}
+
+/// Checks that files can be created in a directory (which is created if it does not exist yet).
+fn check_writable(path: &Path) -> Result<String, String> {
+    if let Err(error) = std::fs::create_dir_all(path) {
+        return Err(format!("Failed to create {}: {}", path.to_string_lossy(), error));
+    }
+
+    let probe = path.join(".repochecker-probe");
+    if let Err(error) = std::fs::write(&probe, b"") {
+        return Err(format!("{} is not writable: {}", path.to_string_lossy(), error));
+    }
+    let _ = std::fs::remove_file(&probe);
+
+    Ok(format!("{} is writable", path.to_string_lossy()))
+}
+
+async fn check_endpoint(url: String) -> Result<String, String> {
+    let client = http::client()?;
+
+    // only the response status is checked, without downloading the whole response
+    match client
+        .get(&url)
+        .timeout(Duration::from_secs(ENDPOINT_TIMEOUT))
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => Ok(format!("{} is reachable", url)),
+        Ok(response) => Err(format!("{} returned {}", url, response.status())),
+        Err(error) => Err(format!("{} is not reachable: {}", url, error)),
+    }
+}
+
+/// Returns the URLs of the external services that are used with the given configuration.
+fn endpoints(config: &Config) -> Vec<String> {
+    let mut urls = vec![POC_URL.to_string(), BZ_URL.to_string()];
+
+    if config.pagure.api_fallback {
+        urls.push(config.pagure.api_url.clone());
+    }
+
+    if let Some(inactivity) = &config.inactivity {
+        urls.push(inactivity.url.clone());
+    }
+
+    if let Some(critpath) = &config.critpath {
+        if critpath.source.starts_with("https://") || critpath.source.starts_with("http://") {
+            urls.push(critpath.source.clone());
+        }
+    }
+
+    urls
+}
+
+/// Checks that dnf (and gpg or the AWS CLI, if data is signed or published) is available, that the data and cache
+/// directories are writable, and that external services are reachable. External tools and services are not checked
+/// when using fake data.
+pub async fn check_readiness(config: &Config, fake_data: bool) -> Readiness {
+    let mut checks = Vec::new();
+
+    if !fake_data {
+        checks.push(ReadinessCheck::new("dnf", check_dnf().await));
+
+        if let Some(signing) = &config.signing {
+            checks.push(ReadinessCheck::new("gpg", check_gpg(signing).await));
+        }
+
+        if config.publish.is_some() {
+            checks.push(ReadinessCheck::new("aws", check_aws().await));
+        }
+    }
+
+    checks.push(ReadinessCheck::new("data directory", check_writable(&get_data_path())));
+
+    if !fake_data {
+        let cache = get_cache_root().and_then(|path| check_writable(&path));
+        checks.push(ReadinessCheck::new("cache directory", cache));
+
+        // endpoints are checked concurrently, so unreachable services do not delay startup for too long
+        let handles: Vec<_> = endpoints(config)
+            .into_iter()
+            .map(|url| tokio::spawn(check_endpoint(url)))
+            .collect();
+
+        for handle in handles {
+            let result = match handle.await {
+                Ok(result) => result,
+                Err(error) => Err(error.to_string()),
+            };
+            checks.push(ReadinessCheck::new("endpoint", result));
+        }
+    }
+
+    Readiness {
+        checked_at: Utc::now(),
+        ready: checks.iter().all(|check| check.ready),
+        checks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    #[test]
+    fn check_writable() {
+        let path = std::env::temp_dir().join(format!("repochecker-{}", uuid::Uuid::new_v4()));
+        assert!(super::check_writable(&path).is_ok());
+        assert!(!path.join(".repochecker-probe").exists());
+
+        // directories cannot be created below files
+        let file = path.join("file");
+        std::fs::write(&file, b"").unwrap();
+        assert!(super::check_writable(&file.join("data")).is_err());
+
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::config::get_config;
+use crate::data::{BrokenItem, DepCategory};
+use crate::feeds::maintainer_feed;
+use crate::reports::{
+    arch_diff, combine_testing, filter_arch, filter_category, filter_group, inactive_admins, new_in_testing,
+    regressions, soname_bumps, TableQuery,
+};
+use crate::templates::{table_export_path, Index, IndexEntry, ReleaseTable};
+use crate::utils::{read_frozen_from_file, read_json_from_file, DATA_DIRECTORIES};
+
+#[derive(Debug, PartialEq)]
+pub struct RenderArgs {
+    pub input: PathBuf,
+    pub output: PathBuf,
+}
+
+struct Snapshot {
+    values: Vec<BrokenItem>,
+    frozen_at: Option<DateTime<Utc>>,
+    modified: Option<SystemTime>,
+}
+
+fn read_snapshot(path: &Path) -> Result<Option<(String, Snapshot)>, String> {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+
+    let modified = std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+
+    if let Some(release) = name.strip_suffix(".json.gz") {
+        let frozen = read_frozen_from_file(path)?;
+        let snapshot = Snapshot {
+            values: frozen.items,
+            frozen_at: Some(frozen.frozen_at),
+            modified,
+        };
+        Ok(Some((release.to_string(), snapshot)))
+    } else if let Some(release) = name.strip_suffix(".json") {
+        let snapshot = Snapshot {
+            values: read_json_from_file(path)?,
+            frozen_at: None,
+            modified,
+        };
+        Ok(Some((release.to_string(), snapshot)))
+    } else {
+        Ok(None)
+    }
+}
+
+fn read_directory(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(path).map_err(|error| format!("{}: {}", path.to_string_lossy(), error))?;
+
+    let mut paths = Vec::new();
+    for entry in entries {
+        paths.push(entry.map_err(|error| error.to_string())?.path());
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Reads data for all releases (including data of frozen releases and of additional products) from a data directory.
+fn read_snapshots(input: &Path) -> Result<BTreeMap<String, Snapshot>, String> {
+    let mut snapshots = BTreeMap::new();
+
+    for path in read_directory(input)? {
+        if path.is_dir() {
+            let product = match path.file_name().and_then(|name| name.to_str()) {
+                Some(product) if !DATA_DIRECTORIES.contains(&product) => product.to_string(),
+                _ => continue,
+            };
+
+            for path in read_directory(&path)? {
+                if let Some((release, snapshot)) = read_snapshot(&path)? {
+                    snapshots.insert(format!("{}/{}", product, release), snapshot);
+                }
+            }
+        } else if let Some((release, snapshot)) = read_snapshot(&path)? {
+            snapshots.insert(release, snapshot);
+        }
+    }
+
+    Ok(snapshots)
+}
+
+fn write_json<T: Serialize + ?Sized>(output: &Path, relative: &str, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|error| error.to_string())?;
+    write_file(output, &format!("{}.json", relative), &json)
+}
+
+fn write_file(output: &Path, relative: &str, contents: &str) -> Result<(), String> {
+    let path = output.join(relative);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| format!("Failed to create directory: {}", error))?;
+    }
+
+    std::fs::write(&path, contents).map_err(|error| format!("Failed to write {}: {}", path.to_string_lossy(), error))
+}
+
+/// Renders the index page, the tables of broken packages of all releases, and all reports as static files from existing
+/// data, without running any checks.
+///
+/// Information that is not persisted (provided sonames, detected ExcludeArch) is not available for reports.
+pub fn render(args: &RenderArgs) -> Result<(), String> {
+    let snapshots = read_snapshots(&args.input)?;
+    let output = args.output.as_path();
+
+    for (release, snapshot) in &snapshots {
+        let values = &snapshot.values;
+
+        write_json(output, &format!("data/{}", release), values)?;
+        let critpath: Vec<&BrokenItem> = values.iter().filter(|item| item.critpath).collect();
+        write_json(output, &format!("data/{}/critpath", release), &critpath)?;
+        write_json(
+            output,
+            &format!("data/{}/arch-diff", release),
+            &arch_diff(values, &BTreeMap::new()),
+        )?;
+
+        // combined data is only available for the stable variant of a release
+        if !release.ends_with("-testing") {
+            let testing = snapshots.get(&format!("{}-testing", release));
+            write_json(
+                output,
+                &format!("data/{}/combined", release),
+                &combine_testing(
+                    values,
+                    testing.map(|snapshot| snapshot.values.as_slice()).unwrap_or_default(),
+                ),
+            )?;
+        }
+
+        write_json(
+            output,
+            &format!("reports/{}/soname-bumps", release),
+            &soname_bumps(values, &BTreeSet::new()),
+        )?;
+
+        for category in DepCategory::ALL {
+            write_json(
+                output,
+                &format!("reports/{}/category/{}", release, category.name()),
+                &filter_category(values, category),
+            )?;
+        }
+
+        let arches: BTreeSet<&str> = values.iter().map(|item| item.arch.as_str()).collect();
+
+        for arch in arches {
+            write_json(
+                output,
+                &format!("reports/{}/arch/{}", release, arch),
+                &filter_arch(values, arch),
+            )?;
+        }
+
+        let groups: BTreeSet<&str> = values
+            .iter()
+            .flat_map(|item| item.groups.iter().map(String::as_str))
+            .collect();
+
+        for group in groups {
+            write_json(
+                output,
+                &format!("reports/{}/group/{}", release, group),
+                &filter_group(values, group),
+            )?;
+        }
+    }
+
+    let values: HashMap<String, Vec<BrokenItem>> = snapshots
+        .iter()
+        .map(|(release, snapshot)| (release.clone(), snapshot.values.clone()))
+        .collect();
+
+    write_json(output, "reports/new-in-testing", &new_in_testing(&values))?;
+
+    // release order is only known from the configuration file
+    let config = match get_config() {
+        Ok(config) => {
+            write_json(output, "reports/regressions", &regressions(&config, &values))?;
+            Some(config)
+        },
+        Err(error) => {
+            warn!("Skipping regressions report without configuration: {}", error);
+            None
+        },
+    };
+    let templates = config.as_ref().and_then(|config| config.templates.clone());
+    let branding = config
+        .as_ref()
+        .map(|config| config.branding.clone())
+        .unwrap_or_default();
+    let attribution = config
+        .as_ref()
+        .map(|config| config.attribution.clone())
+        .unwrap_or_default();
+
+    for (release, snapshot) in &snapshots {
+        let mut page = 1;
+        loop {
+            let query = TableQuery {
+                page: Some(page),
+                ..Default::default()
+            };
+            let table = ReleaseTable::new(
+                release,
+                &snapshot.values,
+                query,
+                attribution.html,
+                false,
+                branding.clone(),
+            );
+            write_file(
+                output,
+                &table_export_path(release, page),
+                &table.render_page(templates.as_ref())?,
+            )?;
+
+            if page >= table.pages() {
+                break;
+            }
+            page += 1;
+        }
+    }
+
+    let inactive: HashSet<String> = values
+        .values()
+        .flatten()
+        .filter(|item| item.admin_inactive)
+        .map(|item| item.admin.clone())
+        .collect();
+    let releases = snapshots
+        .iter()
+        .map(|(release, snapshot)| (release.as_str(), snapshot.values.as_slice()));
+    write_json(output, "reports/inactive", &inactive_admins(releases, &inactive))?;
+
+    // maintainer feeds only cover releases that are not frozen
+    let active: Vec<(&str, &[BrokenItem])> = snapshots
+        .iter()
+        .filter(|(_, snapshot)| snapshot.frozen_at.is_none())
+        .map(|(release, snapshot)| (release.as_str(), snapshot.values.as_slice()))
+        .collect();
+
+    let maintainers: BTreeSet<&str> = active
+        .iter()
+        .flat_map(|(_, values)| values.iter())
+        .flat_map(|item| {
+            let co_maintainers = attribution.feeds.then_some(item.maintainers.iter());
+            std::iter::once(&item.admin).chain(co_maintainers.into_iter().flatten())
+        })
+        .map(String::as_str)
+        .collect();
+
+    for maintainer in maintainers {
+        write_json(
+            output,
+            &format!("feeds/maintainer/{}", maintainer),
+            &maintainer_feed(
+                maintainer,
+                // static exports are public, so they never include email addresses
+                None,
+                active.iter().copied(),
+                attribution.feeds,
+            ),
+        )?;
+    }
+
+    let entries: Vec<IndexEntry> = snapshots
+        .iter()
+        .map(|(key, snapshot)| IndexEntry {
+            key: key.clone(),
+            refreshed: snapshot.modified.map(DateTime::<Utc>::from),
+            frozen_at: snapshot.frozen_at,
+            critpath: snapshot.values.iter().filter(|item| item.critpath).count(),
+            // results of install checks are not persisted
+            uninstallable: 0,
+        })
+        .collect();
+
+    let mut stats: Vec<(String, usize)> = snapshots
+        .iter()
+        .map(|(release, snapshot)| (release.clone(), snapshot.values.len()))
+        .collect();
+    stats.sort();
+    stats.reverse();
+
+    let date_refreshed = match snapshots.values().filter_map(|snapshot| snapshot.modified).max() {
+        Some(modified) => DateTime::<Utc>::from(modified).to_string(),
+        None => String::from("(no data available)"),
+    };
+
+    let index = Index::new(entries, stats, date_refreshed, false, branding);
+    let body = index.render_page(templates.as_ref())?;
+    write_file(output, "index.html", &body)?;
+
+    info!(
+        "Rendered reports for {} releases into {}.",
+        snapshots.len(),
+        output.to_string_lossy()
+    );
+
+    Ok(())
+}
@@ -1,26 +1,507 @@
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
-
-use log::{debug, error};
-
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::future::Future;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, RwLock};
+use std::time::{Duration, SystemTime};
+
+use log::{debug, error, info, warn};
+
+use tokio::io::AsyncReadExt;
 use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
 
-use crate::data::{BrokenItem, Package};
+use crate::config::{ModularMode, PackageGlobs};
+use crate::data::{
+    BrokenItem, FailureReason, InstallCheck, InstallKind, Package, ProgressStage, ProvideIndex, Provider, RepoDownload,
+};
 use crate::overrides::Overrides;
-use crate::parse::{parse_repoclosure, parse_repoquery};
+use crate::parse::{
+    classify_dep, classify_dnf_error, parse_groupmember, parse_install_problems, parse_module_info, parse_repoclosure,
+    parse_repoquery, parse_repoquery_json, parse_soname, DependencyRecord,
+};
+use crate::utils::write_lock;
+#[cfg(feature = "test-backend")]
+use crate::utils::{get_fixture_path, read_json_from_file};
+use crate::version::get_dnf_version;
+
+const CACHE_MARKER: &str = ".repochecker-last-used";
+
+// reference counts of cache directories that are currently used by a worker
+static CACHES_IN_USE: Mutex<BTreeMap<PathBuf, usize>> = Mutex::new(BTreeMap::new());
+
+// whether the installed version of dnf supports machine-readable output (dnf5), detected on first use
+static DNF_JSON: OnceLock<bool> = OnceLock::new();
+
+// whether machine-readable output of dnf could not be parsed, in which case text output is used from then on
+static DNF_JSON_UNPARSEABLE: AtomicBool = AtomicBool::new(false);
+
+// clock ticks per second of CPU times in /proc (USER_HZ), read on first use
+static CLOCK_TICKS: OnceLock<u64> = OnceLock::new();
+
+tokio::task_local! {
+    // CPU time that was used by dnf processes in the current worker run
+    static DNF_CPU_TIME: Cell<Duration>;
+
+    // number of dnf processes in the current worker run that reported known download or cache errors (including
+    // repositories that were skipped)
+    static DNF_ERRORS: Cell<usize>;
+
+    // additional options that are passed to all dnf processes in the current worker run (mirror overrides)
+    static DNF_OPTIONS: Vec<String>;
+
+    // callback for reporting the stages of the current worker run
+    static PROGRESS: ProgressHook;
+
+    // whether all dnf commands of the current worker run and their error output are logged
+    static DNF_VERBOSE: bool;
+}
+
+/// Runs a future that logs all dnf commands that are started by it, and their error output.
+pub async fn with_verbose_output<F: Future>(verbose: bool, future: F) -> F::Output {
+    DNF_VERBOSE.scope(verbose, future).await
+}
 
-fn get_cache_path(release: &str, arch: &str) -> Result<PathBuf, String> {
+/// Callback that is called with every stage of a worker run (and the architecture it applies to, if any).
+pub type ProgressHook = Arc<dyn Fn(ProgressStage, Option<&str>) + Send + Sync>;
+
+/// Runs a future that reports its progress to the given callback.
+pub async fn with_progress<F: Future>(hook: ProgressHook, future: F) -> F::Output {
+    PROGRESS.scope(hook, future).await
+}
+
+/// Reports that the current worker run started a new stage (see [`with_progress`]).
+pub fn report_progress(stage: ProgressStage, arch: Option<&str>) {
+    let _ = PROGRESS.try_with(|hook| hook(stage, arch));
+}
+
+/// Runs a future with additional options for all dnf processes that are started by it.
+pub async fn with_dnf_options<F: Future>(options: Vec<String>, future: F) -> F::Output {
+    DNF_OPTIONS.scope(options, future).await
+}
+
+/// Creates a dnf command with the additional options of the current worker run (see [`with_dnf_options`]).
+fn dnf_command() -> Command {
+    let mut dnf = Command::new("dnf");
+    let _ = DNF_OPTIONS.try_with(|options| dnf.args(options));
+    dnf
+}
+
+/// Runs a future with accounting for the CPU time of all dnf processes that are started by it.
+pub async fn with_cpu_accounting<F: Future>(future: F) -> F::Output {
+    DNF_CPU_TIME.scope(Cell::new(Duration::ZERO), future).await
+}
+
+/// Returns the CPU time that was used by dnf processes so far (see [`with_cpu_accounting`]).
+pub fn dnf_cpu_time() -> Duration {
+    DNF_CPU_TIME.try_with(Cell::get).unwrap_or_default()
+}
+
+/// Runs a future that counts the dnf processes started by it which reported known download or cache errors.
+pub async fn with_error_accounting<F: Future>(future: F) -> F::Output {
+    DNF_ERRORS.scope(Cell::new(0), future).await
+}
+
+/// Returns the number of dnf processes that reported errors so far (see [`with_error_accounting`]).
+pub fn dnf_errors() -> usize {
+    DNF_ERRORS.try_with(Cell::get).unwrap_or_default()
+}
+
+/// Reads the CPU time (user and system time, including that of waited-for children) of a process from /proc.
+fn get_cpu_time(pid: u32) -> Option<Duration> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+
+    // the command name can contain spaces, so fields are counted from the end of it
+    let (_, fields) = stat.rsplit_once(')')?;
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+
+    // utime, stime, cutime, and cstime are the fields 14 to 17
+    let ticks: u64 = fields
+        .get(11..15)?
+        .iter()
+        .map(|field| field.parse::<u64>().ok())
+        .sum::<Option<u64>>()?;
+
+    let clock_ticks = *CLOCK_TICKS.get_or_init(|| {
+        // SAFETY: sysconf only reads a system configuration value
+        match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+            ticks if ticks > 0 => ticks as u64,
+            _ => 100,
+        }
+    });
+
+    Some(Duration::from_millis(ticks * 1000 / clock_ticks))
+}
+
+pub fn get_cache_root() -> Result<PathBuf, String> {
     let mut path = PathBuf::new();
     path.push(std::env::current_dir().map_err(|error| error.to_string())?);
-    path.push(format!("cache/{}/{}", release, arch));
+    path.push("cache/");
     Ok(path)
 }
 
-async fn make_cache(release: &str, arch: &str, repos: &[String]) -> Result<(), String> {
-    let path = get_cache_path(release, arch)?;
+fn get_cache_path(cache: &str, arch: &str) -> Result<PathBuf, String> {
+    let mut path = get_cache_root()?;
+    path.push(format!("{}/{}", cache, arch));
+    Ok(path)
+}
 
-    let mut dnf = Command::new("dnf");
+/// Returns the cache directory for a release / arch combination, and creates it if it does not exist yet (dnf is not
+/// pointed at installroots that do not exist).
+fn create_cache_dir(cache: &str, arch: &str) -> Result<PathBuf, String> {
+    let path = get_cache_path(cache, arch)?;
+
+    if let Err(error) = std::fs::create_dir_all(&path) {
+        return Err(format!("{}: {}", path.to_string_lossy(), error));
+    }
+
+    if !path.is_dir() {
+        return Err(String::from("Cache directory path is not a directory."));
+    }
+
+    Ok(path)
+}
+
+/// Marks the cache directory for a release / arch combination as being in use for as long as this
+/// value is alive, which prevents it from being removed when cleaning up caches to free disk space.
+pub struct CacheLease {
+    path: PathBuf,
+}
+
+impl CacheLease {
+    fn new(cache: &str, arch: &str) -> Result<Self, String> {
+        let path = get_cache_path(cache, arch)?;
+
+        let mut in_use = CACHES_IN_USE.lock().unwrap_or_else(PoisonError::into_inner);
+        *in_use.entry(path.clone()).or_insert(0) += 1;
+
+        Ok(CacheLease { path })
+    }
+}
+
+/// Marks the cache directories of a release as being in use for all given architectures.
+///
+/// The leases need to be held for as long as any queries against the caches are running, including the queries that
+/// are run for post-processing after checking the repositories of a release.
+pub fn lease_caches(cache: &str, arches: &[String]) -> Result<Vec<CacheLease>, String> {
+    arches.iter().map(|arch| CacheLease::new(cache, arch)).collect()
+}
+
+impl Drop for CacheLease {
+    fn drop(&mut self) {
+        let mut in_use = CACHES_IN_USE.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(count) = in_use.get_mut(&self.path) {
+            *count -= 1;
+            if *count == 0 {
+                in_use.remove(&self.path);
+            }
+        }
+    }
+}
+
+/// Returns the space on the filesystem of a path that is available to unprivileged users (like `df`), in bytes.
+fn get_free_space(path: &Path) -> Result<u64, String> {
+    let c_path = match std::ffi::CString::new(path.as_os_str().as_bytes()) {
+        Ok(c_path) => c_path,
+        Err(error) => return Err(format!("Invalid path {}: {}", path.to_string_lossy(), error)),
+    };
+
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: the path is a valid NUL-terminated string, and statvfs only writes to the provided buffer
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(format!(
+            "Failed to determine free space for {}: {}",
+            path.to_string_lossy(),
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    // SAFETY: statvfs initialized the buffer since it succeeded
+    let stat = unsafe { stat.assume_init() };
+
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+fn get_last_used(path: &Path) -> Option<SystemTime> {
+    let marker = path.join(CACHE_MARKER);
+
+    match std::fs::metadata(marker) {
+        Ok(metadata) => metadata.modified().ok(),
+        Err(_) => std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok(),
+    }
+}
+
+/// Returns all cache directories below the root that are not currently in use, sorted from least to most recently used.
+fn get_unused_caches(root: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut caches: Vec<(Option<SystemTime>, PathBuf)> = Vec::new();
+
+    let in_use = CACHES_IN_USE.lock().unwrap_or_else(PoisonError::into_inner);
+
+    for release in std::fs::read_dir(root).map_err(|error| error.to_string())? {
+        let release = release.map_err(|error| error.to_string())?.path();
+        if !release.is_dir() {
+            continue;
+        }
+
+        for arch in std::fs::read_dir(&release).map_err(|error| error.to_string())? {
+            let arch = arch.map_err(|error| error.to_string())?.path();
+            if !arch.is_dir() || in_use.contains_key(&arch) {
+                continue;
+            }
+
+            caches.push((get_last_used(&arch), arch));
+        }
+    }
+
+    caches.sort();
+
+    Ok(caches.into_iter().map(|(_, path)| path).collect())
+}
+
+async fn ensure_free_space(min_free_space: f64) -> Result<(), String> {
+    let root = get_cache_root()?;
+    std::fs::create_dir_all(&root).map_err(|error| error.to_string())?;
+
+    let threshold = (min_free_space * 1024.0 * 1024.0 * 1024.0) as u64;
+
+    let mut free = get_free_space(&root)?;
+    if free >= threshold {
+        return Ok(());
+    }
+
+    warn!(
+        "Only {:.1} GiB of free space left for caches, removing least recently used caches.",
+        free as f64 / 1024.0 / 1024.0 / 1024.0
+    );
+
+    for cache in get_unused_caches(&root)? {
+        info!("Removing cache directory: {}", cache.to_string_lossy());
+
+        if let Err(error) = remove_cache_dir(&cache) {
+            error!(
+                "Failed to remove cache directory {}: {}",
+                cache.to_string_lossy(),
+                error
+            );
+            continue;
+        }
+
+        free = get_free_space(&root)?;
+        if free >= threshold {
+            return Ok(());
+        }
+    }
+
+    Err(format!(
+        "Not enough free space for caches: {:.1} GiB available, {:.1} GiB required.",
+        free as f64 / 1024.0 / 1024.0 / 1024.0,
+        min_free_space
+    ))
+}
+
+/// Checks whether the installed version of dnf supports machine-readable output (`--json`), which is the case for dnf5.
+async fn dnf_supports_json() -> bool {
+    if DNF_JSON_UNPARSEABLE.load(Ordering::Relaxed) {
+        return false;
+    }
+
+    if let Some(supported) = DNF_JSON.get() {
+        return *supported;
+    }
+
+    let supported = match get_dnf_version().await {
+        Some(version) => version.starts_with("dnf5"),
+        None => {
+            warn!("Failed to determine dnf version.");
+            false
+        },
+    };
+
+    debug!("Machine-readable output of dnf is supported: {}", supported);
+    *DNF_JSON.get_or_init(|| supported)
+}
+
+/// Returns the last line of the error output of dnf, which contains the actual error message.
+fn last_error_line(stderr: &str) -> &str {
+    stderr.trim().lines().last().unwrap_or_default()
+}
+
+/// Runs a command to completion, or kills it if the operation is cancelled before it finishes.
+async fn run_command(mut command: Command, cancel: &CancellationToken) -> Result<Output, String> {
+    let verbose = DNF_VERBOSE.try_with(|verbose| *verbose).unwrap_or_default();
+    if verbose {
+        info!("Running command: {:?}", command.as_std());
+    }
+
+    command
+        .kill_on_drop(true)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = command.spawn().map_err(|error| error.to_string())?;
+    let pid = child.id();
+
+    let output = async {
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+
+        if let (Some(out), Some(err)) = (child.stdout.as_mut(), child.stderr.as_mut()) {
+            let (out, err) = tokio::join!(out.read_to_end(&mut stdout), err.read_to_end(&mut stderr));
+            out.map_err(|error| error.to_string())?;
+            err.map_err(|error| error.to_string())?;
+        }
+
+        // the output is closed when the process exits, but it is only reaped after its CPU time was read
+        if let Some(cpu_time) = pid.and_then(get_cpu_time) {
+            let _ = DNF_CPU_TIME.try_with(|total| total.set(total.get() + cpu_time));
+        }
+
+        let status = child.wait().await.map_err(|error| error.to_string())?;
+
+        // dnf only warns about repositories that could not be downloaded if they are skipped, even if it succeeds
+        if classify_dnf_error(&String::from_utf8_lossy(&stderr)) != FailureReason::Other {
+            let _ = DNF_ERRORS.try_with(|errors| errors.set(errors.get() + 1));
+        }
+
+        if verbose {
+            info!(
+                "Command exited with {}: {}",
+                status,
+                String::from_utf8_lossy(&stderr).trim()
+            );
+        }
+
+        Ok(Output { status, stdout, stderr })
+    };
+
+    tokio::select! {
+        output = output => output,
+        _ = cancel.cancelled() => Err(String::from("Operation was cancelled.")),
+    }
+}
+
+/// Returns the total size of the cache directories of a release (in bytes).
+pub async fn get_cache_size(cache: &str, arches: &[String]) -> Result<u64, String> {
+    let mut du = Command::new("du");
+    du.arg("--summarize").arg("--total").arg("--block-size=1");
+
+    for arch in arches {
+        du.arg(get_cache_path(cache, arch)?);
+    }
+
+    let output = du.output().await.map_err(|error| error.to_string())?;
+
+    if !output.status.success() {
+        return Err(format!("Failed to determine size of caches for {}.", cache));
+    }
+
+    let string = String::from_utf8(output.stdout).map_err(|error| error.to_string())?;
+
+    // the last line contains the total size of all directories
+    match string.lines().last().and_then(|line| line.split_whitespace().next()) {
+        Some(size) => size
+            .parse()
+            .map_err(|error| format!("Failed to parse du output: {}", error)),
+        None => Err(format!("Unrecognised output from du: {}", string)),
+    }
+}
+
+/// Passes package globs that are excluded from (or exclusively included in) the repositories to dnf.
+fn add_package_globs(dnf: &mut Command, globs: &PackageGlobs) {
+    if !globs.exclude.is_empty() {
+        dnf.arg(format!("--setopt=excludepkgs={}", globs.exclude.join(",")));
+    }
+
+    if !globs.include.is_empty() {
+        dnf.arg(format!("--setopt=includepkgs={}", globs.include.join(",")));
+    }
+}
+
+/// Returns the total size of the files below a directory that were modified since the given time, and when the last
+/// of them was modified.
+fn get_modified(path: &Path, since: SystemTime) -> (u64, Option<SystemTime>) {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return (0, None),
+    };
+
+    let mut size = 0;
+    let mut last = None;
+    for entry in entries.flatten() {
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+
+        if metadata.is_dir() {
+            let (dir_size, dir_last) = get_modified(&entry.path(), since);
+            size += dir_size;
+            last = last.max(dir_last);
+        } else if let Some(modified) = metadata.modified().ok().filter(|modified| *modified >= since) {
+            size += metadata.len();
+            last = last.max(Some(modified));
+        }
+    }
+
+    (size, last)
+}
+
+/// Returns the cache directories of a repository below a dnf cache directory (which are named after the repository,
+/// followed by a hash of its configuration).
+fn find_repo_caches(path: &Path, repo: &str) -> Vec<PathBuf> {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut caches = Vec::new();
+    for entry in entries.flatten() {
+        if !entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            continue;
+        }
+
+        let name = entry.file_name();
+        let is_cache = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(repo))
+            .and_then(|suffix| suffix.strip_prefix('-'))
+            .is_some_and(|hash| !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()));
+
+        if is_cache {
+            caches.push(entry.path());
+        } else {
+            caches.extend(find_repo_caches(&entry.path(), repo));
+        }
+    }
+
+    caches
+}
+
+/// Refreshes the dnf cache for a release / arch combination, and returns how long downloading the metadata of each
+/// repository took.
+///
+/// All repositories are refreshed by a single dnf process (which downloads their metadata in parallel), so the time
+/// for each repository is measured until the last file in its cache directory was written.
+async fn make_cache(
+    cache: &str,
+    release: &str,
+    arch: &str,
+    repos: &[String],
+    min_free_space: f64,
+    cancel: &CancellationToken,
+) -> Result<Vec<RepoDownload>, String> {
+    ensure_free_space(min_free_space).await?;
+
+    let path = create_cache_dir(cache, arch)?;
+
+    let mut dnf = dnf_command();
 
     dnf.arg("--quiet")
         .arg("--installroot")
@@ -29,14 +510,14 @@ async fn make_cache(release: &str, arch: &str, repos: &[String]) -> Result<(), S
         .arg(release);
 
     for repo in repos {
-        dnf.arg("--repo");
-        dnf.arg(repo);
+        dnf.arg("--repo").arg(repo);
     }
 
     dnf.arg("--forcearch").arg(arch);
     dnf.arg("makecache").arg("--refresh");
 
-    let output = dnf.output().await.map_err(|error| error.to_string())?;
+    let started = SystemTime::now();
+    let output = run_command(dnf, cancel).await?;
 
     if !output.status.success() {
         debug!("dnf makecache for {} / {} exited with an error code:", release, arch);
@@ -49,56 +530,89 @@ async fn make_cache(release: &str, arch: &str, repos: &[String]) -> Result<(), S
             }
         );
 
-        debug!(
-            "{}",
-            match String::from_utf8(output.stderr) {
-                Ok(string) => string,
-                Err(error) => format!("Failed to decode dnf output: {}", error),
-            }
-        );
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        debug!("{}", stderr);
 
         return Err(format!(
-            "dnf makecache for {} / {} exited with an error code.",
-            release, arch
+            "dnf makecache for {} / {} exited with an error code: {}",
+            release,
+            arch,
+            last_error_line(&stderr)
         ));
     };
 
-    Ok(())
-}
+    let mut downloads = Vec::new();
+    for repo in repos {
+        let (bytes, last) = find_repo_caches(&path, repo)
+            .iter()
+            .map(|cache| get_modified(cache, started))
+            .fold((0, None), |(bytes, last), (size, modified)| {
+                (bytes + size, last.max(modified))
+            });
+
+        downloads.push(RepoDownload {
+            repo: repo.clone(),
+            arch: arch.to_string(),
+            bytes,
+            seconds: last
+                .and_then(|last| last.duration_since(started).ok())
+                .unwrap_or_default()
+                .as_secs_f64(),
+        });
+    }
 
-async fn get_repo_contents(release: &str, arch: &str, repos: &[String]) -> Result<Vec<Package>, String> {
-    let path = get_cache_path(release, arch)?;
+    // record last use of this cache for cleaning up least recently used caches
+    if let Err(error) = std::fs::write(path.join(CACHE_MARKER), b"") {
+        debug!("Failed to update cache marker for {} / {}: {}", release, arch, error);
+    }
 
-    if !path.exists() {
-        if let Err(error) = std::fs::create_dir_all(&path) {
-            return Err(error.to_string());
+    Ok(downloads)
+}
+
+async fn get_repo_contents(
+    cache: &str,
+    release: &str,
+    arch: &str,
+    repos: &[String],
+    globs: &PackageGlobs,
+    cancel: &CancellationToken,
+) -> Result<Vec<Package>, String> {
+    let path = create_cache_dir(cache, arch)?;
+
+    let repoquery = || {
+        let mut dnf = dnf_command();
+
+        dnf.arg("--quiet")
+            .arg("--installroot")
+            .arg(&path)
+            .arg("--releasever")
+            .arg(release);
+
+        for repo in repos {
+            dnf.arg("--repo");
+            dnf.arg(repo);
         }
-    };
 
-    if !path.is_dir() {
-        return Err(String::from("Cache directory path is not a directory."));
-    }
+        dnf.arg("--forcearch").arg(arch);
+        add_package_globs(&mut dnf, globs);
 
-    let mut dnf = Command::new("dnf");
+        dnf.arg("repoquery");
+        dnf
+    };
 
-    dnf.arg("--quiet")
-        .arg("--installroot")
-        .arg(&path)
-        .arg("--releasever")
-        .arg(release);
+    // prefer machine-readable output, and fall back to parsing text output if it is not supported (or could not be
+    // parsed before), so repoquery is only run once per query
+    let json = dnf_supports_json().await;
 
-    for repo in repos {
-        dnf.arg("--repo");
-        dnf.arg(repo);
+    let mut dnf = repoquery();
+    if json {
+        dnf.arg("--json");
+    } else {
+        dnf.arg("--queryformat")
+            .arg("%{name}\t%{source_name}\t%{epoch}\t%{version}\t%{release}\t%{arch}\t%{summary}\t%{license}");
     }
 
-    dnf.arg("--forcearch").arg(arch);
-
-    dnf.arg("repoquery")
-        .arg("--queryformat")
-        .arg("%{name} %{source_name} %{epoch} %{version} %{release} %{arch}");
-
-    let output = dnf.output().await.map_err(|error| error.to_string())?;
+    let output = run_command(dnf, cancel).await?;
 
     if !output.status.success() {
         debug!("dnf makecache exited with an error code:",);
@@ -124,7 +638,343 @@ async fn get_repo_contents(release: &str, arch: &str, repos: &[String]) -> Resul
         .trim()
         .to_string();
 
-    parse_repoquery(&string)
+    if !json {
+        return parse_repoquery(&string);
+    }
+
+    parse_repoquery_json(&string).map_err(|error| {
+        warn!("Using text output of dnf repoquery from now on: {}", error);
+        DNF_JSON_UNPARSEABLE.store(true, Ordering::Relaxed);
+        format!("Failed to parse output of dnf repoquery --json: {}", error)
+    })
+}
+
+/// Returns all sonames with one of the given library basenames that are provided by packages in the repositories.
+pub async fn get_provided_sonames(
+    cache: &str,
+    release: &str,
+    arches: &[String],
+    repos: &[String],
+    basenames: &BTreeSet<String>,
+    globs: &PackageGlobs,
+    cancel: &CancellationToken,
+) -> Result<BTreeSet<String>, String> {
+    let mut provided: BTreeSet<String> = BTreeSet::new();
+
+    if basenames.is_empty() {
+        return Ok(provided);
+    }
+
+    let patterns: Vec<String> = basenames.iter().map(|basename| format!("{}*", basename)).collect();
+
+    for arch in arches {
+        let path = get_cache_path(cache, arch)?;
+
+        let mut dnf = dnf_command();
+
+        dnf.arg("--quiet")
+            .arg("--installroot")
+            .arg(&path)
+            .arg("--releasever")
+            .arg(release);
+
+        for repo in repos {
+            dnf.arg("--repo");
+            dnf.arg(repo);
+        }
+
+        dnf.arg("--forcearch").arg(arch);
+        add_package_globs(&mut dnf, globs);
+
+        dnf.arg("repoquery")
+            .arg("--provides")
+            .arg("--whatprovides")
+            .arg(patterns.join(","));
+
+        let output = run_command(dnf, cancel).await?;
+
+        if !output.status.success() {
+            return Err(String::from("dnf repoquery exited with an error code."));
+        };
+
+        let string = String::from_utf8(output.stdout).map_err(|error| error.to_string())?;
+
+        for line in string.lines() {
+            if let Some((basename, soname)) = parse_soname(line) {
+                if basenames.contains(basename) {
+                    provided.insert(soname.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(provided)
+}
+
+/// Dumps names, source package names, provides, and requires of all packages in the repositories.
+pub async fn get_dependency_dump(
+    cache: &str,
+    release: &str,
+    arch: &str,
+    repos: &[String],
+    globs: &PackageGlobs,
+    cancel: &CancellationToken,
+) -> Result<String, String> {
+    let path = get_cache_path(cache, arch)?;
+
+    let mut dnf = dnf_command();
+
+    dnf.arg("--quiet")
+        .arg("--installroot")
+        .arg(&path)
+        .arg("--releasever")
+        .arg(release);
+
+    for repo in repos {
+        dnf.arg("--repo");
+        dnf.arg(repo);
+    }
+
+    dnf.arg("--forcearch").arg(arch);
+    add_package_globs(&mut dnf, globs);
+
+    dnf.arg("repoquery")
+        .arg("--queryformat")
+        .arg("\x1e%{name}\x1f%{source_name}\x1f%{provides}\x1f%{requires}");
+
+    let output = run_command(dnf, cancel).await?;
+
+    if !output.status.success() {
+        return Err(String::from("dnf repoquery exited with an error code."));
+    };
+
+    String::from_utf8(output.stdout).map_err(|error| error.to_string())
+}
+
+/// Checks whether each of the given packages (or comps groups) can be installed (including all of their dependencies)
+/// into an empty installroot, without actually installing anything.
+#[allow(clippy::too_many_arguments)]
+pub async fn check_installable(
+    cache: &str,
+    release: &str,
+    arch: &str,
+    repos: &[String],
+    globs: &PackageGlobs,
+    kind: InstallKind,
+    names: &[String],
+    cancel: &CancellationToken,
+) -> Result<Vec<InstallCheck>, String> {
+    let path = get_cache_path(cache, arch)?;
+
+    let mut checks = Vec::new();
+
+    for name in names {
+        let spec = match kind {
+            InstallKind::Package => name.clone(),
+            InstallKind::Group => format!("@{}", name),
+        };
+
+        let mut dnf = dnf_command();
+
+        dnf.arg("--quiet")
+            .arg("--installroot")
+            .arg(&path)
+            .arg("--releasever")
+            .arg(release);
+
+        for repo in repos {
+            dnf.arg("--repo");
+            dnf.arg(repo);
+        }
+
+        dnf.arg("--forcearch").arg(arch);
+        add_package_globs(&mut dnf, globs);
+
+        dnf.arg("install").arg("--assumeno").arg(&spec);
+
+        // dnf exits with an error code even if the transaction could be resolved, because it is never confirmed
+        let output = run_command(dnf, cancel).await?;
+        let stderr = String::from_utf8(output.stderr).map_err(|error| error.to_string())?;
+
+        let problems = parse_install_problems(&stderr);
+        if !problems.is_empty() {
+            warn!("{} is not installable on {}/{}.", spec, release, arch);
+        }
+
+        checks.push(InstallCheck {
+            kind,
+            name: name.clone(),
+            arch: arch.to_string(),
+            installable: problems.is_empty(),
+            problems,
+        });
+    }
+
+    Ok(checks)
+}
+
+/// Looks up which comps groups the given binary packages are members of.
+pub async fn get_package_groups(
+    cache: &str,
+    release: &str,
+    arch: &str,
+    repos: &[String],
+    globs: &PackageGlobs,
+    packages: &BTreeSet<String>,
+    cancel: &CancellationToken,
+) -> Result<HashMap<String, Vec<String>>, String> {
+    if packages.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let path = get_cache_path(cache, arch)?;
+
+    let mut dnf = dnf_command();
+
+    dnf.arg("--quiet")
+        .arg("--installroot")
+        .arg(&path)
+        .arg("--releasever")
+        .arg(release);
+
+    for repo in repos {
+        dnf.arg("--repo");
+        dnf.arg(repo);
+    }
+
+    dnf.arg("--forcearch").arg(arch);
+    add_package_globs(&mut dnf, globs);
+
+    dnf.arg("repoquery").arg("--groupmember").args(packages);
+
+    let output = run_command(dnf, cancel).await?;
+
+    if !output.status.success() {
+        return Err(String::from("dnf repoquery exited with an error code."));
+    };
+
+    let stdout = String::from_utf8(output.stdout).map_err(|error| error.to_string())?;
+    parse_groupmember(&stdout)
+}
+
+/// Returns the module stream ("name:stream") of every package that is part of a module in the given repositories.
+async fn get_module_artifacts(
+    cache: &str,
+    release: &str,
+    arch: &str,
+    repos: &[String],
+    globs: &PackageGlobs,
+    cancel: &CancellationToken,
+) -> Result<HashMap<String, String>, String> {
+    let path = get_cache_path(cache, arch)?;
+
+    let mut dnf = dnf_command();
+
+    dnf.arg("--quiet")
+        .arg("--installroot")
+        .arg(&path)
+        .arg("--releasever")
+        .arg(release);
+
+    for repo in repos {
+        dnf.arg("--repo");
+        dnf.arg(repo);
+    }
+
+    dnf.arg("--forcearch").arg(arch);
+    add_package_globs(&mut dnf, globs);
+
+    dnf.arg("module").arg("info").arg("*");
+
+    let output = run_command(dnf, cancel).await?;
+
+    if !output.status.success() {
+        return Err(String::from("dnf module info exited with an error code."));
+    };
+
+    let stdout = String::from_utf8(output.stdout).map_err(|error| error.to_string())?;
+    Ok(parse_module_info(&stdout))
+}
+
+/// Returns the number of other packages that depend on each of the given binary packages.
+pub fn count_dependents(records: &[DependencyRecord], packages: &HashSet<String>) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = packages.iter().map(|package| (package.clone(), 0)).collect();
+
+    // map capabilities that are provided by the packages in question to their providers
+    let mut providers: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for record in records {
+        if packages.contains(record.name) {
+            for provide in &record.provides {
+                providers.entry(provide).or_default().insert(record.name);
+            }
+        }
+    }
+
+    let mut dependents: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for record in records {
+        for require in &record.requires {
+            if let Some(targets) = providers.get(require) {
+                for target in targets {
+                    if *target != record.name {
+                        dependents.entry(target).or_default().insert(record.name);
+                    }
+                }
+            }
+        }
+    }
+
+    for (package, names) in dependents {
+        counts.insert(package.to_string(), names.len());
+    }
+
+    counts
+}
+
+/// Builds an index of capabilities to their providers, restricted to capabilities that other packages require.
+pub fn index_provides(records: &[DependencyRecord], previous: Option<&ProvideIndex>) -> ProvideIndex {
+    let required: HashSet<&str> = records
+        .iter()
+        .flat_map(|record| record.requires.iter().copied())
+        .collect();
+
+    let mut index = ProvideIndex::new();
+
+    for record in records {
+        for provide in &record.provides {
+            if required.contains(provide) {
+                let provider = Provider {
+                    name: record.name.to_string(),
+                    source: record.source.to_string(),
+                    evr: record.provide_evrs.get(provide).map(ToString::to_string),
+                    previous: false,
+                };
+
+                // packages can be available for multiple architectures (for example, multilib packages)
+                let providers = index.entry(provide.to_string()).or_default();
+                if !providers.contains(&provider) {
+                    providers.push(provider);
+                }
+            }
+        }
+    }
+
+    // remember the last known providers of capabilities that are still required, but no longer provided
+    if let Some(previous) = previous {
+        for (provide, providers) in previous {
+            if required.contains(provide.as_str()) && !index.contains_key(provide) {
+                let providers = providers
+                    .iter()
+                    .map(|provider| Provider {
+                        previous: true,
+                        ..provider.clone()
+                    })
+                    .collect();
+                index.insert(provide.clone(), providers);
+            }
+        }
+    }
+
+    index
 }
 
 fn get_source_map(contents: &[Package]) -> HashMap<&str, &str> {
@@ -141,26 +991,42 @@ fn get_source_map(contents: &[Package]) -> HashMap<&str, &str> {
     map
 }
 
-#[allow(clippy::many_single_char_names)]
-async fn get_repo_closure_arched_repo(
+/// Checks all repositories that are checked for a release on one architecture in a single pass of repoclosure.
+///
+/// Results are attributed to the repository that contains the broken package (the `repo` field).
+#[allow(clippy::many_single_char_names, clippy::too_many_arguments)]
+async fn get_repo_closure_arched(
+    cache: &str,
     release: &str,
     arch: &str,
     multi_arch: &[String],
     repos: &[String],
-    check: &str,
+    check: &[String],
     admins: &HashMap<String, String>,
     maintainers: &HashMap<String, Vec<String>>,
+    globs: &PackageGlobs,
+    cancel: &CancellationToken,
 ) -> Result<Vec<BrokenItem>, String> {
-    let path = get_cache_path(release, arch)?;
+    // repoclosure checks all enabled repositories if no repository is selected with --check
+    if check.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let path = get_cache_path(cache, arch)?;
 
     if !path.exists() || !path.is_dir() {
         return Err(String::from("Cache does not exist."));
     };
 
-    let contents = get_repo_contents(release, arch, repos).await?;
+    let contents = get_repo_contents(cache, release, arch, repos, globs, cancel).await?;
     let source_map = get_source_map(&contents);
 
-    let mut dnf = Command::new("dnf");
+    let details: HashMap<(&str, &str), &Package> = contents
+        .iter()
+        .map(|package| ((package.name.as_str(), package.arch.as_str()), package))
+        .collect();
+
+    let mut dnf = dnf_command();
 
     dnf.arg("--quiet");
     dnf.arg("--installroot").arg(&path);
@@ -172,6 +1038,8 @@ async fn get_repo_closure_arched_repo(
         dnf.arg(repo);
     }
 
+    add_package_globs(&mut dnf, globs);
+
     dnf.arg("repoclosure").arg("--newest");
 
     for multi in multi_arch {
@@ -179,17 +1047,34 @@ async fn get_repo_closure_arched_repo(
         dnf.arg(multi);
     }
 
-    dnf.arg("--check");
-    dnf.arg(check);
+    for checked in check {
+        dnf.arg("--check");
+        dnf.arg(checked);
+    }
 
-    let output = dnf.output().await.map_err(|error| error.to_string())?;
+    let output = run_command(dnf, cancel).await?;
 
     let string = String::from_utf8(output.stdout)
         .map_err(|error| error.to_string())?
         .trim()
         .to_string();
 
-    let closure = parse_repoclosure(&string)?;
+    // repoclosure exits with an error code if there are unresolved dependencies, so this is only considered to be a
+    // failure if no unresolved dependencies could be parsed from its output (the error is classified by the caller)
+    let closure = match (output.status.success(), parse_repoclosure(&string)) {
+        (true, closure) => closure?,
+        (false, Ok(closure)) if !closure.is_empty() => closure,
+        (false, _) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            debug!("{}", stderr);
+            return Err(format!(
+                "dnf repoclosure for {} / {} exited with an error code: {}",
+                release,
+                arch,
+                last_error_line(&stderr)
+            ));
+        },
+    };
 
     let mut broken_deps: Vec<BrokenItem> = Vec::new();
     for item in closure {
@@ -218,6 +1103,11 @@ async fn get_repo_closure_arched_repo(
             },
         };
 
+        let (summary, license) = match details.get(&(item.package.as_str(), item.arch.as_str())) {
+            Some(package) => (Some(package.summary.clone()), Some(package.license.clone())),
+            None => (None, None),
+        };
+
         let broken_dep = BrokenItem {
             source: source.to_string(),
             package: item.package,
@@ -226,11 +1116,28 @@ async fn get_repo_closure_arched_repo(
             release: item.release,
             arch: item.arch,
             admin,
+            admin_inactive: false,
             maintainers: ms,
             repo: item.repo,
             repo_arch: arch.to_string(),
             broken: item.broken,
+            categories: Vec::new(),
             since: None,
+            dependents: None,
+            leaf: None,
+            hints: Vec::new(),
+            summary,
+            license,
+            distgit_url: None,
+            koji_url: None,
+            bodhi_url: None,
+            generated_by_run: None,
+            critpath: false,
+            groups: Vec::new(),
+            module: None,
+            previous_admin: None,
+            admin_changed: None,
+            generated_by_version: None,
         };
 
         broken_deps.push(broken_dep);
@@ -239,28 +1146,131 @@ async fn get_repo_closure_arched_repo(
     Ok(broken_deps)
 }
 
-async fn get_repo_closure_arched(
+/// Results of checking the repositories of a release.
+pub struct RepoClosure {
+    pub broken: Vec<BrokenItem>,
+    // source packages that were detected to be excluded on each architecture
+    pub excluded: BTreeMap<String, BTreeSet<String>>,
+    pub downloads: Vec<RepoDownload>,
+}
+
+/// Removes a cache directory, unless it is currently in use by another worker.
+///
+/// The directory is moved out of the way while holding the lock for the caches that are in use, so no worker can start
+/// using it between checking whether it is in use and removing it.
+fn remove_cache_dir(path: &Path) -> Result<(), String> {
+    let removed = path.with_file_name(format!(".removed-{}", uuid::Uuid::new_v4().as_simple()));
+
+    {
+        let in_use = CACHES_IN_USE.lock().unwrap_or_else(PoisonError::into_inner);
+        if in_use.contains_key(path) {
+            return Err(format!("Cache directory {} is in use.", path.to_string_lossy()));
+        }
+
+        match std::fs::rename(path, &removed) {
+            Ok(()) => {},
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error.to_string()),
+        }
+    }
+
+    std::fs::remove_dir_all(&removed).map_err(|error| error.to_string())?;
+    info!("Removed cache directory: {}", path.to_string_lossy());
+    Ok(())
+}
+
+/// Removes the cache directory for a release / arch combination, unless it is currently in use by another worker.
+pub fn remove_cache(cache: &str, arch: &str) -> Result<(), String> {
+    remove_cache_dir(&get_cache_path(cache, arch)?)
+}
+
+/// Returns the architectures that an error message from checking a release refers to.
+///
+/// Errors from dnf are reported as "<release> / <arch>", which is used to only invalidate the cache of the architecture
+/// that is affected. If the message does not mention any of the architectures, all of them are returned.
+fn affected_arches<'a>(error: &str, release: &str, arches: &'a [String]) -> Vec<&'a String> {
+    let affected: Vec<&String> = arches
+        .iter()
+        .filter(|arch| {
+            let needle = format!("{} / {}", release, arch);
+            error.match_indices(&needle).any(|(index, _)| {
+                !error[index + needle.len()..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+            })
+        })
+        .collect();
+
+    if affected.is_empty() {
+        arches.iter().collect()
+    } else {
+        affected
+    }
+}
+
+/// Checks the repositories of a release for broken dependencies.
+///
+/// If this fails with an error that indicates a corrupted dnf cache, the cache directory of the affected architecture is
+/// removed, and the check is retried once with a fresh cache.
+#[allow(clippy::too_many_arguments)]
+pub async fn get_repo_closure(
+    cache: &str,
     release: &str,
-    arch: &str,
-    multi_arch: &[String],
+    arches: &[String],
+    multi_arch: &HashMap<String, Vec<String>>,
     repos: &[String],
     check: &[String],
+    overrides: Arc<RwLock<Overrides>>,
     admins: &HashMap<String, String>,
     maintainers: &HashMap<String, Vec<String>>,
-) -> Result<Vec<BrokenItem>, String> {
-    let mut all_broken: Vec<BrokenItem> = Vec::new();
+    min_free_space: f64,
+    detect_exclude_arch: bool,
+    previous_provides: &HashMap<String, ProvideIndex>,
+    globs: &PackageGlobs,
+    modular: Option<ModularMode>,
+    cancel: &CancellationToken,
+) -> Result<RepoClosure, String> {
+    let mut retried = false;
+
+    loop {
+        let result = check_repo_closure(
+            cache,
+            release,
+            arches,
+            multi_arch,
+            repos,
+            check,
+            overrides.clone(),
+            admins,
+            maintainers,
+            min_free_space,
+            detect_exclude_arch,
+            previous_provides,
+            globs,
+            modular,
+            cancel,
+        )
+        .await;
+
+        match result {
+            Err(error) if !retried && classify_dnf_error(&error) == FailureReason::CacheCorruption => {
+                warn!(
+                    "Detected corrupted cache for {}, retrying with fresh caches: {}",
+                    release, error
+                );
 
-    for checked in check {
-        let broken =
-            get_repo_closure_arched_repo(release, arch, multi_arch, repos, checked, admins, maintainers).await?;
-        all_broken.extend(broken);
-    }
+                for arch in affected_arches(&error, release, arches) {
+                    remove_cache(cache, arch)?;
+                }
 
-    Ok(all_broken)
+                retried = true;
+            },
+            result => return result,
+        }
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
-pub async fn get_repo_closure<'a>(
+async fn check_repo_closure(
+    cache: &str,
     release: &str,
     arches: &[String],
     multi_arch: &HashMap<String, Vec<String>>,
@@ -269,14 +1279,29 @@ pub async fn get_repo_closure<'a>(
     overrides: Arc<RwLock<Overrides>>,
     admins: &HashMap<String, String>,
     maintainers: &HashMap<String, Vec<String>>,
-) -> Result<Vec<BrokenItem>, String> {
+    min_free_space: f64,
+    detect_exclude_arch: bool,
+    previous_provides: &HashMap<String, ProvideIndex>,
+    globs: &PackageGlobs,
+    modular: Option<ModularMode>,
+    cancel: &CancellationToken,
+) -> Result<RepoClosure, String> {
+    // prevent caches for this release from being cleaned up while they are in use
+    let _leases = lease_caches(cache, arches)?;
+
     // check which source packages do not produce any binary packages on a given architecture
     // (emulates detection of ExcludeArch / ExclusiveArch, which cannot be queried directly)
     let mut all_packages: HashSet<String> = HashSet::new();
     let mut arch_map: HashMap<&str, Vec<String>> = HashMap::new();
 
     for arch in arches {
-        let packages = get_repo_contents(release, arch, repos).await?;
+        if !detect_exclude_arch {
+            arch_map.insert(arch, Vec::new());
+            continue;
+        }
+
+        report_progress(ProgressStage::Repoquery, Some(arch));
+        let packages = get_repo_contents(cache, release, arch, repos, globs, cancel).await?;
         let mut built: Vec<String> = Vec::new();
 
         for package in packages {
@@ -310,36 +1335,268 @@ pub async fn get_repo_closure<'a>(
     }
 
     let mut all_broken: Vec<BrokenItem> = Vec::new();
+    let mut downloads: Vec<RepoDownload> = Vec::new();
     for arch in arches {
-        make_cache(release, arch, repos).await?;
+        report_progress(ProgressStage::Makecache, Some(arch));
+        downloads.extend(make_cache(cache, release, arch, repos, min_free_space, cancel).await?);
 
-        let multi = multi_arch
-            .get(arch)
-            .unwrap_or_else(|| panic!("Invalid configuration for release {}", release));
+        let multi = match multi_arch.get(arch) {
+            Some(multi) => multi,
+            None => return Err(format!("Missing multiarch configuration for {}/{}.", release, arch)),
+        };
         let arch_excluded = excluded.get(arch.as_str()).expect("Something went terribly wrong.");
 
-        let mut broken = get_repo_closure_arched(release, arch, multi, repos, check, admins, maintainers).await?;
+        report_progress(ProgressStage::Closure, Some(arch));
+        let mut broken = get_repo_closure_arched(
+            cache,
+            release,
+            arch,
+            multi,
+            repos,
+            check,
+            admins,
+            maintainers,
+            globs,
+            cancel,
+        )
+        .await?;
 
         // skip source packages that do not produce any binaries on this architecture,
         // because this means that the current architecture is probably excluded
         broken.retain(|item| !(item.arch == "src" && arch_excluded.contains(&item.source.as_str())));
 
+        if let Some(mode) = modular {
+            match get_module_artifacts(cache, release, arch, repos, globs, cancel).await {
+                Ok(artifacts) => {
+                    for item in broken.iter_mut() {
+                        item.module = artifacts.get(&item.nevra()).cloned();
+                    }
+
+                    if mode == ModularMode::Exclude {
+                        broken.retain(|item| item.module.is_none());
+                    }
+                },
+                Err(error) => warn!("Failed to look up modules for {} / {}: {}", release, arch, error),
+            }
+        }
+
         all_broken.extend(broken);
     }
 
     all_broken.iter_mut().for_each(|item| {
         let arch = item.repo_arch.clone();
         let package = item.package.clone();
-
-        let mut guard = overrides.write().expect("Poisoned lock!");
-        item.broken
-            .retain(|broken| !guard.lookup(release, &arch, &package, broken))
+        let module = item.module.clone();
+
+        let mut guard = write_lock(&overrides);
+        item.broken.retain(|broken| {
+            // strip version constraints (e.g. "foo >= 1.0") for looking up previous providers
+            let capability = broken.split(' ').next().unwrap_or(broken);
+            let provider = previous_provides
+                .get(&arch)
+                .and_then(|index| index.get(capability))
+                .and_then(|providers| providers.first())
+                .map(|provider| provider.name.as_str());
+
+            !guard.lookup(release, &arch, &package, broken, provider, module.as_deref())
+        })
     });
 
     all_broken.retain(|item| !item.broken.is_empty());
 
+    for item in all_broken.iter_mut() {
+        item.categories = item.broken.iter().map(|dep| classify_dep(dep)).collect();
+    }
+
     // sort by (source, package, arch)
     all_broken.sort_by(|a, b| (&a.source, &a.package, &a.arch).cmp(&(&b.source, &b.package, &b.arch)));
 
-    Ok(all_broken)
+    let excluded = excluded
+        .into_iter()
+        .map(|(arch, packages)| (arch.to_string(), packages.into_iter().map(String::from).collect()))
+        .collect();
+
+    Ok(RepoClosure {
+        broken: all_broken,
+        excluded,
+        downloads,
+    })
+}
+
+/// Returns synthetic results from fixture files instead of running dnf (for development and testing).
+///
+/// Fixture files use the same format as the persisted data, and are looked up in the `fixtures` directory with the
+/// same layout as the `data` directory. Releases without a fixture file have no broken packages.
+#[cfg(feature = "test-backend")]
+pub fn get_fake_repo_closure(
+    product: Option<&str>,
+    release: &str,
+    testing: bool,
+    arches: &[String],
+) -> Result<RepoClosure, String> {
+    let path = get_fixture_path(product, release, testing);
+
+    let mut broken = if path.exists() {
+        read_json_from_file(&path)?
+    } else {
+        debug!("No fixture file found for {}: {}", release, path.to_string_lossy());
+        Vec::new()
+    };
+
+    broken.retain(|item| arches.contains(&item.repo_arch));
+
+    for item in broken.iter_mut() {
+        item.categories = item.broken.iter().map(|dep| classify_dep(dep)).collect();
+    }
+
+    broken.sort_by(|a, b| (&a.source, &a.package, &a.arch).cmp(&(&b.source, &b.package, &b.arch)));
+
+    let excluded = arches.iter().map(|arch| (arch.clone(), BTreeSet::new())).collect();
+
+    Ok(RepoClosure {
+        broken,
+        excluded,
+        downloads: Vec::new(),
+    })
+}
+
+/// Fixture files are only supported by builds with the `test-backend` feature.
+#[cfg(not(feature = "test-backend"))]
+pub fn get_fake_repo_closure(
+    _product: Option<&str>,
+    _release: &str,
+    _testing: bool,
+    _arches: &[String],
+) -> Result<RepoClosure, String> {
+    Err(String::from(
+        "Fake data is not supported by this build (the test-backend feature is disabled).",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn get_free_space() {
+        assert!(super::get_free_space(&std::env::temp_dir()).unwrap() > 0);
+        assert!(super::get_free_space(Path::new("/nonexistent/repochecker")).is_err());
+    }
+
+    #[test]
+    fn get_unused_caches() {
+        let root = std::env::temp_dir().join(format!("repochecker-caches-{}", uuid::Uuid::new_v4()));
+
+        let now = SystemTime::now();
+        for (cache, age) in [("41/x86_64", 60), ("41/aarch64", 120), ("rawhide/x86_64", 0)] {
+            let path = root.join(cache);
+            std::fs::create_dir_all(&path).unwrap();
+
+            let marker = std::fs::File::create(path.join(CACHE_MARKER)).unwrap();
+            marker.set_modified(now - Duration::from_secs(age)).unwrap();
+        }
+
+        // caches that are in use are never removed
+        let in_use = root.join("rawhide/x86_64");
+        *CACHES_IN_USE
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(in_use.clone())
+            .or_insert(0) += 1;
+
+        let caches = super::get_unused_caches(&root).unwrap();
+
+        CACHES_IN_USE
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&in_use);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        // least recently used caches are listed first
+        assert_eq!(caches, vec![root.join("41/aarch64"), root.join("41/x86_64")]);
+    }
+
+    #[test]
+    fn remove_cache_dir() {
+        let root = std::env::temp_dir().join(format!("repochecker-caches-{}", uuid::Uuid::new_v4()));
+        let unused = root.join("41/x86_64");
+        let in_use = root.join("41/aarch64");
+        std::fs::create_dir_all(&unused).unwrap();
+        std::fs::create_dir_all(&in_use).unwrap();
+
+        let lease = CacheLease { path: in_use.clone() };
+        *CACHES_IN_USE
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(in_use.clone())
+            .or_insert(0) += 1;
+
+        assert!(super::remove_cache_dir(&unused).is_ok());
+        assert!(super::remove_cache_dir(&in_use).is_err());
+        // missing caches are not an error
+        assert!(super::remove_cache_dir(&unused).is_ok());
+
+        drop(lease);
+        let remaining: Vec<PathBuf> = std::fs::read_dir(root.join("41"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(remaining, vec![in_use]);
+    }
+
+    #[test]
+    fn affected_arches() {
+        let arches = vec![String::from("ppc64"), String::from("ppc64le"), String::from("x86_64")];
+
+        let error = "dnf makecache for 41 / ppc64le exited with an error code: Error: Failed to load repodata";
+        assert_eq!(super::affected_arches(error, "41", &arches), vec!["ppc64le"]);
+
+        let error = "dnf repoclosure for 41 / ppc64 exited with an error code: repomd.xml checksum mismatch";
+        assert_eq!(super::affected_arches(error, "41", &arches), vec!["ppc64"]);
+
+        let error = "repomd.xml checksum mismatch";
+        assert_eq!(
+            super::affected_arches(error, "41", &arches),
+            vec!["ppc64", "ppc64le", "x86_64"]
+        );
+    }
+
+    #[test]
+    fn repo_downloads() {
+        let root = std::env::temp_dir().join(format!("repochecker-cache-{}", uuid::Uuid::new_v4()));
+
+        let started = SystemTime::now();
+        for (repo, size, age) in [
+            ("fedora-0123456789abcdef", 4, 0),
+            ("fedora-0123456789abcdef", 8, 3600),
+            ("fedora-updates-fedcba9876543210", 16, 0),
+        ] {
+            let path = root.join("var/cache/dnf").join(repo).join("repodata");
+            std::fs::create_dir_all(&path).unwrap();
+
+            let file = std::fs::File::create(path.join(format!("{}-{}.xml", size, age))).unwrap();
+            file.set_len(size).unwrap();
+            file.set_modified(started + Duration::from_secs(60) - Duration::from_secs(age))
+                .unwrap();
+        }
+
+        let fedora = find_repo_caches(&root, "fedora");
+        let updates = find_repo_caches(&root, "fedora-updates");
+        let modified = get_modified(&fedora[0], started);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        // repositories whose names are prefixes of other repositories are not confused
+        assert_eq!(fedora, vec![root.join("var/cache/dnf/fedora-0123456789abcdef")]);
+        assert_eq!(
+            updates,
+            vec![root.join("var/cache/dnf/fedora-updates-fedcba9876543210")]
+        );
+
+        // files that were not modified while refreshing the cache are not counted
+        assert_eq!(modified, (4, Some(started + Duration::from_secs(60))));
+    }
 }
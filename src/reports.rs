@@ -0,0 +1,972 @@
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::data::{BrokenItem, DepCategory, ProvideIndex};
+use crate::parse::{expand_alternatives, parse_soname, satisfies};
+
+#[derive(Debug, Serialize)]
+pub struct ImpactedPackage {
+    pub source: String,
+    pub package: String,
+    pub arch: String,
+    pub repo_arch: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SonameCluster {
+    pub library: String,
+    pub missing: BTreeSet<String>,
+    pub provided: BTreeSet<String>,
+    pub probable_bump: bool,
+    pub packages: Vec<ImpactedPackage>,
+}
+
+/// Groups shared library breakages by library basename.
+///
+/// If a library is still provided, but only with sonames that are different from the missing ones, this is reported
+/// as a probable soname bump.
+pub fn soname_bumps(values: &[BrokenItem], provided: &BTreeSet<String>) -> Vec<SonameCluster> {
+    let mut clusters: BTreeMap<String, (BTreeSet<String>, Vec<ImpactedPackage>)> = BTreeMap::new();
+
+    for item in values {
+        let mut libraries: BTreeSet<String> = BTreeSet::new();
+
+        for (dep, category) in item.broken.iter().zip(item.categories.iter()) {
+            // shared libraries can also be alternatives of rich dependencies
+            let alternatives = match category {
+                DepCategory::SharedLibrary => vec![dep.clone()],
+                DepCategory::Rich => expand_alternatives(dep),
+                _ => continue,
+            };
+
+            for alternative in alternatives {
+                if let Some((basename, soname)) = parse_soname(&alternative) {
+                    let (missing, _) = clusters.entry(basename.to_string()).or_default();
+                    missing.insert(soname.to_string());
+                    libraries.insert(basename.to_string());
+                }
+            }
+        }
+
+        for library in libraries {
+            let (_, packages) = clusters.entry(library).or_default();
+            packages.push(ImpactedPackage {
+                source: item.source.clone(),
+                package: item.package.clone(),
+                arch: item.arch.clone(),
+                repo_arch: item.repo_arch.clone(),
+            });
+        }
+    }
+
+    let mut output: Vec<SonameCluster> = clusters
+        .into_iter()
+        .map(|(library, (missing, packages))| {
+            let provided: BTreeSet<String> = provided
+                .iter()
+                .filter(|soname| parse_soname(soname).map(|(basename, _)| basename) == Some(library.as_str()))
+                .cloned()
+                .collect();
+
+            let probable_bump = !provided.is_empty() && provided.is_disjoint(&missing);
+
+            SonameCluster {
+                library,
+                missing,
+                provided,
+                probable_bump,
+                packages,
+            }
+        })
+        .collect();
+
+    // list libraries with the most impacted packages first
+    output.sort_by(|a, b| b.packages.len().cmp(&a.packages.len()).then(a.library.cmp(&b.library)));
+
+    output
+}
+
+/// Only keeps unresolved dependencies of the given category, and drops items without any.
+pub fn filter_category(values: &[BrokenItem], category: DepCategory) -> Vec<BrokenItem> {
+    values
+        .iter()
+        .filter_map(|item| {
+            let (broken, categories): (Vec<String>, Vec<DepCategory>) = item
+                .broken
+                .iter()
+                .zip(item.categories.iter())
+                .filter(|(_, c)| **c == category)
+                .map(|(b, c)| (b.clone(), *c))
+                .unzip();
+
+            if broken.is_empty() {
+                None
+            } else {
+                Some(BrokenItem {
+                    broken,
+                    categories,
+                    ..item.clone()
+                })
+            }
+        })
+        .collect()
+}
+
+/// Only keeps broken packages of the given architecture.
+///
+/// This also covers packages of secondary content architectures (like `i686`), which are checked as part of their
+/// primary architecture, and hence are reported with the primary architecture as `repo_arch`.
+pub fn filter_arch(values: &[BrokenItem], arch: &str) -> Vec<BrokenItem> {
+    values.iter().filter(|item| item.arch == arch).cloned().collect()
+}
+
+/// Only keeps broken packages that are members of the given comps group.
+pub fn filter_group(values: &[BrokenItem], group: &str) -> Vec<BrokenItem> {
+    values
+        .iter()
+        .filter(|item| item.groups.iter().any(|g| g == group))
+        .cloned()
+        .collect()
+}
+
+/// Default and maximum number of rows per page of the table of broken packages of a release.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Page and column filters of the table of broken packages of a release.
+///
+/// Filters match substrings of the respective column (ignoring case), and empty filters are ignored.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct TableQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub broken: Option<String>,
+}
+
+impl TableQuery {
+    /// Drops empty filters (which are submitted for empty fields of the filter form).
+    pub fn normalize(mut self) -> Self {
+        for filter in [
+            &mut self.source,
+            &mut self.package,
+            &mut self.arch,
+            &mut self.repo,
+            &mut self.admin,
+            &mut self.broken,
+        ] {
+            if filter.as_deref().is_some_and(|value| value.trim().is_empty()) {
+                *filter = None;
+            }
+        }
+        self
+    }
+
+    fn matches(&self, item: &BrokenItem) -> bool {
+        fn contains(filter: &Option<String>, value: &str) -> bool {
+            match filter {
+                Some(filter) => value.to_lowercase().contains(&filter.trim().to_lowercase()),
+                None => true,
+            }
+        }
+
+        contains(&self.source, &item.source)
+            && contains(&self.package, &item.package)
+            && contains(&self.arch, &item.arch)
+            && contains(&self.repo, &item.repo)
+            && (contains(&self.admin, &item.admin)
+                || item
+                    .maintainers
+                    .iter()
+                    .any(|maintainer| contains(&self.admin, maintainer)))
+            && (self.broken.is_none() || item.broken.iter().any(|dep| contains(&self.broken, dep)))
+    }
+
+    pub fn per_page(&self) -> usize {
+        self.per_page.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+/// One page of the broken packages of a release that match the filters of a query.
+#[derive(Debug)]
+pub struct TablePage<'a> {
+    pub items: Vec<&'a BrokenItem>,
+    // number of the page (starting at 1), and total number of pages
+    pub page: usize,
+    pub pages: usize,
+    // number of broken packages that match the filters
+    pub matching: usize,
+}
+
+pub fn paginate<'a>(values: &'a [BrokenItem], query: &TableQuery) -> TablePage<'a> {
+    let matching: Vec<&BrokenItem> = values.iter().filter(|item| query.matches(item)).collect();
+
+    let per_page = query.per_page();
+    let pages = matching.len().div_ceil(per_page).max(1);
+    let page = query.page.unwrap_or(1).clamp(1, pages);
+
+    TablePage {
+        matching: matching.len(),
+        items: matching
+            .into_iter()
+            .skip((page - 1) * per_page)
+            .take(per_page)
+            .collect(),
+        page,
+        pages,
+    }
+}
+
+/// Returns the page of the (unfiltered) table of broken packages of a release that contains the first row of the given
+/// source package.
+pub fn page_of_source(values: &[BrokenItem], source: &str) -> Option<usize> {
+    values
+        .iter()
+        .position(|item| item.source == source)
+        .map(|index| index / DEFAULT_PAGE_SIZE + 1)
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ArchDiffEntry {
+    pub source: String,
+    pub package: String,
+    pub broken_on: BTreeSet<String>,
+    pub fine_on: BTreeSet<String>,
+}
+
+/// Lists packages that are broken on some architectures, but not on others.
+///
+/// Architectures on which the source package was detected to be excluded (ExcludeArch / ExclusiveArch) are ignored.
+pub fn arch_diff(values: &[BrokenItem], excluded: &BTreeMap<String, BTreeSet<String>>) -> Vec<ArchDiffEntry> {
+    // fall back to architectures that are present in the data if no information about checked arches is available
+    let arches: BTreeSet<String> = if !excluded.is_empty() {
+        excluded.keys().cloned().collect()
+    } else {
+        values.iter().map(|item| item.repo_arch.clone()).collect()
+    };
+
+    let mut broken: BTreeMap<(&str, &str), BTreeSet<String>> = BTreeMap::new();
+    for item in values {
+        broken
+            .entry((&item.source, &item.package))
+            .or_default()
+            .insert(item.repo_arch.clone());
+    }
+
+    let mut output = Vec::new();
+    for ((source, package), broken_on) in broken {
+        let fine_on: BTreeSet<String> = arches
+            .iter()
+            .filter(|arch| !broken_on.contains(*arch))
+            .filter(|arch| !excluded.get(*arch).map(|e| e.contains(source)).unwrap_or(false))
+            .cloned()
+            .collect();
+
+        if !fine_on.is_empty() {
+            output.push(ArchDiffEntry {
+                source: source.to_string(),
+                package: package.to_string(),
+                broken_on,
+                fine_on,
+            });
+        }
+    }
+
+    output
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RegressionEntry {
+    pub source: String,
+    pub package: String,
+    pub arches: BTreeSet<String>,
+    pub broken: BTreeSet<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReleaseComparison {
+    pub newer: String,
+    pub older: String,
+    // broken in the newer release, but not in the older one
+    pub regressions: Vec<RegressionEntry>,
+    // broken in the older release, but not in the newer one
+    pub fixed: Vec<RegressionEntry>,
+}
+
+fn group_by_package(values: &[BrokenItem]) -> BTreeMap<(&str, &str), RegressionEntry> {
+    let mut grouped: BTreeMap<(&str, &str), RegressionEntry> = BTreeMap::new();
+
+    for item in values {
+        let entry = grouped
+            .entry((&item.source, &item.package))
+            .or_insert_with(|| RegressionEntry {
+                source: item.source.clone(),
+                package: item.package.clone(),
+                arches: BTreeSet::new(),
+                broken: BTreeSet::new(),
+            });
+
+        entry.arches.insert(item.repo_arch.clone());
+        entry.broken.extend(item.broken.iter().cloned());
+    }
+
+    grouped
+}
+
+/// Compares the broken packages of two releases.
+///
+/// Packages are matched by source and binary package name, regardless of their version and architecture.
+pub fn compare_releases(newer: (&str, &[BrokenItem]), older: (&str, &[BrokenItem])) -> ReleaseComparison {
+    let (newer_name, newer_values) = newer;
+    let (older_name, older_values) = older;
+
+    let mut newer_grouped = group_by_package(newer_values);
+    let mut older_grouped = group_by_package(older_values);
+
+    let common: Vec<(&str, &str)> = newer_grouped
+        .keys()
+        .filter(|key| older_grouped.contains_key(*key))
+        .copied()
+        .collect();
+
+    for key in common {
+        newer_grouped.remove(&key);
+        older_grouped.remove(&key);
+    }
+
+    ReleaseComparison {
+        newer: newer_name.to_string(),
+        older: older_name.to_string(),
+        regressions: newer_grouped.into_values().collect(),
+        fixed: older_grouped.into_values().collect(),
+    }
+}
+
+/// Compares consecutive releases (of the top-level releases and of each additional product) for which data is
+/// available.
+pub fn regressions<V: Borrow<Vec<BrokenItem>>>(config: &Config, values: &HashMap<String, V>) -> Vec<ReleaseComparison> {
+    let mut output = Vec::new();
+
+    for releases in config.release_series() {
+        for pair in releases.windows(2) {
+            let (newer, older) = (&pair[0], &pair[1]);
+            if let (Some(newer_values), Some(older_values)) = (values.get(newer), values.get(older)) {
+                output.push(compare_releases(
+                    (newer, newer_values.borrow()),
+                    (older, older_values.borrow()),
+                ));
+            }
+        }
+    }
+
+    output
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestingBreakage {
+    pub release: String,
+    pub packages: Vec<RegressionEntry>,
+}
+
+/// Lists packages that are broken with updates-testing enabled, but not in the stable data of the same release.
+///
+/// These are usually caused by updates that should not be pushed to stable before they are fixed.
+pub fn new_in_testing<V: Borrow<Vec<BrokenItem>>>(values: &HashMap<String, V>) -> Vec<TestingBreakage> {
+    let mut output = Vec::new();
+
+    for (key, testing) in values {
+        let release = match key.strip_suffix("-testing") {
+            Some(release) => release,
+            None => continue,
+        };
+
+        let stable: &[BrokenItem] = values.get(release).map(|v| v.borrow().as_slice()).unwrap_or_default();
+        let comparison = compare_releases((key, testing.borrow()), (release, stable));
+
+        output.push(TestingBreakage {
+            release: release.to_string(),
+            packages: comparison.regressions,
+        });
+    }
+
+    output.sort_by(|a, b| a.release.cmp(&b.release));
+    output
+}
+
+#[derive(Debug, Serialize)]
+pub struct InactiveEntry {
+    pub admin: String,
+    pub packages: BTreeSet<(String, String)>,
+}
+
+/// Lists the (release, source package) pairs of broken packages for every main admin that is inactive.
+pub fn inactive_admins<'a>(
+    releases: impl IntoIterator<Item = (&'a str, &'a [BrokenItem])>,
+    inactive: &HashSet<String>,
+) -> Vec<InactiveEntry> {
+    let mut report: BTreeMap<String, BTreeSet<(String, String)>> = BTreeMap::new();
+
+    for (release, values) in releases {
+        for item in values {
+            if inactive.contains(&item.admin) {
+                report
+                    .entry(item.admin.clone())
+                    .or_default()
+                    .insert((release.to_string(), item.source.clone()));
+            }
+        }
+    }
+
+    report
+        .into_iter()
+        .map(|(admin, packages)| InactiveEntry { admin, packages })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct CapabilityReport {
+    pub release: String,
+    pub capability: String,
+    pub providers: Vec<CapabilityProvider>,
+    // packages that are broken because nothing provides the capability
+    pub broken: Vec<BrokenItem>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CapabilityProvider {
+    pub arch: String,
+    pub name: String,
+    pub source: String,
+    // version of the provided capability (if it is versioned)
+    pub evr: Option<String>,
+    // whether the package still provides the capability (otherwise, it is the last known provider)
+    pub current: bool,
+    // whether the provided version satisfies the version constraint of the query (always true without one)
+    pub satisfies: bool,
+}
+
+/// Looks up which packages provide a capability (on every architecture), and which packages are broken because it is
+/// not provided at all (or not in a version that satisfies their dependency).
+///
+/// The capability can be looked up with a version constraint (for example, "foo >= 2.0"), which is compared with the
+/// provided versions. Unresolved dependencies with version constraints and rich dependencies match if they refer to
+/// the capability.
+pub fn capability_report(
+    release: &str,
+    capability: &str,
+    indices: &[(String, ProvideIndex)],
+    values: &[BrokenItem],
+) -> CapabilityReport {
+    let name = capability.split(' ').next().unwrap_or(capability);
+
+    let refers_to = |dep: &String| {
+        expand_alternatives(dep)
+            .iter()
+            .any(|alternative| alternative.split(' ').next() == Some(name))
+    };
+
+    let broken: Vec<BrokenItem> = values
+        .iter()
+        .filter(|item| item.broken.iter().any(refers_to))
+        .cloned()
+        .collect();
+
+    let providers = indices
+        .iter()
+        .flat_map(|(arch, index)| {
+            index
+                .get(name)
+                .into_iter()
+                .flatten()
+                .map(move |provider| CapabilityProvider {
+                    arch: arch.clone(),
+                    name: provider.name.clone(),
+                    source: provider.source.clone(),
+                    evr: provider.evr.clone(),
+                    // capabilities that are still required but no longer provided keep their last known providers
+                    current: !provider.previous,
+                    satisfies: satisfies(provider.evr.as_deref(), capability),
+                })
+        })
+        .collect();
+
+    CapabilityReport {
+        release: release.to_string(),
+        capability: capability.to_string(),
+        providers,
+        broken,
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SearchResult<'a> {
+    pub release: &'a str,
+    // fields that contain the query ("package", "source", "maintainer", or "broken")
+    pub matched: Vec<&'static str>,
+    #[serde(flatten)]
+    pub item: &'a BrokenItem,
+}
+
+/// One page of the results of a search.
+#[derive(Debug, Serialize)]
+pub struct SearchPage<'a> {
+    // number of the page (starting at 1), total number of pages, and number of all results
+    pub page: usize,
+    pub pages: usize,
+    pub total: usize,
+    pub results: Vec<SearchResult<'a>>,
+}
+
+/// Finds broken packages whose package name, source package name, maintainers, or unresolved dependencies contain the
+/// query (ignoring case). Packages where any of these start with the query are listed first.
+///
+/// Results are returned in pages of `limit` results (with the same default and maximum as tables of broken packages).
+pub fn search<'a>(
+    releases: impl IntoIterator<Item = (&'a str, &'a [BrokenItem])>,
+    query: &str,
+    page: Option<usize>,
+    limit: Option<usize>,
+) -> SearchPage<'a> {
+    let query = query.to_lowercase();
+
+    let mut results: Vec<(bool, SearchResult)> = Vec::new();
+
+    for (release, values) in releases {
+        for item in values {
+            let maintainers = std::iter::once(&item.admin).chain(item.maintainers.iter());
+
+            let fields: [(&'static str, Vec<&String>); 4] = [
+                ("package", vec![&item.package]),
+                ("source", vec![&item.source]),
+                ("maintainer", maintainers.collect()),
+                ("broken", item.broken.iter().collect()),
+            ];
+
+            let mut matched = Vec::new();
+            let mut prefix = false;
+
+            for (field, values) in fields {
+                let values: Vec<String> = values.into_iter().map(|value| value.to_lowercase()).collect();
+
+                if values.iter().any(|value| value.contains(&query)) {
+                    matched.push(field);
+                    prefix |= values.iter().any(|value| value.starts_with(&query));
+                }
+            }
+
+            if !matched.is_empty() {
+                results.push((prefix, SearchResult { release, matched, item }));
+            }
+        }
+    }
+
+    results.sort_by(|(a_prefix, a), (b_prefix, b)| {
+        b_prefix.cmp(a_prefix).then_with(|| {
+            (a.release, &a.item.source, &a.item.package).cmp(&(b.release, &b.item.source, &b.item.package))
+        })
+    });
+
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let total = results.len();
+    let pages = total.div_ceil(limit).max(1);
+    let page = page.unwrap_or(1).clamp(1, pages);
+
+    SearchPage {
+        page,
+        pages,
+        total,
+        results: results
+            .into_iter()
+            .skip((page - 1) * limit)
+            .take(limit)
+            .map(|(_, result)| result)
+            .collect(),
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CombinedItem {
+    #[serde(flatten)]
+    pub item: BrokenItem,
+    // whether the package is only broken with updates-testing enabled
+    pub testing_only: bool,
+}
+
+/// Merges the data for the stable and testing variants of a release into one list.
+///
+/// Packages that are broken in both variants are only listed once (with the data from the testing variant, which is
+/// more recent). Items from the testing data are marked as `testing_only` unless the same package is also broken (on
+/// the same architecture) in the stable data.
+pub fn combine_testing(stable: &[BrokenItem], testing: &[BrokenItem]) -> Vec<CombinedItem> {
+    let key = |item: &BrokenItem| (item.package.clone(), item.arch.clone(), item.repo_arch.clone());
+
+    let broken: HashSet<(String, String, String)> = stable.iter().map(key).collect();
+    let superseded: HashSet<(String, String, String)> = testing.iter().map(key).collect();
+
+    let stable = stable
+        .iter()
+        .filter(|item| !superseded.contains(&key(item)))
+        .map(|item| CombinedItem {
+            item: item.clone(),
+            testing_only: false,
+        });
+
+    let testing = testing.iter().map(|item| CombinedItem {
+        item: item.clone(),
+        testing_only: !broken.contains(&key(item)),
+    });
+
+    stable.chain(testing).collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedItem {
+    pub old: BrokenItem,
+    pub new: BrokenItem,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MaintainerChange {
+    pub package: String,
+    pub repo: String,
+    pub repo_arch: String,
+    pub old_admin: String,
+    pub new_admin: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DataDiff {
+    pub added: Vec<BrokenItem>,
+    pub removed: Vec<BrokenItem>,
+    pub changed: Vec<ChangedItem>,
+    pub maintainer_changed: Vec<MaintainerChange>,
+}
+
+/// Compares two snapshots of data for the same release.
+///
+/// Items are matched by package, repository, and repository architecture. Matching items are reported as changed if
+/// their version or their unresolved dependencies differ, and changes of their main admin are reported separately.
+pub fn diff_items(old: &[BrokenItem], new: &[BrokenItem]) -> DataDiff {
+    fn key(item: &BrokenItem) -> (&str, &str, &str) {
+        (&item.package, &item.repo, &item.repo_arch)
+    }
+
+    let old_map: BTreeMap<(&str, &str, &str), &BrokenItem> = old.iter().map(|item| (key(item), item)).collect();
+    let new_map: BTreeMap<(&str, &str, &str), &BrokenItem> = new.iter().map(|item| (key(item), item)).collect();
+
+    let mut diff = DataDiff::default();
+
+    for (key, new_item) in &new_map {
+        match old_map.get(key) {
+            Some(old_item) => {
+                let old_broken: BTreeSet<&String> = old_item.broken.iter().collect();
+                let new_broken: BTreeSet<&String> = new_item.broken.iter().collect();
+
+                if old_item.evr() != new_item.evr() || old_broken != new_broken {
+                    diff.changed.push(ChangedItem {
+                        old: (*old_item).clone(),
+                        new: (*new_item).clone(),
+                    });
+                }
+
+                if old_item.admin != new_item.admin {
+                    diff.maintainer_changed.push(MaintainerChange {
+                        package: new_item.package.clone(),
+                        repo: new_item.repo.clone(),
+                        repo_arch: new_item.repo_arch.clone(),
+                        old_admin: old_item.admin.clone(),
+                        new_admin: new_item.admin.clone(),
+                    });
+                }
+            },
+            None => diff.added.push((*new_item).clone()),
+        }
+    }
+
+    for (key, old_item) in &old_map {
+        if !new_map.contains_key(key) {
+            diff.removed.push((*old_item).clone());
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::data::broken_item as item;
+
+    #[test]
+    fn capability_report() {
+        use crate::data::Provider;
+
+        let provider = |name: &str, evr: &str, previous: bool| Provider {
+            name: name.to_string(),
+            source: String::from("foo"),
+            evr: Some(evr.to_string()),
+            previous,
+        };
+
+        let indices = vec![
+            (
+                String::from("x86_64"),
+                ProvideIndex::from([(
+                    String::from("foo"),
+                    vec![
+                        provider("foo", "1.0-1.fc41", false),
+                        provider("foo-compat", "2.0-1.fc41", false),
+                    ],
+                )]),
+            ),
+            (
+                String::from("aarch64"),
+                ProvideIndex::from([(String::from("foo"), vec![provider("foo", "0.9-1.fc41", true)])]),
+            ),
+        ];
+        let values = vec![item("bar", &["foo >= 2.0"]), item("baz", &["libbaz.so.1()(64bit)"])];
+
+        // all providers are listed, with whether their version satisfies the version constraint of the query
+        let report = super::capability_report("41", "foo >= 2.0", &indices, &values);
+        let providers: Vec<(&str, &str, bool, bool)> = report
+            .providers
+            .iter()
+            .map(|provider| {
+                (
+                    provider.arch.as_str(),
+                    provider.name.as_str(),
+                    provider.current,
+                    provider.satisfies,
+                )
+            })
+            .collect();
+        assert_eq!(
+            providers,
+            vec![
+                ("x86_64", "foo", true, false),
+                ("x86_64", "foo-compat", true, true),
+                ("aarch64", "foo", false, false),
+            ]
+        );
+        assert_eq!(report.broken, vec![values[0].clone()]);
+
+        // without a version constraint, all providers satisfy the query
+        let report = super::capability_report("41", "foo", &indices, &values);
+        assert!(report.providers.iter().all(|provider| provider.satisfies));
+    }
+
+    #[test]
+    fn search() {
+        let values = vec![
+            item("python-foo", &["python3dist(bar)"]),
+            item("foo", &["libfoo.so.1()(64bit)"]),
+            item("qux", &["perl(Qux)"]),
+        ];
+
+        let results = super::search([("41", values.as_slice())], "Foo", None, None).results;
+
+        assert_eq!(results.len(), 2);
+        // prefix matches are listed first
+        assert_eq!(results[0].item.package, "foo");
+        assert_eq!(results[0].matched, vec!["package", "source", "broken"]);
+        assert_eq!(results[1].item.package, "python-foo");
+
+        let results = super::search([("41", values.as_slice())], "alice", None, None).results;
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].matched, vec!["maintainer"]);
+
+        // results are paginated, and the limit is at least one result
+        let page = super::search([("41", values.as_slice())], "alice", Some(2), Some(2));
+        assert_eq!((page.page, page.pages, page.total), (2, 2, 3));
+        assert_eq!(page.results.len(), 1);
+        assert_eq!(page.results[0].item.package, "qux");
+
+        let page = super::search([("41", values.as_slice())], "alice", Some(5), Some(0));
+        assert_eq!((page.page, page.pages), (3, 3));
+    }
+
+    #[test]
+    fn paginate() {
+        let values: Vec<BrokenItem> = (0..25)
+            .map(|i| item(&format!("python-foo{}", i), &["python3dist(bar)"]))
+            .chain([item("qux", &["perl(Qux)"])])
+            .collect();
+
+        let query = TableQuery {
+            page: Some(3),
+            per_page: Some(10),
+            package: Some(String::from("Python")),
+            broken: Some(String::new()),
+            ..Default::default()
+        }
+        .normalize();
+        let page = super::paginate(&values, &query);
+
+        assert_eq!(page.matching, 25);
+        assert_eq!(page.pages, 3);
+        assert_eq!(page.items.len(), 5);
+        assert_eq!(page.items[0].package, "python-foo20");
+
+        // pages past the end are clamped to the last page
+        let query = TableQuery {
+            page: Some(10),
+            broken: Some(String::from("perl")),
+            ..Default::default()
+        };
+        let page = super::paginate(&values, &query);
+
+        assert_eq!((page.page, page.pages, page.matching), (1, 1, 1));
+        assert_eq!(page.items[0].package, "qux");
+
+        assert_eq!(page_of_source(&values, "python-foo0"), Some(1));
+        assert_eq!(page_of_source(&values, "qux"), Some(1));
+        assert_eq!(page_of_source(&values, "bar"), None);
+    }
+
+    #[test]
+    fn soname_bumps() {
+        let values = vec![
+            item("foo", &["libfoo.so.1()(64bit)", "libbar.so.3()(64bit)"]),
+            item("foo-tools", &["libfoo.so.1()(64bit)", "python3dist(foo)"]),
+            item("foo-extras", &["(libfoo.so.1()(64bit) if foo-extras-gui)"]),
+        ];
+
+        let provided = BTreeSet::from([String::from("libfoo.so.2")]);
+
+        let clusters = super::soname_bumps(&values, &provided);
+
+        assert_eq!(clusters.len(), 2);
+
+        assert_eq!(clusters[0].library, "libfoo.so");
+        assert_eq!(clusters[0].packages.len(), 3);
+        assert!(clusters[0].probable_bump);
+
+        assert_eq!(clusters[1].library, "libbar.so");
+        assert_eq!(clusters[1].packages.len(), 1);
+        assert!(!clusters[1].probable_bump);
+    }
+
+    #[test]
+    fn combine_testing() {
+        let stable = vec![item("foo", &["bar"]), item("quux", &["bar"])];
+        let testing = vec![item("foo", &["bar", "qux"]), item("baz", &["qux"])];
+
+        let combined = super::combine_testing(&stable, &testing);
+
+        // packages that are broken in both variants are listed once, with the data from testing
+        assert_eq!(combined.len(), 3);
+        assert_eq!(combined[0].item.package, "quux");
+        assert!(!combined[0].testing_only);
+        assert_eq!(combined[1].item.package, "foo");
+        assert_eq!(combined[1].item.broken.len(), 2);
+        assert!(!combined[1].testing_only);
+        assert_eq!(combined[2].item.package, "baz");
+        assert!(combined[2].testing_only);
+    }
+
+    #[test]
+    fn new_in_testing() {
+        let values = HashMap::from([
+            (String::from("41"), vec![item("foo", &["bar"])]),
+            (
+                String::from("41-testing"),
+                vec![item("foo", &["bar"]), item("baz", &["qux"])],
+            ),
+            (String::from("40"), vec![item("baz", &["qux"])]),
+        ]);
+
+        let report = super::new_in_testing(&values);
+
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].release, "41");
+        assert_eq!(report[0].packages.len(), 1);
+        assert_eq!(report[0].packages[0].package, "baz");
+    }
+
+    #[test]
+    fn arch_diff() {
+        let arched = |package: &str, arch: &str| BrokenItem {
+            repo_arch: arch.to_string(),
+            ..item(package, &["bar"])
+        };
+
+        let values = vec![
+            arched("foo", "x86_64"),
+            arched("foo", "aarch64"),
+            arched("baz", "x86_64"),
+            arched("qux", "x86_64"),
+        ];
+
+        let excluded = BTreeMap::from([
+            (String::from("x86_64"), BTreeSet::new()),
+            (String::from("aarch64"), BTreeSet::from([String::from("qux")])),
+            (
+                String::from("s390x"),
+                BTreeSet::from([String::from("foo"), String::from("qux")]),
+            ),
+        ]);
+
+        let diff = super::arch_diff(&values, &excluded);
+
+        assert_eq!(
+            diff,
+            vec![ArchDiffEntry {
+                source: String::from("baz"),
+                package: String::from("baz"),
+                broken_on: BTreeSet::from([String::from("x86_64")]),
+                fine_on: BTreeSet::from([String::from("aarch64"), String::from("s390x")]),
+            }]
+        );
+    }
+
+    #[test]
+    fn compare_releases() {
+        let newer = vec![item("foo", &["libbar.so.2()(64bit)"]), item("baz", &["qux"])];
+        let older = vec![item("baz", &["qux"]), item("quux", &["corge"])];
+
+        let comparison = super::compare_releases(("rawhide", &newer), ("41", &older));
+
+        assert_eq!(comparison.regressions.len(), 1);
+        assert_eq!(comparison.regressions[0].package, "foo");
+        assert_eq!(comparison.fixed.len(), 1);
+        assert_eq!(comparison.fixed[0].package, "quux");
+    }
+
+    #[test]
+    fn diff_items() {
+        let old = vec![item("foo", &["bar"]), item("baz", &["qux"]), item("quux", &["corge"])];
+
+        let mut updated = item("baz", &["qux", "grault"]);
+        updated.version = String::from("2.0");
+        updated.admin = String::from("bob");
+        let new = vec![item("foo", &["bar"]), updated, item("garply", &["waldo"])];
+
+        let diff = super::diff_items(&old, &new);
+
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].package, "garply");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].package, "quux");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].new.version, "2.0");
+        assert_eq!(diff.maintainer_changed.len(), 1);
+        assert_eq!(diff.maintainer_changed[0].old_admin, "alice");
+        assert_eq!(diff.maintainer_changed[0].new_admin, "bob");
+    }
+}
@@ -1,411 +1,3712 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::net::SocketAddr;
-use std::sync::{Arc, RwLock};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
 
-use askama::Template;
 use chrono::{DateTime, Utc};
-use log::{error, info};
-use serde::Serialize;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
 
-use axum::extract::Path;
-use axum::http::header::CONTENT_TYPE;
-use axum::http::{HeaderMap, StatusCode};
-use axum::routing::get;
+use axum::body::{Bytes, StreamBody};
+use axum::extract::{DefaultBodyLimit, Path, Query};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
 use axum::{Router, Server};
 
-use crate::config::{get_config, Config, MatrixEntry};
-use crate::data::BrokenItem;
-use crate::overrides::Overrides;
-use crate::pagure::{get_admins, get_maintainers};
-use crate::repo::get_repo_closure;
-use crate::templates::Index;
-use crate::utils::{get_json_path, read_json_from_file, write_json_to_file};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::accounts::{flag_inactive, get_inactive};
+use crate::alerts::{evaluate as evaluate_alerts, Alert};
+use crate::config::{
+    diff_matrix, get_config, release_key, AdminConfig, Config, MatrixEntry, PublishConfig, RetiredData, SigningConfig,
+    UrlConfig,
+};
+use crate::critpath::{flag_critpath, load_critpath, CritPath};
+use crate::data::{
+    BrokenItem, DepCategory, FailureReason, FrozenData, InstallCheck, InstallKind, ProgressStage, ProvideIndex,
+    RepoDownload, SinceIndex,
+};
+use crate::feeds::maintainer_feed;
+use crate::hints::get_hints;
+use crate::history::{
+    get_history_path, list_generations, parse_timestamp, read_generation, read_generation_at, write_generation,
+};
+use crate::import::{external_entry, validate as validate_import};
+use crate::overrides::{load_product_overrides, schema as overrides_schema, OverrideStats, Overrides};
+use crate::pagure::{account_churn, get_accounts, AccountChurn};
+use crate::parse::{classify_dnf_error, expand_alternatives, parse_dependency_dump, parse_soname};
+use crate::publish::{data_key, publish_file};
+use crate::readiness::Readiness;
+use crate::repo::{
+    check_installable, count_dependents, dnf_cpu_time, dnf_errors, get_cache_size, get_dependency_dump,
+    get_fake_repo_closure, get_package_groups, get_provided_sonames, get_repo_closure, index_provides, lease_caches,
+    remove_cache, report_progress, with_cpu_accounting, with_dnf_options, with_error_accounting, with_progress,
+    with_verbose_output, ProgressHook,
+};
+use crate::reports::{
+    arch_diff, capability_report, combine_testing, diff_items, filter_arch, filter_category, filter_group,
+    inactive_admins, new_in_testing, page_of_source, regressions, search, soname_bumps, DataDiff, TableQuery,
+};
+use crate::signing::{get_signature_path, sign_release};
+use crate::snapshot::StateSnapshot;
+use crate::templates::{source_anchor, table_export_path, Index, IndexEntry, ReleaseTable};
+use crate::utils::{
+    archive_json_file, get_data_path, get_frozen_path, get_index_export_path, get_json_path, get_provides_path,
+    get_schedule_path, get_since_path, read_frozen_from_file, read_frozen_summary, read_json_from_file, read_lock,
+    read_provides_from_file, read_schedule_from_file, read_since_from_file, schema_for, write_frozen_to_file,
+    write_lock, write_provides_to_file, write_schedule_to_file, write_since_to_file, DATA_DIRECTORIES,
+};
+use crate::version::{get_version_info, VERSION};
 
 pub(crate) struct State {
     pub(crate) config: Config,
     pub(crate) overrides: Arc<RwLock<Overrides>>,
+    pub(crate) product_overrides: HashMap<String, Arc<RwLock<Overrides>>>,
     pub(crate) admins: HashMap<String, String>,
     pub(crate) maintainers: HashMap<String, Vec<String>>,
     pub(crate) values: HashMap<String, Arc<Vec<BrokenItem>>>,
+    // frozen data of archived releases, which is only loaded into `values` when it is requested
+    pub(crate) archived: HashMap<String, ArchivedRelease>,
+    // when the data of archived releases that are currently loaded was last requested (which is recorded without
+    // locking the whole state for writing)
+    pub(crate) archived_access: Mutex<HashMap<String, Instant>>,
     pub(crate) date_refreshed: Option<DateTime<Utc>>,
+    pub(crate) frozen: HashMap<String, DateTime<Utc>>,
+    // archived releases whose data could not be frozen, which are not checked again in refresh cycles (only when they
+    // are rerun)
+    pub(crate) freeze_failed: HashSet<String>,
+    // when data was last imported for external releases
+    pub(crate) imported: HashMap<String, DateTime<Utc>>,
+    pub(crate) inactive: HashSet<String>,
+    pub(crate) provided_sonames: HashMap<String, BTreeSet<String>>,
+    // critical path packages, which are fetched once per refresh cycle (the previous list is kept if that fails)
+    pub(crate) critpath: Option<Arc<CritPath>>,
+    // persisted provides of all architectures of a release, which are read when they are first requested after a run
+    pub(crate) provides: HashMap<String, Arc<Vec<(String, ProvideIndex)>>>,
+    pub(crate) excluded: HashMap<String, BTreeMap<String, BTreeSet<String>>>,
+    pub(crate) install_checks: HashMap<String, Vec<InstallCheck>>,
+    pub(crate) cycle: Option<Uuid>,
+    pub(crate) runs: HashMap<String, RunInfo>,
+    pub(crate) schedule: HashMap<String, Schedule>,
+    // most recent failure of checking a release (cleared when checking it succeeds again)
+    pub(crate) failures: HashMap<String, RunFailure>,
+    // unfiltered results of releases with quarantined packages, and the number of consecutive runs in which each
+    // package was broken (only if a confirmation window is configured)
+    pub(crate) raw: HashMap<String, Arc<Vec<BrokenItem>>>,
+    pub(crate) streaks: HashMap<String, HashMap<String, usize>>,
+    // changes of package maintainers in the most recent refresh
+    pub(crate) account_churn: Option<AccountChurn>,
+    // alerts that were raised for the most recent results of a release
+    pub(crate) alerts: HashMap<String, Vec<Alert>>,
+    // progress of releases that are currently being checked
+    pub(crate) progress: HashMap<String, WorkerProgress>,
+    // releases that are currently being checked (in refresh cycles or reruns), which are claimed before checks start
+    // so that releases are never checked twice at the same time
+    pub(crate) checking: HashSet<String>,
+    // releases that are checked outside of refresh cycles, with the tokens for aborting these runs
+    pub(crate) reruns: HashMap<String, CancellationToken>,
+    // threads that releases are checked on (not set in tests, where checks run on the runtime of the test)
+    pub(crate) workers: Option<tokio::runtime::Handle>,
+    // releases that were cancelled when the previous refresh cycle exceeded its maximum duration
+    pub(crate) carried_over: BTreeSet<String>,
+    // when the next refresh cycle starts (not set while a refresh cycle is running)
+    pub(crate) next_cycle: Option<DateTime<Utc>>,
+    // whether new refresh cycles are started (running workers are not affected)
+    pub(crate) paused: bool,
+    // reason why the service started without package maintainers (checks are delayed until they are available)
+    pub(crate) degraded: Option<String>,
+    // results of checking the requirements of the service on startup
+    pub(crate) readiness: Option<Readiness>,
+    // whether results are generated from fixture files (they are only kept in memory, and never persisted or published)
+    pub(crate) fake_data: bool,
+}
+
+/// Provenance of the most recent data that was generated for a release.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct RunInfo {
+    pub(crate) run: Uuid,
+    pub(crate) cycle: Uuid,
+    pub(crate) started_at: DateTime<Utc>,
+    pub(crate) finished_at: DateTime<Utc>,
+    // resource usage of the run: wall time and CPU time of dnf (in seconds), and size of the caches at the end of the
+    // run (in bytes)
+    pub(crate) wall_time: f64,
+    pub(crate) dnf_cpu_time: f64,
+    #[serde(alias = "peak_cache_size")]
+    pub(crate) cache_size: Option<u64>,
+    // metadata downloads of all repositories (only if the caches were refreshed)
+    #[serde(default)]
+    pub(crate) downloads: Vec<RepoDownload>,
+}
+
+/// Frozen data of an archived release that is stored on disk.
+#[derive(Clone, Debug)]
+pub(crate) struct ArchivedRelease {
+    pub(crate) path: PathBuf,
+    // number of broken packages (and of those that are part of the critical path), for the index page
+    pub(crate) items: usize,
+    pub(crate) critpath: usize,
+}
+
+/// Parameters that are overridden for a single run of a release that was requested via the administrative API.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct RerunOptions {
+    // remove the dnf caches of the release before checking it
+    #[serde(default)]
+    pub(crate) clean_cache: bool,
+    // only check this architecture (results for other architectures are kept)
+    #[serde(default)]
+    pub(crate) arch: Option<String>,
+    // log all dnf commands and their error output
+    #[serde(default)]
+    pub(crate) verbose: bool,
+    // additional dnf options (for example, "debuglevel=10"), which are passed as "--setopt" arguments
+    #[serde(default)]
+    pub(crate) setopt: Vec<String>,
+}
+
+/// Progress of a release that is currently being checked.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct WorkerProgress {
+    pub(crate) stage: ProgressStage,
+    pub(crate) arch: Option<String>,
+    // number of the current stage, and estimated number of stages of the whole run
+    pub(crate) step: usize,
+    pub(crate) steps: usize,
+    pub(crate) started_at: DateTime<Utc>,
+    pub(crate) stage_started_at: DateTime<Utc>,
+}
+
+/// Failure of checking a release, with a machine-readable reason.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct RunFailure {
+    pub(crate) reason: FailureReason,
+    pub(crate) message: String,
+    pub(crate) failed_at: DateTime<Utc>,
+}
+
+/// Refresh schedule of a release (only used if adaptive refresh intervals are enabled).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct Schedule {
+    // current refresh interval (in hours)
+    pub(crate) interval: f64,
+    pub(crate) next_run: DateTime<Utc>,
+}
+
+/// Refresh schedules of releases and releases that were carried over from the previous refresh cycle, which are
+/// persisted so that they survive restarts of the service.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct PersistedSchedule {
+    #[serde(default)]
+    schedule: HashMap<String, Schedule>,
+    #[serde(default)]
+    carried_over: BTreeSet<String>,
+}
+
+/// Persists refresh schedules and releases that were carried over.
+pub(crate) fn persist_schedule(state: &GlobalState) {
+    let persisted = {
+        let guard = read_lock(state);
+        if guard.fake_data {
+            return;
+        }
+
+        PersistedSchedule {
+            schedule: guard.schedule.clone(),
+            carried_over: guard.carried_over.clone(),
+        }
+    };
+
+    if let Err(error) = write_schedule_to_file(&get_schedule_path(), &persisted) {
+        error!("Failed to persist refresh schedule: {}", error);
+    }
+}
+
+/// Restores refresh schedules and releases that were carried over from a previous run of the service (only for
+/// releases that are still configured).
+pub(crate) fn load_schedule(state: &GlobalState, matrix: &[MatrixEntry]) {
+    match read_schedule_from_file(&get_schedule_path()) {
+        Ok(persisted) => restore_schedule(&mut write_lock(state), persisted, matrix),
+        Err(error) => info!("{}", error),
+    }
+}
+
+fn restore_schedule(state: &mut State, persisted: PersistedSchedule, matrix: &[MatrixEntry]) {
+    let keys: HashSet<String> = matrix.iter().map(MatrixEntry::key).collect();
+
+    state.schedule = persisted
+        .schedule
+        .into_iter()
+        .filter(|(key, _)| keys.contains(key))
+        .collect();
+    state.carried_over = persisted
+        .carried_over
+        .into_iter()
+        .filter(|key| keys.contains(key))
+        .collect();
 }
 
 impl State {
+    /// Returns whether a release is due to be checked again, based on its schedule.
+    pub(crate) fn is_due(&self, key: &str, now: DateTime<Utc>) -> bool {
+        match self.schedule.get(key) {
+            Some(schedule) => schedule.next_run <= now,
+            None => true,
+        }
+    }
+
+    /// Drops the data of archived releases from memory if it was not requested within the idle time, or if more than
+    /// `capacity` archived releases are loaded (starting with the least recently used ones), and returns their keys.
+    pub(crate) fn evict_archived(&mut self, capacity: usize, idle: Duration, now: Instant) -> Vec<String> {
+        let access = self.archived_access.get_mut().unwrap_or_else(PoisonError::into_inner);
+
+        let mut loaded: Vec<(Instant, String)> = self
+            .archived
+            .keys()
+            .filter(|key| self.values.contains_key(*key))
+            .map(|key| (access.get(key).copied().unwrap_or(now), key.clone()))
+            .collect();
+
+        // most recently used first
+        loaded.sort_by(|a, b| b.cmp(a));
+
+        let mut evicted = Vec::new();
+        for (index, (last_access, key)) in loaded.into_iter().enumerate() {
+            if index < capacity && now.saturating_duration_since(last_access) <= idle {
+                continue;
+            }
+
+            self.values.remove(&key);
+            access.remove(&key);
+            evicted.push(key);
+        }
+
+        evicted
+    }
+
+    /// Records that the data of an archived release was requested.
+    fn touch_archived(&self, key: &str) {
+        self.archived_access
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key.to_string(), Instant::now());
+    }
+
     pub(crate) fn init(
         config: Config,
         overrides: Overrides,
+        product_overrides: HashMap<String, Arc<RwLock<Overrides>>>,
         admins: HashMap<String, String>,
         maintainers: HashMap<String, Vec<String>>,
+        fake_data: bool,
     ) -> State {
         State {
             config,
             overrides: Arc::new(RwLock::new(overrides)),
+            product_overrides,
             admins,
             maintainers,
             values: HashMap::new(),
+            archived: HashMap::new(),
+            archived_access: Mutex::new(HashMap::new()),
             date_refreshed: None,
+            frozen: HashMap::new(),
+            freeze_failed: HashSet::new(),
+            imported: HashMap::new(),
+            inactive: HashSet::new(),
+            provided_sonames: HashMap::new(),
+            critpath: None,
+            provides: HashMap::new(),
+            excluded: HashMap::new(),
+            install_checks: HashMap::new(),
+            cycle: None,
+            runs: HashMap::new(),
+            schedule: HashMap::new(),
+            failures: HashMap::new(),
+            raw: HashMap::new(),
+            account_churn: None,
+            streaks: HashMap::new(),
+            alerts: HashMap::new(),
+            progress: HashMap::new(),
+            checking: HashSet::new(),
+            reruns: HashMap::new(),
+            workers: None,
+            carried_over: BTreeSet::new(),
+            next_cycle: None,
+            paused: false,
+            degraded: None,
+            readiness: None,
+            fake_data,
         }
     }
 }
 
 pub(crate) type GlobalState = Arc<RwLock<State>>;
 
-pub(crate) async fn watcher(state: GlobalState) {
-    match get_config() {
-        Ok(config) => {
-            let mut guard = state.write().expect("Found a poisoned lock.");
-            let state = &mut *guard;
-            state.config = config;
-        },
-        Err(error) => error!("Failed to read updated configuration: {}", error),
-    };
+/// Dedicated runtime for checking releases, so that heavy refresh cycles (dnf processes and (de)serialization of large
+/// data files) do not compete with handling HTTP requests.
+pub(crate) struct WorkerPool {
+    // the runtime is only taken when the pool is dropped
+    runtime: Option<tokio::runtime::Runtime>,
+    handle: tokio::runtime::Handle,
+}
 
-    match Overrides::load_from_disk() {
-        Ok(overrides) => {
-            let mut guard = state.write().expect("Found a poisoned lock.");
-            let state = &mut *guard;
-            state.overrides = Arc::new(RwLock::new(overrides));
-        },
-        Err(error) => error!("Failed to read updated overrides: {}", error),
-    };
+impl WorkerPool {
+    pub(crate) fn new(threads: usize) -> Result<Self, String> {
+        let runtime = match tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(threads.max(1))
+            .thread_name("repochecker-worker")
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(error) => return Err(format!("Failed to start worker threads: {}", error)),
+        };
 
-    match get_admins(15).await {
-        Ok(admins) => {
-            let mut guard = state.write().expect("Found a poisoned lock.");
-            let state = &mut *guard;
-            state.admins = admins;
-        },
-        Err(error) => error!("Failed to read updated package maintainers: {}", error),
+        let handle = runtime.handle().clone();
+        Ok(WorkerPool {
+            runtime: Some(runtime),
+            handle,
+        })
     }
 
-    match get_maintainers(15).await {
-        Ok(maintainers) => {
-            let mut guard = state.write().expect("Found a poisoned lock.");
-            let state = &mut *guard;
-            state.maintainers = maintainers;
-        },
-        Err(error) => error!("Failed to read updated package maintainers: {}", error),
-    };
+    /// Returns a handle for spawning checks on the worker threads from other parts of the service.
+    pub(crate) fn handle(&self) -> tokio::runtime::Handle {
+        self.handle.clone()
+    }
+
+    pub(crate) fn spawn<F>(&self, future: F) -> tokio::task::JoinHandle<F::Output>
+    where
+        F: std::future::Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.handle.spawn(future)
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // the runtime is dropped from within the main runtime, where blocking on its shutdown is not allowed
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
 }
 
-pub(crate) async fn worker(state: GlobalState, entry: MatrixEntry) {
-    let suffix = if !entry.with_testing { "" } else { "-testing" };
-    let pretty = format!("{}{}", &entry.release, suffix);
+// upper limit for checking recent activity of all main admins of broken packages
+const INACTIVITY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+
+/// Returns the start of the first refresh cycle (starting at `next_cycle` and repeated every `interval` hours) that
+/// begins after a release is due.
+pub(crate) fn first_cycle_after(next_cycle: DateTime<Utc>, due: DateTime<Utc>, interval: f64) -> DateTime<Utc> {
+    if due <= next_cycle || interval <= 0.0 {
+        return next_cycle;
+    }
+
+    let step = interval * 60.0 * 60.0;
+    let cycles = ((due - next_cycle).num_seconds() as f64 / step).ceil();
+    next_cycle + chrono::Duration::seconds((cycles * step) as i64)
+}
 
-    let json_path = get_json_path(&entry.release, entry.with_testing);
+/// Selects the releases that are checked in a refresh cycle: releases that were carried over from the previous cycle
+/// (which are listed first), and all other releases that are due. Releases are paired with whether they were carried
+/// over.
+pub(crate) fn cycle_releases(
+    matrix: Vec<MatrixEntry>,
+    carried_over: &BTreeSet<String>,
+    is_due: impl Fn(&str) -> bool,
+) -> Vec<(MatrixEntry, bool)> {
+    let (mut releases, others): (Vec<_>, Vec<_>) = matrix
+        .into_iter()
+        .map(|entry| {
+            let carried = carried_over.contains(&entry.key());
+            (entry, carried)
+        })
+        .filter(|(entry, carried)| *carried || is_due(&entry.key()))
+        .partition(|(_, carried)| *carried);
 
-    let previous = {
-        let guard = state.read().expect("Found a poisoned lock.");
-        let state = &*guard;
+    releases.extend(others);
+    releases
+}
 
-        state.values.contains_key(&pretty)
+/// Drops data for releases that were present in the old configuration but were removed from the current one.
+fn retire_removed_releases(state: &mut State, old: &Config) {
+    let (old_matrix, new_matrix) = match (old.to_matrix(), state.config.to_matrix()) {
+        (Ok(old_matrix), Ok(new_matrix)) => (old_matrix, new_matrix),
+        (Err(error), _) | (_, Err(error)) => {
+            error!("Failed to compare old and new configuration: {}", error);
+            return;
+        },
     };
 
-    if !previous {
-        // populate data with cached values from file, if available
-        let cached = read_json_from_file(&json_path);
-        if let Ok(values) = cached {
-            if !entry.archived {
-                info!("Reusing cached data for {} until fresh data is available.", &pretty);
-            } else {
-                info!("Reusing archival data for {}.", &pretty);
-            }
+    let current: HashSet<String> = new_matrix.iter().map(|entry| entry.key()).collect();
 
-            let mut guard = state.write().expect("Found a poisoned lock.");
-            let state = &mut *guard;
+    for entry in old_matrix {
+        let key = entry.key();
+        if current.contains(&key) {
+            continue;
+        }
 
-            state.values.insert(pretty.clone(), Arc::new(values));
+        info!(
+            "Release {} was removed from the configuration, dropping its data.",
+            &key
+        );
+        state.values.remove(&key);
+        state.archived.remove(&key);
+        state.frozen.remove(&key);
+        state.freeze_failed.remove(&key);
 
-            if entry.archived {
-                return;
+        let json_path = get_json_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+        let frozen_path = get_frozen_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+
+        for path in [json_path, frozen_path] {
+            if path.exists() {
+                retire_data_file(state.config.repochecker.retired_data, &key, &path);
             }
-        };
+        }
     }
+}
 
-    info!("Generating data for {}", &pretty);
+/// Retires data files of releases that are not present in the configuration, which were removed from it while the
+/// service was not running.
+pub(crate) fn retire_unconfigured_releases(config: &Config) {
+    let retired_data = config.repochecker.retired_data;
+    if retired_data == RetiredData::Keep {
+        return;
+    }
 
-    let mut arches: Vec<String> = Vec::new();
-    let mut multi_arch: HashMap<String, Vec<String>> = HashMap::new();
+    let matrix = match config.to_matrix() {
+        Ok(matrix) => matrix,
+        Err(error) => {
+            error!("Failed to read releases from configuration: {}", error);
+            return;
+        },
+    };
 
-    for arch in &entry.arches {
-        arches.push(arch.name.clone());
-        multi_arch.insert(arch.name.clone(), arch.multi_arch.clone());
+    let configured: HashSet<PathBuf> = matrix
+        .iter()
+        .flat_map(|entry| {
+            [
+                get_json_path(entry.product.as_deref(), &entry.release, entry.with_testing),
+                get_frozen_path(entry.product.as_deref(), &entry.release, entry.with_testing),
+            ]
+        })
+        .collect();
+
+    for path in find_data_files(&get_data_path()) {
+        if configured.contains(&path) {
+            continue;
+        }
+
+        let key = path
+            .strip_prefix(get_data_path())
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .trim_end_matches(".gz")
+            .trim_end_matches(".json")
+            .to_string();
+
+        info!(
+            "Release {} is not present in the configuration, dropping its data.",
+            &key
+        );
+        retire_data_file(retired_data, &key, &path);
+    }
+}
+
+/// Returns the data files of releases (and of frozen releases) in the data directory, including data files of
+/// additional products.
+fn find_data_files(data_path: &std::path::Path) -> Vec<PathBuf> {
+    fn is_data_file(path: &std::path::Path) -> bool {
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        path.is_file() && (name.ends_with(".json") || name.ends_with(".json.gz"))
     }
 
-    let overrides = {
-        let guard = state.read().expect("Found a poisoned lock.");
-        let state = &*guard;
-        state.overrides.clone()
+    let read_dir = |path: &std::path::Path| -> Vec<PathBuf> {
+        match std::fs::read_dir(path) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .collect(),
+            Err(error) => {
+                if error.kind() != std::io::ErrorKind::NotFound {
+                    error!("Failed to read data directory {}: {}", path.to_string_lossy(), error);
+                }
+                Vec::new()
+            },
+        }
     };
 
-    let admins = {
-        let guard = state.read().expect("Found a poisoned lock.");
-        let state = &*guard;
+    let mut files = Vec::new();
+    for path in read_dir(data_path) {
+        if path.is_dir() {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            if DATA_DIRECTORIES.contains(&name) {
+                continue;
+            }
 
-        state.admins.clone()
-    };
+            files.extend(read_dir(&path).into_iter().filter(|path| is_data_file(path)));
+        } else if is_data_file(&path) {
+            files.push(path);
+        }
+    }
 
-    let maintainers = {
-        let guard = state.read().expect("Found a poisoned lock.");
-        let state = &*guard;
-        state.maintainers.clone()
-    };
+    files.sort();
+    files
+}
 
-    let broken = match get_repo_closure(
-        &entry.release,
-        &arches,
-        &multi_arch,
-        &entry.repos,
-        &entry.check,
-        overrides,
-        &admins,
-        &maintainers,
-    )
-    .await
-    {
-        Ok(broken) => broken,
+fn retire_data_file(retired_data: RetiredData, key: &str, path: &std::path::Path) {
+    match retired_data {
+        RetiredData::Keep => {},
+        RetiredData::Archive => match archive_json_file(path) {
+            Ok(target) => info!("Archived data for {} to {}.", key, target.to_string_lossy()),
+            Err(error) => error!("Failed to archive data for {}: {}", key, error),
+        },
+        RetiredData::Delete => match std::fs::remove_file(path) {
+            Ok(()) => info!("Deleted data for {}.", key),
+            Err(error) => error!("Failed to delete data for {}: {}", key, error),
+        },
+    }
+}
+
+/// Cancels the current refresh cycle as soon as the configuration file on disk no longer matches the given one.
+pub(crate) async fn config_monitor(config: Config, cancel: CancellationToken) {
+    let current = match serde_json::to_string(&config) {
+        Ok(current) => current,
         Err(error) => {
-            error!("Failed to generate repoclosure: {}", error);
+            error!("Failed to serialize configuration: {}", error);
             return;
         },
     };
 
-    {
-        let mut guard = state.write().expect("Found a poisoned lock.");
-        let state = &mut *guard;
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => {},
+            _ = cancel.cancelled() => return,
+        }
 
-        let old_broken = state.values.remove(&pretty);
-        let mut new_broken = broken;
+        let changed = match get_config().map(|config| serde_json::to_string(&config)) {
+            Ok(Ok(updated)) => updated != current,
+            // invalid configuration files are reported by the watcher
+            _ => false,
+        };
 
-        // check if packages were already broken and set "since" datetime accordingly
-        if let Some(old_broken) = old_broken {
-            fn matches(old: &BrokenItem, new: &BrokenItem) -> bool {
-                old.package == new.package && old.repo == new.repo && old.repo_arch == new.repo_arch
-            }
+        if changed {
+            info!("Configuration has changed, cancelling workers.");
+            cancel.cancel();
+            return;
+        }
+    }
+}
 
-            for new in new_broken.iter_mut() {
-                for old in old_broken.iter() {
-                    if matches(old, new) {
-                        // use old "since" time in case of a match
-                        new.since = old.since;
-                        // there can only be one match per package+repo+repo_arch combination
-                        break;
-                    }
-                }
+/// Reloads the configuration (and overrides) from disk whenever the configuration file changes.
+pub(crate) async fn config_reloader(state: GlobalState) {
+    loop {
+        let config = read_lock(&state).config.clone();
 
-                // if no old "since" time was found or the entry is new, set "since" to "now"
-                if new.since.is_none() {
-                    new.since = Some(Utc::now());
-                }
-            }
+        let changed = CancellationToken::new();
+        config_monitor(config, changed.clone()).await;
+        if !changed.is_cancelled() {
+            return;
         }
 
-        if write_json_to_file(&json_path, &new_broken).is_err() {
-            error!("Failed to write results to disk in JSON format.");
-        };
+        watcher(state.clone()).await;
+    }
+}
 
-        state.values.insert(pretty.clone(), Arc::new(new_broken));
+/// Waits until checks are resumed after they were paused via the administrative API.
+pub(crate) async fn wait_until_resumed(state: &GlobalState) {
+    info!("Checks are paused, waiting until they are resumed.");
+
+    while read_lock(state).paused {
+        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
     }
 
-    info!("Generated data for {}.", &pretty);
+    info!("Checks were resumed.");
 }
 
-pub(crate) async fn server(state: GlobalState) {
-    let router = Router::new();
+/// Retries reading package maintainers after the service was started without them, until this succeeds.
+pub(crate) async fn recover_accounts(state: &GlobalState) {
+    let mut delay = Duration::from_secs(60);
 
-    let index_state = state.clone();
-    let router = router.route(
-        "/",
-        get(move || async move {
-            let (mut releases, mut stats, date_refreshed): (Vec<String>, Vec<(String, usize)>, String) = {
-                let guard = index_state.read().expect("Found a poisoned lock.");
-                let state = &*guard;
+    loop {
+        info!(
+            "Running in degraded mode, retrying to read package maintainers in {} seconds.",
+            delay.as_secs()
+        );
+        tokio::time::sleep(delay).await;
 
-                let releases = state.values.keys().cloned().collect();
-                let stats = state
-                    .values
-                    .iter()
-                    .map(|(release, broken_items)| (release.to_owned(), broken_items.len()))
-                    .collect();
+        let pagure = read_lock(state).config.pagure.clone();
+        match get_accounts(&pagure, 15).await {
+            Ok((admins, maintainers)) => {
+                let mut guard = write_lock(state);
+                let state = &mut *guard;
 
-                let date_refreshed = match state.date_refreshed {
-                    Some(dt) => dt.to_string(),
-                    None => String::from("(initial refresh still running after service was restarted)"),
-                };
+                // results that are missing could not be fetched (or were already fetched by a previous attempt)
+                if let Some(admins) = admins {
+                    state.admins = admins;
+                }
+                if let Some(maintainers) = maintainers {
+                    state.maintainers = maintainers;
+                }
 
-                (releases, stats, date_refreshed)
-            };
+                if !state.admins.is_empty() && !state.maintainers.is_empty() {
+                    state.degraded = None;
+                    info!("Read package maintainers, leaving degraded mode.");
+                    return;
+                }
 
-            releases.sort();
-            releases.reverse();
-
-            stats.sort();
-            stats.reverse();
-
-            let index = Index::new(releases, stats, date_refreshed);
-            match index.render() {
-                Ok(body) => {
-                    let mut headers = HeaderMap::new();
-                    headers.insert(
-                        CONTENT_TYPE,
-                        "text/html".parse().expect("Failed to parse hardcoded header value."),
-                    );
-                    (StatusCode::OK, headers, body)
-                },
-                Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new(), error.to_string()),
+                delay = (delay * 2).min(Duration::from_secs(30 * 60));
+            },
+            Err(error) => {
+                warn!("Failed to read package maintainers: {}", error);
+                write_lock(state).degraded = Some(error);
+                delay = (delay * 2).min(Duration::from_secs(30 * 60));
+            },
+        }
+    }
+}
+
+pub(crate) async fn watcher(state: GlobalState) {
+    match get_config() {
+        Ok(config) => {
+            if let Err(error) = crate::http::configure(&config) {
+                error!("Failed to update HTTP client: {}", error);
             }
-        }),
-    );
 
-    let release_state = state.clone();
-    let router = router.route(
-        "/data/:release",
-        get(move |release: Path<String>| async move {
-            let values = {
-                let guard = release_state.read().expect("Found a poisoned lock.");
-                let state = &*guard;
-                state.values.get(&release.0).cloned()
-            };
+            let mut guard = write_lock(&state);
+            let state = &mut *guard;
+            let old = std::mem::replace(&mut state.config, config);
+            retire_removed_releases(state, &old);
+        },
+        Err(error) => error!("Failed to read updated configuration: {}", error),
+    };
 
-            match values {
-                Some(values) => {
-                    let mut headers = HeaderMap::new();
-                    headers.insert(
-                        CONTENT_TYPE,
-                        "application/json"
-                            .parse()
-                            .expect("Failed to parse hardcoded header value."),
-                    );
-                    let body = serde_json::to_string_pretty(&*values).expect("Failed to serialize into JSON.");
-                    (StatusCode::OK, headers, body)
-                },
-                None => {
-                    let body = String::from("This release does not exist.");
-                    (StatusCode::NOT_FOUND, HeaderMap::new(), body)
-                },
-            }
-        }),
-    );
+    match Overrides::load_from_disk() {
+        Ok(overrides) => {
+            let mut guard = write_lock(&state);
+            let state = &mut *guard;
+            state.overrides = Arc::new(RwLock::new(overrides));
+        },
+        Err(error) => error!("Failed to read updated overrides: {}", error),
+    };
 
-    let config_state = state.clone();
-    let router = router.route(
-        "/config",
-        get(move || async move {
-            let body = {
-                let state = config_state.read().expect("Found a poisoned lock.");
-                basic_toml::to_string(&state.config).expect("Failed to serialize into TOML.")
-            };
+    let products = read_lock(&state).config.products.clone();
+    match load_product_overrides(&products) {
+        Ok(product_overrides) => {
+            let mut guard = write_lock(&state);
+            let state = &mut *guard;
+            state.product_overrides = product_overrides;
+        },
+        Err(error) => error!("Failed to read updated overrides: {}", error),
+    };
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                CONTENT_TYPE,
-                "text/plain".parse().expect("Failed to parse hardcoded header value."),
-            );
-            (StatusCode::OK, headers, body)
-        }),
-    );
+    // package maintainers and user activity are refreshed separately if an interval is configured for them
+    if read_lock(&state).config.repochecker.account_interval.is_none() {
+        refresh_accounts(&state).await;
+    }
+}
 
-    let overrides_state = state.clone();
-    let router = router.route(
-        "/overrides",
-        get(move || async move {
-            let body = {
-                let state = overrides_state.read().expect("Found a poisoned lock.");
-                let overrides = state.overrides.read().expect("Found a poisoned lock.");
-                serde_json::to_string_pretty(&overrides.data).expect("Failed to serialize into JSON.")
-            };
+/// Fetches the list of critical path packages for a refresh cycle.
+///
+/// If fetching the list fails, the error is logged and the previous list is kept.
+pub(crate) async fn refresh_critpath(state: &GlobalState) {
+    let critpath_config = read_lock(state).config.critpath.clone();
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                CONTENT_TYPE,
-                "application/json"
-                    .parse()
-                    .expect("Failed to parse hardcoded header value."),
-            );
+    let critpath_config = match critpath_config {
+        Some(critpath_config) => critpath_config,
+        None => {
+            write_lock(state).critpath = None;
+            return;
+        },
+    };
 
-            (StatusCode::OK, headers, body)
-        }),
-    );
+    match load_critpath(&critpath_config, 15).await {
+        Ok(critpath) => write_lock(state).critpath = Some(Arc::new(critpath)),
+        Err(error) => error!("Failed to read critical path packages: {}", error),
+    }
+}
 
-    let stats_state = state.clone();
-    let router = router.route(
-        "/stats",
-        get(move || async move {
-            let values = {
-                let state = stats_state.read().expect("Found a poisoned lock.");
-                state.overrides.clone()
-            };
+/// Refreshes main admins, maintainers, and inactive users concurrently.
+///
+/// Results of lookups that fail (or time out) are logged and discarded, and the previous values are kept.
+pub(crate) async fn refresh_accounts(state: &GlobalState) {
+    // package maintainers and user activity are not queried when using fake data
+    if read_lock(state).fake_data {
+        return;
+    }
 
-            let body = {
-                let overrides = values.read().expect("Found a poisoned lock.");
-                let stats = &overrides.stats;
+    let (inactivity, pagure) = {
+        let guard = read_lock(state);
+        (guard.config.inactivity.clone(), guard.config.pagure.clone())
+    };
 
-                #[derive(Serialize)]
-                struct StatsEntry<'a> {
-                    path: &'a str,
-                    count: u32,
-                }
+    let check_inactive = async {
+        let inactivity = inactivity?;
 
-                let mut output: Vec<StatsEntry> = stats
-                    .iter()
-                    .map(|(path, count)| StatsEntry { path, count: *count })
-                    .collect();
+        // only check main admins of packages that are currently broken
+        let admins: HashSet<String> = {
+            let guard = read_lock(state);
+            let state = &*guard;
 
-                output.sort_by_key(|b| b.count);
-                output.reverse();
+            state
+                .values
+                .values()
+                .flat_map(|values| values.iter().map(|item| item.admin.clone()))
+                .collect()
+        };
 
-                serde_json::to_string_pretty(&output).expect("Failed to serialize into JSON.")
-            };
+        match tokio::time::timeout(INACTIVITY_TIMEOUT, get_inactive(&inactivity, admins, 15)).await {
+            Ok(inactive) => Some(inactive),
+            Err(_) => {
+                error!("Timed out while checking for inactive users.");
+                None
+            },
+        }
+    };
+
+    let (accounts, inactive) = tokio::join!(get_accounts(&pagure, 15), check_inactive);
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                CONTENT_TYPE,
-                "application/json"
-                    .parse()
-                    .expect("Failed to parse hardcoded header value."),
+    let mut guard = write_lock(state);
+    let state = &mut *guard;
+
+    match accounts {
+        Ok((admins, maintainers)) => {
+            let churn = account_churn(
+                &state.admins,
+                admins.as_ref().unwrap_or(&state.admins),
+                &state.maintainers,
+                maintainers.as_ref().unwrap_or(&state.maintainers),
             );
 
-            (StatusCode::OK, headers, body)
-        }),
-    );
+            if !churn.is_empty() {
+                info!(
+                    "Package maintainers changed: {} packages changed their main admin ({} were orphaned), {} changed \
+                     their maintainers, {} were added, and {} were removed.",
+                    churn.admin_changed, churn.orphaned, churn.maintainers_changed, churn.added, churn.removed
+                );
+            }
+            state.account_churn = Some(churn);
 
-    let status_state = state.clone();
-    let router = router.route(
+            if let Some(admins) = admins {
+                state.admins = admins;
+            }
+            if let Some(maintainers) = maintainers {
+                state.maintainers = maintainers;
+            }
+        },
+        Err(error) => error!("Failed to read updated package maintainers: {}", error),
+    }
+
+    if let Some(inactive) = inactive {
+        // data that is already served is flagged again as well, instead of only when the release is checked again
+        for values in state.values.values_mut() {
+            if values
+                .iter()
+                .any(|item| item.admin_inactive != inactive.contains(&item.admin))
+            {
+                let mut items = values.to_vec();
+                flag_inactive(&mut items, &inactive);
+                *values = Arc::new(items);
+            }
+        }
+
+        state.inactive = inactive;
+    }
+}
+
+/// Refreshes package maintainers and user activity independently of repository checks, if an interval is configured.
+pub(crate) async fn account_refresher(state: GlobalState) {
+    loop {
+        // the interval is read again every time in case the configuration was changed
+        let interval = read_lock(&state).config.repochecker.account_interval;
+
+        match interval {
+            Some(interval) => {
+                tokio::time::sleep(std::time::Duration::from_secs_f64(interval * 60.0 * 60.0)).await;
+                info!("Refreshing package maintainers and user activity.");
+                refresh_accounts(&state).await;
+            },
+            None => tokio::time::sleep(std::time::Duration::from_secs(60)).await,
+        }
+    }
+}
+
+/// Annotates broken items with the number of packages that depend on them (to distinguish leaf packages from ones
+/// whose breakage cascades to other packages), and with hints about what happened to packages that used to provide
+/// missing capabilities in the previous run. Returns the updated provides of all architectures (if they could be
+/// updated for all of them).
+async fn annotate_dependencies(
+    entry: &MatrixEntry,
+    pretty: &str,
+    arches: &[String],
+    broken: &mut [BrokenItem],
+    cancel: &CancellationToken,
+) -> Option<Vec<(String, ProvideIndex)>> {
+    let mut indices = Some(Vec::new());
+
+    for arch in arches {
+        if cancel.is_cancelled() {
+            return None;
+        }
+
+        let dump = match get_dependency_dump(
+            &entry.cache_name(),
+            &entry.release,
+            arch,
+            &entry.repos,
+            &entry.package_globs,
+            cancel,
+        )
+        .await
+        {
+            Ok(dump) => dump,
+            Err(error) => {
+                error!("Failed to query dependencies for {} / {}: {}", pretty, arch, error);
+                indices = None;
+                continue;
+            },
+        };
+
+        let records = match parse_dependency_dump(&dump) {
+            Ok(records) => records,
+            Err(error) => {
+                error!("Failed to parse dependencies for {} / {}: {}", pretty, arch, error);
+                indices = None;
+                continue;
+            },
+        };
+
+        let packages: HashSet<String> = broken
+            .iter()
+            .filter(|item| &item.repo_arch == arch && item.arch != "src")
+            .map(|item| item.package.clone())
+            .collect();
+
+        let counts = count_dependents(&records, &packages);
+
+        let provides_path = get_provides_path(entry.product.as_deref(), &entry.release, entry.with_testing, arch);
+        let previous = read_provides_from_file(&provides_path).ok();
+
+        let names: HashSet<&str> = records.iter().map(|record| record.name).collect();
+        let sources: HashSet<&str> = records.iter().map(|record| record.source).collect();
+
+        for item in broken.iter_mut().filter(|item| &item.repo_arch == arch) {
+            if item.arch != "src" {
+                item.dependents = counts.get(&item.package).copied();
+                item.leaf = item.dependents.map(|count| count == 0);
+            }
+
+            if let Some(previous) = &previous {
+                item.hints = get_hints(item, previous, &names, &sources);
+            }
+        }
+
+        let index = index_provides(&records, previous.as_ref());
+        if let Err(error) = write_provides_to_file(&provides_path, &index) {
+            error!("Failed to store provides for {} / {}: {}", pretty, arch, error);
+        }
+
+        if let Some(indices) = indices.as_mut() {
+            indices.push((arch.clone(), index));
+        }
+    }
+
+    indices
+}
+
+/// Stores the current data for an archived release in compressed form and marks it as read-only.
+fn freeze(state: &GlobalState, pretty: &str, json_path: &std::path::Path, frozen_path: &std::path::Path) {
+    // fake data must never replace the data of archived releases permanently
+    if read_lock(state).fake_data {
+        return;
+    }
+
+    let values = match read_lock(state).values.get(pretty) {
+        Some(values) => values.clone(),
+        None => {
+            error!("No data available for freezing archived release {}.", pretty);
+            write_lock(state).freeze_failed.insert(pretty.to_string());
+            return;
+        },
+    };
+
+    let frozen = FrozenData {
+        frozen_at: Utc::now(),
+        items: values.to_vec(),
+    };
+
+    if let Err(error) = write_frozen_to_file(frozen_path, &frozen) {
+        error!("Failed to freeze data for {}: {}", pretty, error);
+        write_lock(state).freeze_failed.insert(pretty.to_string());
+        return;
+    }
+
+    match std::fs::remove_file(json_path) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+            error!("Failed to remove uncompressed data for {}: {}", pretty, error);
+        },
+        _ => {},
+    }
+
+    // the signature of the uncompressed data is kept, since it still matches the data that is served
+
+    {
+        let mut guard = write_lock(state);
+        let state = &mut *guard;
+
+        state.frozen.insert(pretty.to_string(), frozen.frozen_at);
+        state.freeze_failed.remove(pretty);
+        state.archived.insert(
+            pretty.to_string(),
+            ArchivedRelease {
+                path: frozen_path.to_path_buf(),
+                items: frozen.items.len(),
+                critpath: frozen.items.iter().filter(|item| item.critpath).count(),
+            },
+        );
+        state.touch_archived(pretty);
+    }
+
+    info!("Froze data for archived release {}.", pretty);
+}
+
+/// Returns the data of a release, and loads the frozen data of an archived release into memory first if it was not
+/// loaded yet.
+///
+/// Handlers use the returned data instead of looking it up in the state again, since the data of archived releases
+/// can be dropped from memory at any time. The state is only locked for writing if data has to be loaded.
+pub(crate) async fn load_archived(state: &GlobalState, key: &str) -> Option<Arc<Vec<BrokenItem>>> {
+    let path = {
+        let guard = read_lock(state);
+        let state = &*guard;
+
+        let archived = state.archived.get(key);
+        if let Some(values) = state.values.get(key) {
+            if archived.is_some() {
+                state.touch_archived(key);
+            }
+            return Some(values.clone());
+        }
+
+        archived?.path.clone()
+    };
+
+    let frozen = match tokio::task::spawn_blocking(move || read_frozen_from_file(&path)).await {
+        Ok(Ok(frozen)) => frozen,
+        Ok(Err(error)) => {
+            error!("Failed to load data for archived release {}: {}", key, error);
+            return None;
+        },
+        Err(error) => {
+            error!("Failed to load data for archived release {}: {}", key, error);
+            return None;
+        },
+    };
+
+    debug!("Loaded data for archived release {}.", key);
+
+    let mut guard = write_lock(state);
+    let state = &mut *guard;
+
+    // the data may have been loaded by another request in the meantime
+    let values = state
+        .values
+        .entry(key.to_string())
+        .or_insert_with(|| Arc::new(frozen.items))
+        .clone();
+    state.touch_archived(key);
+
+    let capacity = state.config.repochecker.archived_cache_size;
+    let idle = Duration::from_secs_f64(state.config.repochecker.archived_idle_time * 60.0);
+    for evicted in state.evict_archived(capacity, idle, Instant::now()) {
+        debug!("Dropped data for archived release {} from memory.", evicted);
+    }
+
+    Some(values)
+}
+
+/// Periodically drops the data of archived releases that have not been requested recently from memory.
+pub(crate) async fn archived_evictor(state: GlobalState) {
+    loop {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+
+        let mut guard = write_lock(&state);
+        let state = &mut *guard;
+
+        let capacity = state.config.repochecker.archived_cache_size;
+        let idle = Duration::from_secs_f64(state.config.repochecker.archived_idle_time * 60.0);
+        for evicted in state.evict_archived(capacity, idle, Instant::now()) {
+            debug!("Dropped data for archived release {} from memory.", evicted);
+        }
+    }
+}
+
+/// Replaces the alerts of a release, and logs alerts whose rules did not raise an alert for the previous results.
+///
+/// Alerts are not delivered anywhere else yet, but they are listed in `/status`.
+fn raise_alerts(state: &mut State, release: &str, alerts: Vec<Alert>) {
+    let previous = state.alerts.remove(release).unwrap_or_default();
+
+    for alert in &alerts {
+        if !previous.iter().any(|other| other.rule == alert.rule) {
+            warn!("Alert {} was raised for {}: {}", &alert.rule, release, &alert.message);
+        }
+    }
+
+    for alert in &previous {
+        if !alerts.iter().any(|other| other.rule == alert.rule) {
+            info!("Alert {} for {} was resolved.", &alert.rule, release);
+        }
+    }
+
+    if !alerts.is_empty() {
+        state.alerts.insert(release.to_string(), alerts);
+    }
+}
+
+/// Checks whether any packages started or stopped being broken, or whether their unresolved dependencies changed.
+fn data_changed(old: &[BrokenItem], new: &[BrokenItem]) -> bool {
+    fn key(item: &BrokenItem) -> (String, String, &[String]) {
+        (item.since_key(), item.evr(), item.broken.as_slice())
+    }
+
+    let old: BTreeSet<_> = old.iter().map(key).collect();
+    let new: BTreeSet<_> = new.iter().map(key).collect();
+
+    old != new
+}
+
+/// Splits new results into the packages that are published and those that are quarantined because they have not been
+/// broken in `confirm_runs` consecutive runs yet (packages that were already published stay published while they are
+/// broken). Returns the published packages and the updated number of consecutive runs in which each package was broken.
+fn confirm_items(
+    items: &[BrokenItem],
+    published: &[BrokenItem],
+    streaks: &HashMap<String, usize>,
+    confirm_runs: usize,
+) -> (Vec<BrokenItem>, HashMap<String, usize>) {
+    let published: HashSet<String> = published.iter().map(BrokenItem::since_key).collect();
+
+    let mut new_streaks = HashMap::new();
+    let mut confirmed = Vec::new();
+
+    for item in items {
+        let key = item.since_key();
+
+        // there can be multiple items per package (for example, for different architectures)
+        let streak = match new_streaks.get(&key) {
+            Some(streak) => *streak,
+            None => streaks.get(&key).copied().unwrap_or_default() + 1,
+        };
+
+        if streak >= confirm_runs || published.contains(&key) {
+            confirmed.push(item.clone());
+        }
+
+        new_streaks.insert(key, streak);
+    }
+
+    (confirmed, new_streaks)
+}
+
+/// Populates data for a release with cached values from disk (if they were not loaded yet), and returns whether the
+/// release is frozen.
+pub(crate) fn load_cached(state: &GlobalState, entry: &MatrixEntry) -> bool {
+    let pretty = entry.key();
+
+    if entry.archived {
+        if read_lock(state).frozen.contains_key(&pretty) {
+            return true;
+        }
+
+        // frozen data is only loaded into memory when it is requested
+        let frozen_path = get_frozen_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+        if let Ok(summary) = read_frozen_summary(&frozen_path) {
+            info!("Reusing frozen data for {} (frozen at {}).", &pretty, summary.frozen_at);
+
+            let mut guard = write_lock(state);
+            let state = &mut *guard;
+
+            state.archived.insert(
+                pretty.clone(),
+                ArchivedRelease {
+                    path: frozen_path,
+                    items: summary.items.len(),
+                    critpath: summary.items.iter().filter(|item| item.critpath).count(),
+                },
+            );
+            state.frozen.insert(pretty, summary.frozen_at);
+            return true;
+        }
+    } else {
+        // releases that are no longer archived are checked again (and their frozen data is served until then)
+        let mut guard = write_lock(state);
+        guard.freeze_failed.remove(&pretty);
+        if guard.frozen.remove(&pretty).is_some() {
+            info!("Release {} is no longer archived, unfreezing its data.", &pretty);
+            guard.archived.remove(&pretty);
+            guard
+                .archived_access
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&pretty);
+        }
+    }
+
+    if read_lock(state).values.contains_key(&pretty) {
+        return false;
+    }
+
+    // populate data with cached values from file, if available
+    let json_path = get_json_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+    if let Ok(values) = read_json_from_file(&json_path) {
+        info!("Reusing cached data for {} until fresh data is available.", &pretty);
+        write_lock(state).values.insert(pretty, Arc::new(values));
+        return false;
+    };
+
+    // the uncompressed data of releases that were frozen before is removed
+    if !entry.archived {
+        let frozen_path = get_frozen_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+        if let Ok(frozen) = read_frozen_from_file(&frozen_path) {
+            info!("Reusing frozen data for {} until fresh data is available.", &pretty);
+            write_lock(state).values.insert(pretty, Arc::new(frozen.items));
+        }
+    }
+
+    false
+}
+
+pub(crate) async fn worker(state: GlobalState, entry: MatrixEntry, cycle: Uuid, cancel: CancellationToken) {
+    let pretty = entry.key();
+
+    // releases are not checked twice at the same time
+    if !write_lock(&state).checking.insert(pretty.clone()) {
+        info!("Skipping {}, which is currently being rerun.", &pretty);
+        return;
+    }
+
+    // archived releases are checked only once in refresh cycles, even if their data could not be frozen
+    if entry.archived && read_lock(&state).freeze_failed.contains(&pretty) {
+        info!(
+            "Skipping archived release {}, which could not be frozen (it can be rerun).",
+            &pretty
+        );
+        write_lock(&state).checking.remove(&pretty);
+        return;
+    }
+
+    run_with_options(state.clone(), entry, cycle, cancel, &RerunOptions::default()).await;
+
+    write_lock(&state).checking.remove(&pretty);
+}
+
+/// Checks a release outside of refresh cycles (as requested via the administrative API), with parameters that are
+/// overridden for this run only. The release must already be claimed in `checking` and `reruns`.
+pub(crate) async fn rerun(state: GlobalState, entry: MatrixEntry, options: RerunOptions, cancel: CancellationToken) {
+    let pretty = entry.key();
+
+    if options.clean_cache {
+        for arch in &entry.arches {
+            if let Err(error) = remove_cache(&entry.cache_name(), &arch.name) {
+                warn!("Failed to remove cache for {} / {}: {}", &pretty, &arch.name, error);
+            }
+        }
+    }
+
+    run_with_options(state.clone(), entry, Uuid::new_v4(), cancel, &options).await;
+
+    let mut guard = write_lock(&state);
+    guard.checking.remove(&pretty);
+    guard.reruns.remove(&pretty);
+}
+
+async fn run_with_options(
+    state: GlobalState,
+    mut entry: MatrixEntry,
+    cycle: Uuid,
+    cancel: CancellationToken,
+    overrides: &RerunOptions,
+) {
+    // the configuration was validated when it was loaded
+    let mut options = read_lock(&state).config.mirrors.dnf_options().unwrap_or_default();
+    options.extend(entry.compose_options());
+    options.extend(overrides.setopt.iter().map(|option| format!("--setopt={}", option)));
+
+    let partial = match &overrides.arch {
+        Some(arch) => {
+            entry.arches.retain(|other| &other.name == arch);
+            true
+        },
+        None => false,
+    };
+
+    let pretty = entry.key();
+    let hook = progress_hook(state.clone(), &entry);
+
+    with_dnf_options(
+        options,
+        with_verbose_output(
+            overrides.verbose,
+            with_progress(
+                hook,
+                with_cpu_accounting(with_error_accounting(run_worker(
+                    state.clone(),
+                    entry,
+                    cycle,
+                    cancel,
+                    partial,
+                ))),
+            ),
+        ),
+    )
+    .await;
+
+    write_lock(&state).progress.remove(&pretty);
+}
+
+/// Returns a callback that records the progress of checking a release in the global state.
+fn progress_hook(state: GlobalState, entry: &MatrixEntry) -> ProgressHook {
+    let pretty = entry.key();
+    let started_at = Utc::now();
+
+    // every architecture is queried (if ExcludeArch detection is enabled), refreshed, and checked, followed by
+    // post-processing of the results
+    let per_arch = if entry.detect_exclude_arch { 3 } else { 2 };
+    let steps = entry.arches.len() * per_arch + 1;
+
+    Arc::new(move |stage: ProgressStage, arch: Option<&str>| {
+        let mut guard = write_lock(&state);
+        let step = match stage {
+            ProgressStage::PostProcessing => steps,
+            _ => guard
+                .progress
+                .get(&pretty)
+                .map(|progress| progress.step + 1)
+                .unwrap_or(1),
+        };
+
+        guard.progress.insert(
+            pretty.clone(),
+            WorkerProgress {
+                stage,
+                arch: arch.map(String::from),
+                // stages are repeated if checking the release is retried
+                step: step.min(steps),
+                steps,
+                started_at,
+                stage_started_at: Utc::now(),
+            },
+        );
+    })
+}
+
+/// Annotates new results for a release: packages are flagged if their main admin is inactive, links are filled in, and
+/// "since" timestamps (and changes of the main admin) are carried over from the previous data, or from persisted
+/// timestamps if the previous data is not available. Returns the timestamps of all packages that are currently broken.
+///
+/// This does not depend on the state of the running service, so data that is imported on the command line is annotated
+/// the same way as data that is checked or imported by the service.
+pub(crate) fn annotate_results(
+    items: &mut [BrokenItem],
+    old: Option<&[BrokenItem]>,
+    since_index: &SinceIndex,
+    urls: &UrlConfig,
+    inactive: &HashSet<String>,
+    run: Uuid,
+) -> SinceIndex {
+    flag_inactive(items, inactive);
+    for item in items.iter_mut() {
+        item.generated_by_run = Some(run);
+        item.generated_by_version = Some(VERSION.to_string());
+        urls.apply(item);
+    }
+
+    // check if packages were already broken and set "since" datetime accordingly
+    if let Some(old) = old {
+        fn matches(old: &BrokenItem, new: &BrokenItem) -> bool {
+            old.package == new.package && old.repo == new.repo && old.repo_arch == new.repo_arch
+        }
+
+        for new in items.iter_mut() {
+            for old in old.iter() {
+                if matches(old, new) {
+                    // use old "since" time in case of a match
+                    new.since = old.since;
+
+                    // keep track of packages that were orphaned or taken over while they were broken
+                    if !old.admin.is_empty() && old.admin != new.admin {
+                        info!(
+                            "Main admin of {} changed from {} to {}.",
+                            &new.package, &old.admin, &new.admin
+                        );
+                        new.previous_admin = Some(old.admin.clone());
+                        new.admin_changed = Some(Utc::now());
+                    } else {
+                        new.previous_admin = old.previous_admin.clone();
+                        new.admin_changed = old.admin_changed;
+                    }
+                    // there can only be one match per package+repo+repo_arch combination
+                    break;
+                }
+            }
+        }
+    }
+
+    // fall back to persisted timestamps if the previous data was not available
+    for new in items.iter_mut() {
+        if new.since.is_none() {
+            new.since = since_index.get(&new.since_key()).copied();
+        }
+
+        // if no old "since" time was found or the entry is new, set "since" to "now"
+        if new.since.is_none() {
+            new.since = Some(Utc::now());
+        }
+    }
+
+    // only packages that are currently broken are kept (including quarantined packages)
+    items
+        .iter()
+        .filter_map(|item| item.since.map(|since| (item.since_key(), since)))
+        .collect()
+}
+
+/// Writes new results for a release to disk: the data file (with its signature), the persisted timestamps, and the
+/// previous generations of the data. Returns the path of the signature, if signing is configured, or an error if the
+/// data file could not be written (failing to keep timestamps or previous generations is only logged).
+pub(crate) async fn persist_results(
+    entry: &MatrixEntry,
+    run: Uuid,
+    items: &[BrokenItem],
+    since_index: &SinceIndex,
+    signing: Option<&SigningConfig>,
+    history_size: usize,
+) -> Result<Option<PathBuf>, String> {
+    let pretty = entry.key();
+    let json_path = get_json_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+    let since_path = get_since_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+
+    let signature_path = sign_release(signing, &json_path, items, false).await?;
+
+    if let Err(error) = write_since_to_file(&since_path, since_index) {
+        error!("Failed to persist timestamps for {}: {}", &pretty, error);
+    }
+
+    if history_size > 0 {
+        let history_path = get_history_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+        if let Err(error) = write_generation(&history_path, run, Utc::now(), items, history_size) {
+            error!("Failed to keep previous generation of data for {}: {}", &pretty, error);
+        }
+    }
+
+    Ok(signature_path)
+}
+
+/// Uploads the data file of a release (and its signature) to the configured bucket.
+pub(crate) async fn publish_results(publish: &PublishConfig, entry: &MatrixEntry, signature_path: Option<PathBuf>) {
+    let json_path = get_json_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+
+    let mut files = vec![(json_path, "application/json")];
+    if let Some(signature_path) = signature_path {
+        files.push((signature_path, "application/pgp-signature"));
+    }
+
+    for (path, content_type) in files {
+        let result = match data_key(&path) {
+            Ok(key) => publish_file(publish, &path, &key, content_type).await,
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = result {
+            error!("Failed to publish data for {}: {}", entry.key(), error);
+        }
+    }
+}
+
+/// Stores new results for a release: items are annotated (see [`annotate_results`]), packages are quarantined until
+/// they are confirmed, alerts are raised, and the data is persisted (with its signature and previous generations),
+/// published, and served. Returns whether the data changed.
+///
+/// This is used both for results of checking a release, and for data of external releases that is imported.
+async fn store_results(state: &GlobalState, entry: &MatrixEntry, run: Uuid, broken: Vec<BrokenItem>) -> bool {
+    let pretty = entry.key();
+
+    // persisted timestamps are read before taking the lock, so requests do not wait for the disk
+    let since_path = get_since_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+    let since_index = match read_since_from_file(&since_path) {
+        Ok(since_index) => since_index,
+        Err(error) => {
+            info!("No persisted timestamps are available for {}: {}", &pretty, error);
+            SinceIndex::new()
+        },
+    };
+
+    let (new_broken, since_index, changed) = {
+        let mut guard = write_lock(state);
+        let state = &mut *guard;
+
+        let old_broken = state.values.get(&pretty).cloned();
+        let mut new_broken = broken;
+
+        let since_index = annotate_results(
+            &mut new_broken,
+            old_broken.as_deref().map(Vec::as_slice),
+            &since_index,
+            &state.config.urls,
+            &state.inactive,
+            run,
+        );
+
+        // hold back packages that have not been broken in enough consecutive runs yet
+        if let Some(confirm_runs) = state.config.repochecker.confirm_runs {
+            let (published, streaks) = confirm_items(
+                &new_broken,
+                old_broken.as_deref().map(Vec::as_slice).unwrap_or_default(),
+                state.streaks.get(&pretty).unwrap_or(&HashMap::new()),
+                confirm_runs,
+            );
+
+            let quarantined = new_broken.len() - published.len();
+            if quarantined > 0 {
+                info!(
+                    "Quarantined {} packages for {} until they are broken in {} consecutive runs.",
+                    quarantined, &pretty, confirm_runs
+                );
+            }
+
+            state.streaks.insert(pretty.clone(), streaks);
+            state.raw.insert(pretty.clone(), Arc::new(new_broken));
+            new_broken = published;
+        } else {
+            state.streaks.remove(&pretty);
+            state.raw.remove(&pretty);
+        }
+
+        let changed = match &old_broken {
+            Some(old_broken) => data_changed(old_broken, &new_broken),
+            None => true,
+        };
+
+        let alerts = evaluate_alerts(
+            &state.config.alerts.rules,
+            &pretty,
+            old_broken.as_deref().map(Vec::as_slice),
+            &new_broken,
+        );
+        raise_alerts(state, &pretty, alerts);
+
+        (new_broken, since_index, changed)
+    };
+
+    // the data is written (and signed) before it is served, so the served signature always matches it
+    let (signing, publish, history_size, fake_data) = {
+        let guard = read_lock(state);
+        (
+            guard.config.signing.clone(),
+            guard.config.publish.clone(),
+            guard.config.repochecker.history_size,
+            guard.fake_data,
+        )
+    };
+
+    // fake data is only served from memory
+    if fake_data {
+        let mut guard = write_lock(state);
+        guard.values.insert(pretty.clone(), Arc::new(new_broken));
+        guard.failures.remove(&pretty);
+        return changed;
+    }
+
+    let signature_path =
+        match persist_results(entry, run, &new_broken, &since_index, signing.as_ref(), history_size).await {
+            Ok(signature_path) => signature_path,
+            Err(error) => {
+                error!("Failed to write results for {} to disk: {}", &pretty, error);
+                None
+            },
+        };
+
+    let new_broken = Arc::new(new_broken);
+
+    {
+        let mut guard = write_lock(state);
+        let state = &mut *guard;
+
+        state.values.insert(pretty.clone(), new_broken.clone());
+        state.failures.remove(&pretty);
+    }
+
+    if let Some(publish) = publish {
+        publish_results(&publish, entry, signature_path).await;
+        export_tables(state, &publish, &pretty, &new_broken).await;
+    }
+
+    changed
+}
+
+/// Checks a release and replaces its data. If only some architectures are checked (`partial`), the results for all
+/// other architectures are kept.
+async fn run_worker(state: GlobalState, entry: MatrixEntry, cycle: Uuid, cancel: CancellationToken, partial: bool) {
+    let pretty = entry.key();
+    let run = Uuid::new_v4();
+    let started_at = Utc::now();
+
+    let json_path = get_json_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+    let frozen_path = get_frozen_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+
+    // data for frozen releases is never regenerated
+    if load_cached(&state, &entry) {
+        return;
+    }
+
+    // data for external releases is only imported (see /admin/import)
+    if entry.external {
+        return;
+    }
+
+    if !entry.archived {
+        info!("Generating data for {} (run {}, cycle {})", &pretty, run, cycle);
+    } else {
+        info!(
+            "Generating final data for archived release {} (run {}, cycle {})",
+            &pretty, run, cycle
+        );
+    }
+
+    let mut arches: Vec<String> = Vec::new();
+    let mut multi_arch: HashMap<String, Vec<String>> = HashMap::new();
+
+    for arch in &entry.arches {
+        arches.push(arch.name.clone());
+        multi_arch.insert(arch.name.clone(), arch.multi_arch.clone());
+    }
+
+    let overrides = {
+        let guard = read_lock(&state);
+        let state = &*guard;
+
+        match &entry.product {
+            Some(product) => match state.product_overrides.get(product) {
+                Some(overrides) => overrides.clone(),
+                None => {
+                    error!("Failed to find overrides for product {}.", product);
+                    return;
+                },
+            },
+            None => state.overrides.clone(),
+        }
+    };
+
+    let admins = {
+        let guard = read_lock(&state);
+        let state = &*guard;
+
+        state.admins.clone()
+    };
+
+    let maintainers = {
+        let guard = read_lock(&state);
+        let state = &*guard;
+        state.maintainers.clone()
+    };
+
+    let min_free_space = {
+        let guard = read_lock(&state);
+        let state = &*guard;
+        state.config.repochecker.min_free_space
+    };
+
+    let fake_data = read_lock(&state).fake_data;
+
+    // packages that used to provide capabilities in the previous run (for matching overrides by provider)
+    let previous_provides: HashMap<String, ProvideIndex> = arches
+        .iter()
+        .filter_map(|arch| {
+            let path = get_provides_path(entry.product.as_deref(), &entry.release, entry.with_testing, arch);
+            read_provides_from_file(&path).ok().map(|index| (arch.clone(), index))
+        })
+        .collect();
+
+    // keep the caches of this release from being removed until all queries that are run after the check are done
+    let _leases = if !fake_data {
+        match lease_caches(&entry.cache_name(), &arches) {
+            Ok(leases) => leases,
+            Err(error) => {
+                error!("Failed to mark caches for {} as in use: {}", &pretty, error);
+                return;
+            },
+        }
+    } else {
+        Vec::new()
+    };
+
+    let result = if !fake_data {
+        get_repo_closure(
+            &entry.cache_name(),
+            &entry.release,
+            &arches,
+            &multi_arch,
+            &entry.repos,
+            &entry.check,
+            overrides,
+            &admins,
+            &maintainers,
+            min_free_space,
+            entry.detect_exclude_arch,
+            &previous_provides,
+            &entry.package_globs,
+            entry.modular,
+            &cancel,
+        )
+        .await
+    } else {
+        get_fake_repo_closure(entry.product.as_deref(), &entry.release, entry.with_testing, &arches)
+    };
+
+    let closure = match result {
+        Ok(closure) => closure,
+        Err(_) if cancel.is_cancelled() => {
+            info!("Cancelled generating data for {}.", &pretty);
+            return;
+        },
+        Err(error) => {
+            error!(
+                "Failed to generate repoclosure for {} (run {}): {}",
+                &pretty, run, error
+            );
+
+            write_lock(&state).failures.insert(
+                pretty.clone(),
+                RunFailure {
+                    reason: classify_dnf_error(&error),
+                    message: error,
+                    failed_at: Utc::now(),
+                },
+            );
+
+            if entry.archived {
+                // freeze the most recent data that is available instead
+                freeze(&state, &pretty, &json_path, &frozen_path);
+            }
+
+            return;
+        },
+    };
+
+    report_progress(ProgressStage::PostProcessing, None);
+
+    let mut broken = closure.broken;
+    let downloads = closure.downloads;
+
+    if partial {
+        let guard = read_lock(&state);
+        let checked: HashSet<&str> = entry.arches.iter().map(|arch| arch.name.as_str()).collect();
+
+        if let Some(old) = guard.values.get(&pretty) {
+            broken.extend(
+                old.iter()
+                    .filter(|item| !checked.contains(item.repo_arch.as_str()))
+                    .cloned(),
+            );
+            broken.sort_by(|a, b| (&a.source, &a.package, &a.arch).cmp(&(&b.source, &b.package, &b.arch)));
+        }
+    }
+
+    {
+        let mut guard = write_lock(&state);
+        let excluded = guard.excluded.entry(pretty.clone()).or_default();
+        if !partial {
+            excluded.clear();
+        }
+        excluded.extend(closure.excluded);
+    }
+
+    // drop packages that are out of scope for this deployment
+    {
+        let guard = read_lock(&state);
+        broken.retain(|item| guard.config.filter.matches(item));
+    }
+
+    // keep serving the previous data if the new results are implausible
+    let validation = {
+        let guard = read_lock(&state);
+        let previous = guard.values.get(&pretty).map(|values| values.len()).unwrap_or_default();
+        guard
+            .config
+            .repochecker
+            .validation
+            .validate(previous, broken.len(), dnf_errors())
+    };
+
+    if let Err(error) = validation {
+        warn!("Rejected results for {} (run {}): {}", &pretty, run, error);
+
+        write_lock(&state).failures.insert(
+            pretty.clone(),
+            RunFailure {
+                reason: FailureReason::ImplausibleResult,
+                message: error,
+                failed_at: Utc::now(),
+            },
+        );
+
+        if entry.archived {
+            freeze(&state, &pretty, &json_path, &frozen_path);
+        }
+
+        return;
+    }
+
+    {
+        let guard = read_lock(&state);
+        if guard.config.critpath.is_some() {
+            let previous = guard
+                .values
+                .get(&pretty)
+                .map(|values| values.as_slice())
+                .unwrap_or_default();
+            flag_critpath(&mut broken, &entry.release, guard.critpath.as_deref(), previous);
+        }
+    }
+
+    // look up which versions of libraries with missing sonames are still available
+    let basenames: BTreeSet<String> = broken
+        .iter()
+        .flat_map(|item| item.broken.iter().zip(item.categories.iter()))
+        .filter(|(_, category)| matches!(category, DepCategory::SharedLibrary | DepCategory::Rich))
+        .flat_map(|(dep, _)| expand_alternatives(dep))
+        .filter_map(|dep| parse_soname(&dep).map(|(basename, _)| basename.to_string()))
+        .collect();
+
+    if !fake_data {
+        match get_provided_sonames(
+            &entry.cache_name(),
+            &entry.release,
+            &arches,
+            &entry.repos,
+            &basenames,
+            &entry.package_globs,
+            &cancel,
+        )
+        .await
+        {
+            Ok(provided) => {
+                write_lock(&state).provided_sonames.insert(pretty.clone(), provided);
+            },
+            Err(error) => error!("Failed to look up provided sonames for {}: {}", &pretty, error),
+        }
+
+        // provides that were read before they were updated are replaced (and read again if not all architectures were
+        // updated)
+        match annotate_dependencies(&entry, &pretty, &arches, &mut broken, &cancel).await {
+            Some(indices) if !partial => write_lock(&state).provides.insert(pretty.clone(), Arc::new(indices)),
+            _ => write_lock(&state).provides.remove(&pretty),
+        };
+
+        // comps group membership is the same on all architectures, so only query the primary one
+        if let Some(arch) = arches.first() {
+            let packages: BTreeSet<String> = broken
+                .iter()
+                .filter(|item| item.arch != "src")
+                .map(|item| item.package.clone())
+                .collect();
+
+            match get_package_groups(
+                &entry.cache_name(),
+                &entry.release,
+                arch,
+                &entry.repos,
+                &entry.package_globs,
+                &packages,
+                &cancel,
+            )
+            .await
+            {
+                Ok(groups) => {
+                    for item in broken.iter_mut() {
+                        if let Some(groups) = groups.get(&item.package) {
+                            item.groups = groups.clone();
+                        }
+                    }
+                },
+                Err(error) => error!("Failed to look up comps groups for {}: {}", &pretty, error),
+            }
+        }
+
+        // check that packages and groups which must always be installable can actually be installed
+        if !entry.protected.is_empty() || !entry.protected_groups.is_empty() {
+            let mut checks = Vec::new();
+
+            for arch in &arches {
+                for (kind, names) in [
+                    (InstallKind::Package, &entry.protected),
+                    (InstallKind::Group, &entry.protected_groups),
+                ] {
+                    if names.is_empty() {
+                        continue;
+                    }
+
+                    match check_installable(
+                        &entry.cache_name(),
+                        &entry.release,
+                        arch,
+                        &entry.repos,
+                        &entry.package_globs,
+                        kind,
+                        names,
+                        &cancel,
+                    )
+                    .await
+                    {
+                        Ok(results) => checks.extend(results),
+                        Err(error) => error!("Failed to check protected packages for {}/{}: {}", &pretty, arch, error),
+                    }
+                }
+            }
+
+            write_lock(&state).install_checks.insert(pretty.clone(), checks);
+        }
+    }
+
+    // discard incomplete data
+    if cancel.is_cancelled() {
+        info!("Cancelled generating data for {}.", &pretty);
+        return;
+    }
+
+    let cache_size = if !fake_data {
+        match get_cache_size(&entry.cache_name(), &arches).await {
+            Ok(size) => Some(size),
+            Err(error) => {
+                warn!("Failed to determine size of caches for {}: {}", &pretty, error);
+                None
+            },
+        }
+    } else {
+        None
+    };
+
+    let changed = store_results(&state, &entry, run, broken).await;
+
+    {
+        let mut guard = write_lock(&state);
+        let state = &mut *guard;
+
+        let finished_at = Utc::now();
+        let wall_time = (finished_at - started_at).num_milliseconds() as f64 / 1000.0;
+
+        if let Some(threshold) = state.config.repochecker.run_time_warning {
+            if wall_time > threshold * 60.0 {
+                warn!(
+                    "Checking {} took {:.1} minutes, which is longer than the configured threshold ({} minutes).",
+                    &pretty,
+                    wall_time / 60.0,
+                    threshold
+                );
+            }
+        }
+
+        if let Some(adaptive) = &state.config.repochecker.adaptive {
+            let interval =
+                adaptive.next_interval(state.schedule.get(&pretty).map(|schedule| schedule.interval), changed);
+            let next_run = started_at + chrono::Duration::seconds((interval * 60.0 * 60.0) as i64);
+
+            info!(
+                "Data for {} {}, checking again in {:.1} hours.",
+                &pretty,
+                if changed { "changed" } else { "did not change" },
+                interval
+            );
+            state.schedule.insert(pretty.clone(), Schedule { interval, next_run });
+        }
+
+        state.runs.insert(
+            pretty.clone(),
+            RunInfo {
+                run,
+                cycle,
+                started_at,
+                finished_at,
+                wall_time,
+                dnf_cpu_time: dnf_cpu_time().as_secs_f64(),
+                cache_size,
+                downloads,
+            },
+        );
+    }
+
+    if read_lock(&state).config.repochecker.adaptive.is_some() {
+        persist_schedule(&state);
+    }
+
+    if entry.archived {
+        freeze(&state, &pretty, &json_path, &frozen_path);
+    }
+
+    info!("Generated data for {} (run {}).", &pretty, run);
+}
+
+fn render_index(state: &GlobalState, live: bool) -> Result<String, String> {
+    let (entries, mut stats, date_refreshed, templates, branding) = {
+        let guard = read_lock(state);
+        let state = &*guard;
+
+        let mut entries: Vec<IndexEntry> = state
+            .values
+            .iter()
+            .map(|(key, values)| IndexEntry {
+                key: key.clone(),
+                refreshed: state
+                    .runs
+                    .get(key)
+                    .map(|run| run.finished_at)
+                    .or_else(|| state.imported.get(key).copied()),
+                frozen_at: state.frozen.get(key).copied(),
+                critpath: values.iter().filter(|item| item.critpath).count(),
+                uninstallable: state
+                    .install_checks
+                    .get(key)
+                    .map(|checks| checks.iter().filter(|check| !check.installable).count())
+                    .unwrap_or_default(),
+            })
+            .collect();
+        let mut stats: Vec<(String, usize)> = state
+            .values
+            .iter()
+            .map(|(release, broken_items)| (release.to_owned(), broken_items.len()))
+            .collect();
+
+        // archived releases whose data was not loaded into memory
+        for (key, archived) in &state.archived {
+            if state.values.contains_key(key) {
+                continue;
+            }
+
+            entries.push(IndexEntry {
+                key: key.clone(),
+                refreshed: state.runs.get(key).map(|run| run.finished_at),
+                frozen_at: state.frozen.get(key).copied(),
+                critpath: archived.critpath,
+                uninstallable: 0,
+            });
+            stats.push((key.clone(), archived.items));
+        }
+
+        let date_refreshed = match state.date_refreshed {
+            Some(dt) => dt.to_string(),
+            None => String::from("(initial refresh still running after service was restarted)"),
+        };
+
+        (
+            entries,
+            stats,
+            date_refreshed,
+            state.config.templates.clone(),
+            state.config.branding.clone(),
+        )
+    };
+
+    stats.sort();
+    stats.reverse();
+
+    Index::new(entries, stats, date_refreshed, live, branding).render_page(templates.as_ref())
+}
+
+/// Writes a static export of the index page and uploads it, if publishing is configured.
+pub(crate) async fn export_index(state: &GlobalState) {
+    let publish = {
+        let guard = read_lock(state);
+        match &guard.config.publish {
+            // fake data is never published
+            Some(publish) if !guard.fake_data => publish.clone(),
+            _ => return,
+        }
+    };
+
+    let path = get_index_export_path();
+
+    // the exported page links to other exported files instead of routes of the service
+    let body = match render_index(state, false) {
+        Ok(body) => body,
+        Err(error) => {
+            error!("Failed to export index page: {}", error);
+            return;
+        },
+    };
+
+    if let Err(error) = std::fs::write(&path, body) {
+        error!("Failed to export index page: {}", error);
+        return;
+    }
+
+    if let Err(error) = publish_file(&publish, &path, "index.html", "text/html").await {
+        error!("Failed to publish index page: {}", error);
+    }
+}
+
+/// Writes static exports of all pages of the table of broken packages of a release and uploads them.
+async fn export_tables(state: &GlobalState, publish: &PublishConfig, key: &str, values: &[BrokenItem]) {
+    let (templates, co_maintainers, branding) = {
+        let guard = read_lock(state);
+        (
+            guard.config.templates.clone(),
+            guard.config.attribution.html,
+            guard.config.branding.clone(),
+        )
+    };
+
+    let mut page = 1;
+    loop {
+        let query = TableQuery {
+            page: Some(page),
+            ..Default::default()
+        };
+        let table = ReleaseTable::new(key, values, query, co_maintainers, false, branding.clone());
+
+        let body = match table.render_page(templates.as_ref()) {
+            Ok(body) => body,
+            Err(error) => {
+                error!("Failed to export table of broken packages of {}: {}", key, error);
+                return;
+            },
+        };
+
+        // exports are stored with the same layout as static renders
+        let relative = table_export_path(key, page);
+        let path = get_data_path().join(&relative);
+
+        let written = match path.parent() {
+            Some(parent) => std::fs::create_dir_all(parent).and_then(|_| std::fs::write(&path, body)),
+            None => std::fs::write(&path, body),
+        };
+
+        if let Err(error) = written {
+            error!("Failed to export table of broken packages of {}: {}", key, error);
+            return;
+        }
+
+        if let Err(error) = publish_file(publish, &path, &relative, "text/html").await {
+            error!("Failed to publish table of broken packages of {}: {}", key, error);
+        }
+
+        if page >= table.pages() {
+            break;
+        }
+        page += 1;
+    }
+}
+
+/// Returns the persisted provides of all architectures of a release, which are read from disk (without blocking the
+/// runtime) when they are first requested after the release was checked.
+async fn release_provides(state: &GlobalState, entry: MatrixEntry) -> Arc<Vec<(String, ProvideIndex)>> {
+    let key = entry.key();
+
+    if let Some(indices) = read_lock(state).provides.get(&key) {
+        return indices.clone();
+    }
+
+    let indices = tokio::task::spawn_blocking(move || {
+        entry
+            .arches
+            .iter()
+            .filter_map(|arch| {
+                let path = get_provides_path(entry.product.as_deref(), &entry.release, entry.with_testing, &arch.name);
+                read_provides_from_file(&path)
+                    .ok()
+                    .map(|index| (arch.name.clone(), index))
+            })
+            .collect()
+    })
+    .await
+    .unwrap_or_default();
+
+    write_lock(state)
+        .provides
+        .entry(key)
+        .or_insert_with(|| Arc::new(indices))
+        .clone()
+}
+
+/// Error type for HTTP handlers that is turned into a plain-text response with the appropriate status code.
+pub(crate) struct AppError {
+    status: StatusCode,
+    message: String,
+}
+
+impl AppError {
+    pub(crate) fn internal(message: impl Into<String>) -> Self {
+        let message = message.into();
+        error!("Failed to handle request: {}", message);
+
+        AppError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message,
+        }
+    }
+
+    pub(crate) fn not_found(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn bad_request(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn unauthorized(message: impl Into<String>) -> Self {
+        AppError {
+            status: StatusCode::UNAUTHORIZED,
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        (self.status, self.message).into_response()
+    }
+}
+
+fn json_response<T: Serialize + ?Sized>(value: &T) -> Result<Response, AppError> {
+    let body = serde_json::to_string_pretty(value)
+        .map_err(|error| AppError::internal(format!("Failed to serialize into JSON: {}", error)))?;
+
+    Ok(([(CONTENT_TYPE, HeaderValue::from_static("application/json"))], body).into_response())
+}
+
+/// Output format of endpoints that return lists (of broken packages, or of report entries).
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ListFormat {
+    #[default]
+    Json,
+    // JSON Lines, with one compact JSON object per line
+    Ndjson,
+}
+
+#[derive(Deserialize)]
+struct FormatParams {
+    #[serde(default)]
+    format: ListFormat,
+}
+
+#[derive(Deserialize)]
+struct DataParams {
+    #[serde(default)]
+    format: ListFormat,
+    // include quarantined packages
+    #[serde(default)]
+    raw: bool,
+}
+
+/// Returns the data of all releases, including the frozen data of archived releases that is not loaded into memory.
+///
+/// This is used for reports across all releases, which would otherwise depend on which archived releases were
+/// requested recently.
+pub(crate) async fn all_values(state: &GlobalState) -> HashMap<String, Arc<Vec<BrokenItem>>> {
+    let (mut values, unloaded) = {
+        let guard = read_lock(state);
+        let state = &*guard;
+
+        let unloaded: Vec<String> = state
+            .archived
+            .keys()
+            .filter(|key| !state.values.contains_key(*key))
+            .cloned()
+            .collect();
+
+        (state.values.clone(), unloaded)
+    };
+
+    for key in unloaded {
+        if let Some(archived) = load_archived(state, &key).await {
+            values.insert(key, archived);
+        }
+    }
+
+    values
+}
+
+/// Returns the published data of a release, or its unfiltered data (if `raw` is set and any packages are quarantined).
+async fn release_values(state: &GlobalState, key: &str, raw: bool) -> Option<Arc<Vec<BrokenItem>>> {
+    if raw {
+        if let Some(values) = read_lock(state).raw.get(key).cloned() {
+            return Some(values);
+        }
+    }
+
+    load_archived(state, key).await
+}
+
+// number of items that are serialized at once when streaming JSON Lines
+const NDJSON_CHUNK_SIZE: usize = 256;
+
+/// Returns a list as a JSON array, or in the JSON Lines format.
+///
+/// JSON Lines are serialized in chunks while the response is sent, so the serialized form of large lists is never held
+/// in memory as a whole.
+fn list_response<T: Serialize + Send + Sync + 'static>(
+    values: Arc<Vec<T>>,
+    format: ListFormat,
+) -> Result<Response, AppError> {
+    match format {
+        ListFormat::Json => json_response(values.as_slice()),
+        ListFormat::Ndjson => {
+            let chunks = (0..values.len()).step_by(NDJSON_CHUNK_SIZE).map(move |start| {
+                let end = values.len().min(start + NDJSON_CHUNK_SIZE);
+
+                let mut chunk = Vec::new();
+                for value in &values[start..end] {
+                    serde_json::to_writer(&mut chunk, value)?;
+                    chunk.push(b'\n');
+                }
+
+                Ok::<_, serde_json::Error>(Bytes::from(chunk))
+            });
+
+            let body = StreamBody::new(futures_util::stream::iter(chunks));
+            Ok(([(CONTENT_TYPE, HeaderValue::from_static("application/x-ndjson"))], body).into_response())
+        },
+    }
+}
+
+/// Checks that a request to an administrative endpoint carries the configured bearer token.
+fn authorize(state: &GlobalState, headers: &HeaderMap) -> Result<(), AppError> {
+    let admin = match read_lock(state).config.admin.clone() {
+        Some(admin) => admin,
+        None => return Err(AppError::not_found("Administrative endpoints are not enabled.")),
+    };
+
+    let token = admin.token().map_err(AppError::internal)?;
+
+    let provided = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    // compare all bytes to not leak how much of the token matched via response times
+    match provided {
+        Some(provided)
+            if provided.len() == token.len()
+                && provided.bytes().zip(token.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0 =>
+        {
+            Ok(())
+        },
+        _ => Err(AppError::unauthorized("Missing or invalid token.")),
+    }
+}
+
+fn text_response(body: String) -> Response {
+    ([(CONTENT_TYPE, HeaderValue::from_static("text/plain"))], body).into_response()
+}
+
+fn html_response(body: String) -> Response {
+    ([(CONTENT_TYPE, HeaderValue::from_static("text/html"))], body).into_response()
+}
+
+/// Formats the resource usage of the most recent run for every release in the Prometheus text format.
+fn prometheus_metrics(runs: &HashMap<String, RunInfo>) -> String {
+    let mut releases: Vec<(&String, &RunInfo)> = runs.iter().collect();
+    releases.sort_by_key(|(release, _)| *release);
+
+    let mut output = String::new();
+
+    let mut push_metric = |name: &str, help: &str, value: &dyn Fn(&RunInfo) -> Option<f64>| {
+        output.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+
+        for (release, run) in &releases {
+            if let Some(value) = value(run) {
+                output.push_str(&format!("{}{{release=\"{}\"}} {}\n", name, release, value));
+            }
+        }
+    };
+
+    push_metric(
+        "repochecker_run_wall_seconds",
+        "Wall time of the most recent run.",
+        &|run| Some(run.wall_time),
+    );
+    push_metric(
+        "repochecker_run_dnf_cpu_seconds",
+        "CPU time used by dnf during the most recent run.",
+        &|run| Some(run.dnf_cpu_time),
+    );
+    push_metric(
+        "repochecker_run_cache_bytes",
+        "Size of the dnf caches at the end of the most recent run.",
+        &|run| run.cache_size.map(|size| size as f64),
+    );
+
+    // metadata downloads are reported per repository, to find out which mirrors are slow
+    let mut push_download_metric = |name: &str, help: &str, value: &dyn Fn(&RepoDownload) -> f64| {
+        output.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+
+        for (release, run) in &releases {
+            for download in &run.downloads {
+                output.push_str(&format!(
+                    "{}{{release=\"{}\",arch=\"{}\",repo=\"{}\"}} {}\n",
+                    name,
+                    release,
+                    download.arch,
+                    download.repo,
+                    value(download)
+                ));
+            }
+        }
+    };
+
+    push_download_metric(
+        "repochecker_repo_download_bytes",
+        "Size of the metadata that was downloaded for a repository during the most recent run.",
+        &|download| download.bytes as f64,
+    );
+    push_download_metric(
+        "repochecker_repo_download_seconds",
+        "Time that refreshing the metadata of a repository took during the most recent run.",
+        &|download| download.seconds,
+    );
+    push_download_metric(
+        "repochecker_repo_download_rate_bytes",
+        "Download rate (in bytes per second) of the metadata of a repository during the most recent run.",
+        &|download| {
+            if download.seconds > 0.0 {
+                download.bytes as f64 / download.seconds
+            } else {
+                0.0
+            }
+        },
+    );
+
+    output
+}
+
+pub(crate) fn router(state: GlobalState) -> Router {
+    let router = Router::new();
+
+    // snapshots and imported data are larger than the default limit for request bodies
+    let max_body_bytes = AdminConfig::max_body_bytes(read_lock(&state).config.admin.as_ref());
+
+    let index_state = state.clone();
+    let router = router.route(
+        "/",
+        get(move || async move {
+            let body = render_index(&index_state, true).map_err(AppError::internal)?;
+            Ok::<_, AppError>(html_response(body))
+        }),
+    );
+
+    let release_state = state.clone();
+    let router = router.route(
+        "/data/:release",
+        get(
+            move |release: Path<String>, Query(params): Query<DataParams>| async move {
+                let values = release_values(&release_state, &release.0, params.raw).await;
+
+                match values {
+                    Some(values) => list_response(values, params.format),
+                    None => Err(AppError::not_found("This release does not exist.")),
+                }
+            },
+        ),
+    );
+
+    let arch_diff_state = state.clone();
+    let router = router.route(
+        "/data/:release/arch-diff",
+        get(move |release: Path<String>| async move {
+            let values = load_archived(&arch_diff_state, &release.0).await;
+            let excluded = read_lock(&arch_diff_state)
+                .excluded
+                .get(&release.0)
+                .cloned()
+                .unwrap_or_default();
+
+            match values {
+                Some(values) => json_response(&arch_diff(&values, &excluded)),
+                None => Err(AppError::not_found("This release does not exist.")),
+            }
+        }),
+    );
+
+    /// Renders one page of the table of broken packages of a release.
+    async fn release_table_response(state: &GlobalState, key: &str, query: TableQuery) -> Result<Response, AppError> {
+        let values = load_archived(state, key).await;
+        let (templates, co_maintainers, branding) = {
+            let guard = read_lock(state);
+            (
+                guard.config.templates.clone(),
+                guard.config.attribution.html,
+                guard.config.branding.clone(),
+            )
+        };
+
+        let values = match values {
+            Some(values) => values,
+            None => return Err(AppError::not_found("This release does not exist.")),
+        };
+
+        let body = ReleaseTable::new(key, &values, query.normalize(), co_maintainers, true, branding)
+            .render_page(templates.as_ref())
+            .map_err(AppError::internal)?;
+        Ok(html_response(body))
+    }
+
+    let table_state = state.clone();
+    let router = router.route(
+        "/releases/:release",
+        get(
+            move |Path(release): Path<String>, Query(query): Query<TableQuery>| async move {
+                release_table_response(&table_state, &release, query).await
+            },
+        ),
+    );
+
+    let product_table_state = state.clone();
+    let router = router.route(
+        "/products/:product/releases/:release",
+        get(
+            move |Path((product, release)): Path<(String, String)>, Query(query): Query<TableQuery>| async move {
+                let key = release_key(Some(&product), &release);
+                release_table_response(&product_table_state, &key, query).await
+            },
+        ),
+    );
+
+    /// Redirects to the row of a source package in the table of broken packages of a release.
+    async fn go_response(state: &GlobalState, key: &str, source: &str) -> Result<Response, AppError> {
+        let page = match load_archived(state, key).await {
+            Some(values) => page_of_source(&values, source),
+            None => return Err(AppError::not_found("This release does not exist.")),
+        };
+
+        let page = match page {
+            Some(page) => page,
+            None => return Err(AppError::not_found("This package is not broken in this release.")),
+        };
+
+        let path = match key.split_once('/') {
+            Some((product, release)) => format!("/products/{}/releases/{}", product, release),
+            None => format!("/releases/{}", key),
+        };
+
+        Ok(Redirect::temporary(&format!("{}?page={}#{}", path, page, source_anchor(source))).into_response())
+    }
+
+    let go_state = state.clone();
+    let router = router.route(
+        "/go/:release/:source",
+        get(move |Path((release, source)): Path<(String, String)>| async move {
+            go_response(&go_state, &release, &source).await
+        }),
+    );
+
+    let product_go_state = state.clone();
+    let router = router.route(
+        "/products/:product/go/:release/:source",
+        get(
+            move |Path((product, release, source)): Path<(String, String, String)>| async move {
+                let key = release_key(Some(&product), &release);
+                go_response(&product_go_state, &key, &source).await
+            },
+        ),
+    );
+
+    /// Lists broken packages of a release that are part of the critical path.
+    async fn critpath_response(state: &GlobalState, key: &str, format: ListFormat) -> Result<Response, AppError> {
+        match load_archived(state, key).await {
+            Some(values) => {
+                let critpath: Vec<BrokenItem> = values.iter().filter(|item| item.critpath).cloned().collect();
+                list_response(Arc::new(critpath), format)
+            },
+            None => Err(AppError::not_found("This release does not exist.")),
+        }
+    }
+
+    /// Lists the results of checking whether protected packages of a release are installable.
+    fn protected_response(state: &GlobalState, key: &str) -> Result<Response, AppError> {
+        let checks = read_lock(state).install_checks.get(key).cloned();
+
+        match checks {
+            Some(checks) => json_response(&checks),
+            None => Err(AppError::not_found(
+                "This release does not exist or has no protected packages.",
+            )),
+        }
+    }
+
+    let protected_state = state.clone();
+    let router = router.route(
+        "/data/:release/protected",
+        get(move |release: Path<String>| async move { protected_response(&protected_state, &release.0) }),
+    );
+
+    let product_protected_state = state.clone();
+    let router = router.route(
+        "/products/:product/data/:release/protected",
+        get(move |Path((product, release)): Path<(String, String)>| async move {
+            protected_response(&product_protected_state, &release_key(Some(&product), &release))
+        }),
+    );
+
+    let critpath_state = state.clone();
+    let router = router.route(
+        "/data/:release/critpath",
+        get(
+            move |release: Path<String>, Query(params): Query<FormatParams>| async move {
+                critpath_response(&critpath_state, &release.0, params.format).await
+            },
+        ),
+    );
+
+    let product_critpath_state = state.clone();
+    let router = router.route(
+        "/products/:product/data/:release/critpath",
+        get(
+            move |Path((product, release)): Path<(String, String)>, Query(params): Query<FormatParams>| async move {
+                let key = release_key(Some(&product), &release);
+                critpath_response(&product_critpath_state, &key, params.format).await
+            },
+        ),
+    );
+
+    let combined_state = state.clone();
+    let router = router.route(
+        "/data/:release/combined",
+        get(
+            move |release: Path<String>, Query(params): Query<FormatParams>| async move {
+                let stable = load_archived(&combined_state, &release.0).await;
+                let testing = load_archived(&combined_state, &format!("{}-testing", release.0)).await;
+
+                if stable.is_none() && testing.is_none() {
+                    return Err(AppError::not_found("This release does not exist."));
+                }
+
+                let combined = combine_testing(
+                    stable.as_deref().map(Vec::as_slice).unwrap_or_default(),
+                    testing.as_deref().map(Vec::as_slice).unwrap_or_default(),
+                );
+
+                list_response(Arc::new(combined), params.format)
+            },
+        ),
+    );
+
+    let excluded_state = state.clone();
+    let router = router.route(
+        "/debug/:release/excluded",
+        get(move |release: Path<String>| async move {
+            let excluded = {
+                let guard = read_lock(&excluded_state);
+                let state = &*guard;
+                state.excluded.get(&release.0).cloned()
+            };
+
+            match excluded {
+                Some(excluded) => json_response(&excluded),
+                None => Err(AppError::not_found(
+                    "This release does not exist or has not been checked yet.",
+                )),
+            }
+        }),
+    );
+
+    let product_state = state.clone();
+    let router = router.route(
+        "/products/:product/data/:release",
+        get(
+            move |Path((product, release)): Path<(String, String)>, Query(params): Query<DataParams>| async move {
+                let key = release_key(Some(&product), &release);
+                let values = release_values(&product_state, &key, params.raw).await;
+
+                match values {
+                    Some(values) => list_response(values, params.format),
+                    None => Err(AppError::not_found("This release does not exist.")),
+                }
+            },
+        ),
+    );
+
+    /// Looks up the detached signature of the persisted data for a release.
+    fn signature_response(state: &GlobalState, key: &str) -> Result<Response, AppError> {
+        let matrix = read_lock(state)
+            .config
+            .to_matrix()
+            .map_err(|error| AppError::internal(format!("Invalid configuration: {}", error)))?;
+
+        let entry = match matrix.iter().find(|entry| entry.key() == key) {
+            Some(entry) => entry,
+            None => return Err(AppError::not_found("This release does not exist.")),
+        };
+
+        let json_path = get_json_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+
+        match std::fs::read_to_string(get_signature_path(&json_path)) {
+            Ok(signature) => Ok((
+                [(CONTENT_TYPE, HeaderValue::from_static("application/pgp-signature"))],
+                signature,
+            )
+                .into_response()),
+            Err(_) => Err(AppError::not_found("No signature is available for this release.")),
+        }
+    }
+
+    /// Looks up the directory in which previous generations of the data for a release are kept.
+    fn history_path(state: &GlobalState, key: &str) -> Result<std::path::PathBuf, AppError> {
+        let matrix = read_lock(state)
+            .config
+            .to_matrix()
+            .map_err(|error| AppError::internal(format!("Invalid configuration: {}", error)))?;
+
+        match matrix.iter().find(|entry| entry.key() == key) {
+            Some(entry) => Ok(get_history_path(
+                entry.product.as_deref(),
+                &entry.release,
+                entry.with_testing,
+            )),
+            None => Err(AppError::not_found("This release does not exist.")),
+        }
+    }
+
+    fn history_response(state: &GlobalState, key: &str) -> Result<Response, AppError> {
+        let generations = list_generations(&history_path(state, key)?).map_err(AppError::internal)?;
+        json_response(&generations)
+    }
+
+    fn generation_response(
+        state: &GlobalState,
+        key: &str,
+        run: &str,
+        format: ListFormat,
+    ) -> Result<Response, AppError> {
+        let run = match Uuid::parse_str(run) {
+            Ok(run) => run,
+            Err(error) => return Err(AppError::bad_request(format!("Invalid run ID: {}", error))),
+        };
+
+        match read_generation(&history_path(state, key)?, run).map_err(AppError::internal)? {
+            Some(values) => list_response(Arc::new(values), format),
+            None => Err(AppError::not_found("No data is available for this run.")),
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct ChangesParams {
+        since: String,
+    }
+
+    #[derive(Serialize)]
+    struct Changes {
+        since: DateTime<Utc>,
+        // generation of the data that was published at that time, which the current data is compared to
+        run: Uuid,
+        generated_at: DateTime<Utc>,
+        #[serde(flatten)]
+        diff: DataDiff,
+    }
+
+    /// Compares the current data of a release with the data that was published at the given time (which needs to be
+    /// kept in the history of the release).
+    async fn changes_response(state: &GlobalState, key: &str, since: &str) -> Result<Response, AppError> {
+        let since = parse_timestamp(since).map_err(AppError::bad_request)?;
+        let path = history_path(state, key)?;
+
+        let (generation, old) = match read_generation_at(&path, since).map_err(AppError::internal)? {
+            Some(generation) => generation,
+            None => {
+                return Err(AppError::not_found(format!(
+                    "No data from before {} is kept in the history of this release.",
+                    since.to_rfc3339()
+                )))
+            },
+        };
+
+        let new = match release_values(state, key, false).await {
+            Some(values) => values,
+            None => return Err(AppError::not_found("This release does not exist.")),
+        };
+
+        json_response(&Changes {
+            since,
+            run: generation.run,
+            generated_at: generation.generated_at,
+            diff: diff_items(&old, &new),
+        })
+    }
+
+    let changes_state = state.clone();
+    let router = router.route(
+        "/data/:release/changes",
+        get(
+            move |release: Path<String>, Query(params): Query<ChangesParams>| async move {
+                changes_response(&changes_state, &release.0, &params.since).await
+            },
+        ),
+    );
+
+    let product_changes_state = state.clone();
+    let router = router.route(
+        "/products/:product/data/:release/changes",
+        get(
+            move |Path((product, release)): Path<(String, String)>, Query(params): Query<ChangesParams>| async move {
+                let key = release_key(Some(&product), &release);
+                changes_response(&product_changes_state, &key, &params.since).await
+            },
+        ),
+    );
+
+    let history_state = state.clone();
+    let router = router.route(
+        "/data/:release/history",
+        get(move |release: Path<String>| async move { history_response(&history_state, &release.0) }),
+    );
+
+    let product_history_state = state.clone();
+    let router = router.route(
+        "/products/:product/data/:release/history",
+        get(move |Path((product, release)): Path<(String, String)>| async move {
+            history_response(&product_history_state, &release_key(Some(&product), &release))
+        }),
+    );
+
+    let generation_state = state.clone();
+    let router = router.route(
+        "/data/:release/history/:run",
+        get(
+            move |Path((release, run)): Path<(String, String)>, Query(params): Query<FormatParams>| async move {
+                generation_response(&generation_state, &release, &run, params.format)
+            },
+        ),
+    );
+
+    let product_generation_state = state.clone();
+    let router =
+        router.route(
+            "/products/:product/data/:release/history/:run",
+            get(
+                move |Path((product, release, run)): Path<(String, String, String)>,
+                      Query(params): Query<FormatParams>| async move {
+                    generation_response(
+                        &product_generation_state,
+                        &release_key(Some(&product), &release),
+                        &run,
+                        params.format,
+                    )
+                },
+            ),
+        );
+
+    let router = router.route(
+        "/schema/overrides",
+        get(|| async { json_response(&overrides_schema(&format!("repochecker {}", VERSION))) }),
+    );
+
+    let router = router.route(
+        "/schema/config",
+        get(|| async {
+            json_response(&schema_for::<Config>(
+                "Configuration",
+                &format!("repochecker {}", VERSION),
+            ))
+        }),
+    );
+
+    let signature_state = state.clone();
+    let router = router.route(
+        "/data/:release/signature",
+        get(move |release: Path<String>| async move { signature_response(&signature_state, &release.0) }),
+    );
+
+    let product_signature_state = state.clone();
+    let router = router.route(
+        "/products/:product/data/:release/signature",
+        get(move |Path((product, release)): Path<(String, String)>| async move {
+            signature_response(&product_signature_state, &release_key(Some(&product), &release))
+        }),
+    );
+
+    /// Wraps the data for a release in an object that includes when it was generated.
+    async fn dataset_response(state: &GlobalState, key: &str) -> Result<Response, AppError> {
+        #[derive(Serialize)]
+        struct Dataset<'a> {
+            release: &'a str,
+            generated_at: Option<DateTime<Utc>>,
+            frozen_at: Option<DateTime<Utc>>,
+            // version of repochecker that serves the data (named like in the response of /version)
+            version: &'a str,
+            items: &'a [BrokenItem],
+        }
+
+        let values = load_archived(state, key).await;
+        let (run, frozen_at, matrix) = {
+            let guard = read_lock(state);
+            let state = &*guard;
+            (
+                state
+                    .runs
+                    .get(key)
+                    .map(|run| run.finished_at)
+                    .or_else(|| state.imported.get(key).copied()),
+                state.frozen.get(key).copied(),
+                state.config.to_matrix().unwrap_or_default(),
+            )
+        };
+
+        let values = match values {
+            Some(values) => values,
+            None => return Err(AppError::not_found("This release does not exist.")),
+        };
+
+        // data that was loaded from disk after a restart was generated when its file was last written
+        let generated_at = run.or(frozen_at).or_else(|| {
+            let entry = matrix.iter().find(|entry| entry.key() == key)?;
+            let json_path = get_json_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+            let modified = std::fs::metadata(json_path)
+                .and_then(|metadata| metadata.modified())
+                .ok()?;
+            Some(DateTime::<Utc>::from(modified))
+        });
+
+        json_response(&Dataset {
+            release: key,
+            generated_at,
+            frozen_at,
+            version: VERSION,
+            items: &values,
+        })
+    }
+
+    let api_state = state.clone();
+    let router = router.route(
+        "/api/v1/data/:release",
+        get(move |release: Path<String>| async move { dataset_response(&api_state, &release.0).await }),
+    );
+
+    let api_product_state = state.clone();
+    let router = router.route(
+        "/api/v1/products/:product/data/:release",
+        get(move |Path((product, release)): Path<(String, String)>| async move {
+            let key = release_key(Some(&product), &release);
+            dataset_response(&api_product_state, &key).await
+        }),
+    );
+
+    let feed_state = state.clone();
+    let router = router.route(
+        "/feeds/maintainer/:file",
+        get(move |file: Path<String>, headers: HeaderMap| async move {
+            let maintainer = match file.0.strip_suffix(".json") {
+                Some(maintainer) => maintainer.to_string(),
+                None => return Err(AppError::not_found("This page does not exist.")),
+            };
+
+            let (email, values, co_maintainers) = {
+                let guard = read_lock(&feed_state);
+                let state = &*guard;
+
+                let matrix = state
+                    .config
+                    .to_matrix()
+                    .map_err(|error| AppError::internal(format!("Invalid configuration: {}", error)))?;
+
+                let values: Vec<(String, Arc<Vec<BrokenItem>>)> = matrix
+                    .iter()
+                    .filter(|entry| !entry.archived)
+                    .filter_map(|entry| {
+                        let key = entry.key();
+                        state.values.get(&key).map(|values| (key, values.clone()))
+                    })
+                    .collect();
+
+                (
+                    // email addresses are only included for requests that are authenticated with the admin token
+                    Some(state.config.email.address(&maintainer)),
+                    values,
+                    state.config.attribution.feeds,
+                )
+            };
+
+            let email = email.filter(|_| authorize(&feed_state, &headers).is_ok());
+
+            let feed = maintainer_feed(
+                &maintainer,
+                email,
+                values
+                    .iter()
+                    .map(|(release, values)| (release.as_str(), values.as_slice())),
+                co_maintainers,
+            );
+
+            json_response(&feed)
+        }),
+    );
+
+    let category_state = state.clone();
+    let router = router.route(
+        "/reports/:release/category/:category",
+        get(
+            move |Path((release, category)): Path<(String, String)>, Query(params): Query<FormatParams>| async move {
+                let category = match DepCategory::from_name(&category) {
+                    Some(category) => category,
+                    None => return Err(AppError::not_found("This category does not exist.")),
+                };
+
+                let values = match load_archived(&category_state, &release).await {
+                    Some(values) => values,
+                    None => return Err(AppError::not_found("This release does not exist.")),
+                };
+
+                list_response(Arc::new(filter_category(&values, category)), params.format)
+            },
+        ),
+    );
+
+    let arch_state = state.clone();
+    let router = router.route(
+        "/reports/:release/arch/:arch",
+        get(
+            move |Path((release, arch)): Path<(String, String)>, Query(params): Query<FormatParams>| async move {
+                let values = match load_archived(&arch_state, &release).await {
+                    Some(values) => values,
+                    None => return Err(AppError::not_found("This release does not exist.")),
+                };
+
+                // packages of all architectures that are checked (including multilib architectures) can be listed
+                let matrix = read_lock(&arch_state)
+                    .config
+                    .to_matrix()
+                    .map_err(|error| AppError::internal(format!("Invalid configuration: {}", error)))?;
+                let checked = matrix
+                    .iter()
+                    .filter(|entry| entry.key() == release)
+                    .flat_map(|entry| entry.arches.iter())
+                    .any(|checked| checked.multi_arch.contains(&arch));
+                if !checked {
+                    return Err(AppError::not_found(
+                        "This architecture is not checked for this release.",
+                    ));
+                }
+
+                list_response(Arc::new(filter_arch(&values, &arch)), params.format)
+            },
+        ),
+    );
+
+    let group_state = state.clone();
+    let router = router.route(
+        "/reports/:release/group/:group",
+        get(
+            move |Path((release, group)): Path<(String, String)>, Query(params): Query<FormatParams>| async move {
+                let values = match load_archived(&group_state, &release).await {
+                    Some(values) => values,
+                    None => return Err(AppError::not_found("This release does not exist.")),
+                };
+
+                list_response(Arc::new(filter_group(&values, &group)), params.format)
+            },
+        ),
+    );
+
+    let soname_state = state.clone();
+    let router = router.route(
+        "/reports/:release/soname-bumps",
+        get(
+            move |release: Path<String>, Query(params): Query<FormatParams>| async move {
+                let values = load_archived(&soname_state, &release.0).await;
+                let provided = read_lock(&soname_state)
+                    .provided_sonames
+                    .get(&release.0)
+                    .cloned()
+                    .unwrap_or_default();
+
+                match values {
+                    Some(values) => list_response(Arc::new(soname_bumps(&values, &provided)), params.format),
+                    None => Err(AppError::not_found("This release does not exist.")),
+                }
+            },
+        ),
+    );
+
+    let regressions_state = state.clone();
+    let router = router.route(
+        "/reports/regressions",
+        get(move |Query(params): Query<FormatParams>| async move {
+            // archived releases are not compared, so their data does not need to be loaded
+            let comparisons = {
+                let guard = read_lock(&regressions_state);
+                regressions(&guard.config, &guard.values)
+            };
+
+            list_response(Arc::new(comparisons), params.format)
+        }),
+    );
+
+    let testing_state = state.clone();
+    let router = router.route(
+        "/reports/new-in-testing",
+        get(move |Query(params): Query<FormatParams>| async move {
+            list_response(
+                Arc::new(new_in_testing(&all_values(&testing_state).await)),
+                params.format,
+            )
+        }),
+    );
+
+    #[derive(Deserialize)]
+    struct ProvidesParams {
+        release: Option<String>,
+    }
+
+    let provides_state = state.clone();
+    let router = router.route(
+        "/provides/:capability",
+        get(
+            move |Path(capability): Path<String>, Query(params): Query<ProvidesParams>| async move {
+                let matrix = read_lock(&provides_state)
+                    .config
+                    .to_matrix()
+                    .map_err(|error| AppError::internal(format!("Invalid configuration: {}", error)))?;
+                let values = all_values(&provides_state).await;
+
+                let mut reports = Vec::new();
+
+                for entry in matrix {
+                    let key = entry.key();
+                    if params.release.as_ref().is_some_and(|release| release != &key) {
+                        continue;
+                    }
+
+                    let values = match values.get(&key) {
+                        Some(values) => values,
+                        None => continue,
+                    };
+
+                    let indices = release_provides(&provides_state, entry).await;
+                    reports.push(capability_report(&key, &capability, &indices, values));
+                }
+
+                if reports.is_empty() && params.release.is_some() {
+                    return Err(AppError::not_found("This release does not exist."));
+                }
+
+                json_response(&reports)
+            },
+        ),
+    );
+
+    #[derive(Deserialize)]
+    struct SearchParams {
+        q: String,
+        page: Option<usize>,
+        limit: Option<usize>,
+    }
+
+    let search_state = state.clone();
+    let router = router.route(
+        "/search",
+        get(move |Query(params): Query<SearchParams>| async move {
+            let query = params.q.trim();
+            if query.is_empty() {
+                return Err(AppError::bad_request("The search query must not be empty."));
+            }
+
+            // results refer to snapshots of the data, so the state is not locked while searching
+            let values = all_values(&search_state).await;
+            let releases = values
+                .iter()
+                .map(|(release, values)| (release.as_str(), values.as_slice()));
+
+            json_response(&search(releases, query, params.page, params.limit))
+        }),
+    );
+
+    let inactive_state = state.clone();
+    let router = router.route(
+        "/reports/inactive",
+        get(move |Query(params): Query<FormatParams>| async move {
+            let values = all_values(&inactive_state).await;
+            let inactive = read_lock(&inactive_state).inactive.clone();
+
+            let releases = values
+                .iter()
+                .map(|(release, values)| (release.as_str(), values.as_slice()));
+            list_response(Arc::new(inactive_admins(releases, &inactive)), params.format)
+        }),
+    );
+
+    let config_state = state.clone();
+    let router = router.route(
+        "/config",
+        get(move || async move {
+            let body = {
+                let state = read_lock(&config_state);
+                basic_toml::to_string(&state.config)
+                    .map_err(|error| AppError::internal(format!("Failed to serialize into TOML: {}", error)))?
+            };
+
+            Ok::<_, AppError>(text_response(body))
+        }),
+    );
+
+    let overrides_state = state.clone();
+    let router = router.route(
+        "/overrides",
+        get(move || async move {
+            let values = {
+                let state = read_lock(&overrides_state);
+                state.overrides.clone()
+            };
+
+            let overrides = read_lock(&values);
+            json_response(&overrides.data)
+        }),
+    );
+
+    /// Shows the overrides that apply to a release (of a product, if the key includes one) and architecture.
+    fn effective_response(state: &GlobalState, key: &str, arch: &str) -> Result<Response, AppError> {
+        let (overrides, release) = {
+            let state = read_lock(state);
+
+            match key.split_once('/') {
+                Some((product, release)) => match state.product_overrides.get(product) {
+                    Some(overrides) => (overrides.clone(), release.to_string()),
+                    None => return Err(AppError::not_found("This product does not exist.")),
+                },
+                None => (state.overrides.clone(), key.to_string()),
+            }
+        };
+
+        let overrides = read_lock(&overrides);
+        json_response(&overrides.effective(&release, arch))
+    }
+
+    let effective_state = state.clone();
+    let router = router.route(
+        "/overrides/effective/:release/:arch",
+        get(move |Path((release, arch)): Path<(String, String)>| async move {
+            effective_response(&effective_state, &release, &arch)
+        }),
+    );
+
+    let product_effective_state = state.clone();
+    let router = router.route(
+        "/products/:product/overrides/effective/:release/:arch",
+        get(
+            move |Path((product, release, arch)): Path<(String, String, String)>| async move {
+                effective_response(&product_effective_state, &release_key(Some(&product), &release), &arch)
+            },
+        ),
+    );
+
+    /// Lists override paths with their use counts, most frequently used first.
+    fn stats_response(stats: &OverrideStats) -> Result<Response, AppError> {
+        #[derive(Serialize)]
+        struct StatsEntry<'a> {
+            path: &'a str,
+            count: u32,
+        }
+
+        let mut output: Vec<StatsEntry> = stats
+            .iter()
+            .map(|(path, count)| StatsEntry { path, count: *count })
+            .collect();
+
+        output.sort_by_key(|b| b.count);
+        output.reverse();
+
+        json_response(&output)
+    }
+
+    let stats_state = state.clone();
+    let router = router.route(
+        "/stats",
+        get(move || async move {
+            let values = {
+                let state = read_lock(&stats_state);
+                state.overrides.clone()
+            };
+
+            let overrides = read_lock(&values);
+            stats_response(&overrides.stats)
+        }),
+    );
+
+    let package_stats_state = state.clone();
+    let router = router.route(
+        "/stats/packages",
+        get(move || async move {
+            let values = {
+                let state = read_lock(&package_stats_state);
+                state.overrides.clone()
+            };
+
+            let overrides = read_lock(&values);
+            stats_response(&overrides.package_stats)
+        }),
+    );
+
+    let router = router.route(
+        "/version",
+        get(move || async move { json_response(&get_version_info().await) }),
+    );
+
+    let status_state = state.clone();
+    let router = router.route(
         "/status",
         get(move || async move {
-            let value = status_state.read().expect("Found a poisoned lock.").date_refreshed;
+            let (value, frozen, cycle, runs, schedule, failures, alerts, paused, degraded, readiness, account_churn) = {
+                let state = read_lock(&status_state);
+                (
+                    state.date_refreshed,
+                    state.frozen.clone(),
+                    state.cycle,
+                    state.runs.clone(),
+                    state.schedule.clone(),
+                    state.failures.clone(),
+                    state.alerts.clone(),
+                    state.paused,
+                    state.degraded.clone(),
+                    state.readiness.clone(),
+                    state.account_churn.clone(),
+                )
+            };
+
+            #[derive(Serialize)]
+            struct FrozenRelease {
+                release: String,
+                frozen_at: String,
+                read_only: bool,
+            }
+
+            #[derive(Serialize)]
+            struct ReleaseRun {
+                release: String,
+                generated_by_run: RunInfo,
+                schedule: Option<Schedule>,
+            }
+
+            #[derive(Serialize)]
+            struct ReleaseFailure {
+                release: String,
+                #[serde(flatten)]
+                failure: RunFailure,
+            }
+
+            #[derive(Serialize)]
+            struct ServiceStatus {
+                last_refreshed: Option<String>,
+                cycle: Option<Uuid>,
+                paused: bool,
+                // reason why the service is running without package maintainers
+                degraded: Option<String>,
+                readiness: Option<Readiness>,
+                account_churn: Option<AccountChurn>,
+                frozen: Vec<FrozenRelease>,
+                runs: Vec<ReleaseRun>,
+                failures: Vec<ReleaseFailure>,
+                alerts: Vec<Alert>,
+            }
+
+            let last_refreshed = value.map(|dt| dt.to_string());
+
+            let mut frozen: Vec<FrozenRelease> = frozen
+                .into_iter()
+                .map(|(release, frozen_at)| FrozenRelease {
+                    release,
+                    frozen_at: frozen_at.to_string(),
+                    read_only: true,
+                })
+                .collect();
+            frozen.sort_by(|a, b| a.release.cmp(&b.release));
+
+            let mut runs: Vec<ReleaseRun> = runs
+                .into_iter()
+                .map(|(release, generated_by_run)| ReleaseRun {
+                    schedule: schedule.get(&release).cloned(),
+                    release,
+                    generated_by_run,
+                })
+                .collect();
+            runs.sort_by(|a, b| a.release.cmp(&b.release));
+
+            let mut failures: Vec<ReleaseFailure> = failures
+                .into_iter()
+                .map(|(release, failure)| ReleaseFailure { release, failure })
+                .collect();
+            failures.sort_by(|a, b| a.release.cmp(&b.release));
+
+            let mut alerts: Vec<Alert> = alerts.into_values().flatten().collect();
+            alerts.sort_by(|a, b| (&a.release, &a.rule).cmp(&(&b.release, &b.rule)));
+
+            json_response(&ServiceStatus {
+                last_refreshed,
+                cycle,
+                paused,
+                degraded,
+                readiness,
+                account_churn,
+                frozen,
+                runs,
+                failures,
+                alerts,
+            })
+        }),
+    );
+
+    let matrix_state = state.clone();
+    let router = router.route(
+        "/matrix",
+        get(move || async move {
+            #[derive(Serialize)]
+            struct PlannedEntry {
+                key: String,
+                #[serde(flatten)]
+                entry: MatrixEntry,
+                running: bool,
+                carried_over: bool,
+                // not set while the release is being checked, or while a refresh cycle is running
+                next_run: Option<DateTime<Utc>>,
+            }
+
+            let guard = read_lock(&matrix_state);
+            let state = &*guard;
+
+            let matrix = state
+                .config
+                .to_matrix()
+                .map_err(|error| AppError::internal(format!("Invalid configuration: {}", error)))?;
+
+            let entries: Vec<PlannedEntry> = matrix
+                .into_iter()
+                .map(|entry| {
+                    let key = entry.key();
+                    let running = state.progress.contains_key(&key);
+
+                    // releases are only checked in refresh cycles, and only once they are due (with adaptive intervals)
+                    let next_run = match (state.next_cycle, &state.config.repochecker.adaptive) {
+                        _ if running => None,
+                        (Some(next_cycle), Some(adaptive)) => match state.schedule.get(&key) {
+                            Some(schedule) if !state.carried_over.contains(&key) => {
+                                Some(first_cycle_after(next_cycle, schedule.next_run, adaptive.min_interval))
+                            },
+                            _ => Some(next_cycle),
+                        },
+                        (next_cycle, _) => next_cycle,
+                    };
+
+                    PlannedEntry {
+                        carried_over: state.carried_over.contains(&key),
+                        key,
+                        entry,
+                        running,
+                        next_run,
+                    }
+                })
+                .collect();
+
+            json_response(&entries)
+        }),
+    );
+
+    /// Shows the status of one release, including the progress of a check that is currently running.
+    fn release_status_response(state: &GlobalState, key: &str) -> Result<Response, AppError> {
+        #[derive(Serialize)]
+        struct ReleaseStatus {
+            release: String,
+            running: bool,
+            progress: Option<WorkerProgress>,
+            frozen_at: Option<DateTime<Utc>>,
+            generated_by_run: Option<RunInfo>,
+            schedule: Option<Schedule>,
+            failure: Option<RunFailure>,
+            alerts: Vec<Alert>,
+        }
+
+        let guard = read_lock(state);
+        let matrix = guard
+            .config
+            .to_matrix()
+            .map_err(|error| AppError::internal(format!("Invalid configuration: {}", error)))?;
+
+        if !matrix.iter().any(|entry| entry.key() == key) {
+            return Err(AppError::not_found("This release does not exist."));
+        }
+
+        let progress = guard.progress.get(key).cloned();
+
+        json_response(&ReleaseStatus {
+            release: key.to_string(),
+            running: progress.is_some(),
+            progress,
+            frozen_at: guard.frozen.get(key).copied(),
+            generated_by_run: guard.runs.get(key).cloned(),
+            schedule: guard.schedule.get(key).cloned(),
+            failure: guard.failures.get(key).cloned(),
+            alerts: guard.alerts.get(key).cloned().unwrap_or_default(),
+        })
+    }
+
+    let release_status_state = state.clone();
+    let router = router.route(
+        "/status/:release",
+        get(move |release: Path<String>| async move { release_status_response(&release_status_state, &release.0) }),
+    );
+
+    let product_status_state = state.clone();
+    let router = router.route(
+        "/products/:product/status/:release",
+        get(move |Path((product, release)): Path<(String, String)>| async move {
+            release_status_response(&product_status_state, &release_key(Some(&product), &release))
+        }),
+    );
+
+    let pause_state = state.clone();
+    let router = router.route(
+        "/admin/pause",
+        post(move |headers: HeaderMap| async move {
+            authorize(&pause_state, &headers)?;
+
+            write_lock(&pause_state).paused = true;
+            info!("Checks were paused via the administrative API.");
+
+            Ok::<_, AppError>(text_response(String::from("Paused.")))
+        }),
+    );
+
+    let resume_state = state.clone();
+    let router = router.route(
+        "/admin/resume",
+        post(move |headers: HeaderMap| async move {
+            authorize(&resume_state, &headers)?;
+
+            write_lock(&resume_state).paused = false;
+            info!("Checks were resumed via the administrative API.");
+
+            Ok::<_, AppError>(text_response(String::from("Resumed.")))
+        }),
+    );
+
+    let rerun_state = state.clone();
+    let router = router.route(
+        "/admin/rerun/*release",
+        post(
+            move |headers: HeaderMap, Path(release): Path<String>, body: String| async move {
+                authorize(&rerun_state, &headers)?;
+
+                let mut options: RerunOptions = if body.trim().is_empty() {
+                    RerunOptions::default()
+                } else {
+                    match serde_json::from_str(&body) {
+                        Ok(options) => options,
+                        Err(error) => return Err(AppError::bad_request(format!("Invalid options: {}", error))),
+                    }
+                };
+
+                let matrix = read_lock(&rerun_state)
+                    .config
+                    .to_matrix()
+                    .map_err(|error| AppError::internal(format!("Invalid configuration: {}", error)))?;
+
+                let entry = match matrix.into_iter().find(|entry| entry.key() == release) {
+                    Some(entry) => entry,
+                    None => return Err(AppError::not_found("This release does not exist.")),
+                };
+
+                // secondary content architectures are rerun as part of their primary architecture
+                if let Some(arch) = &options.arch {
+                    match entry.checked_arch(arch) {
+                        Some(checked) => options.arch = Some(checked.name.clone()),
+                        None => {
+                            return Err(AppError::bad_request(format!(
+                                "Architecture {} is not checked for this release.",
+                                arch
+                            )))
+                        },
+                    }
+                }
+
+                let cancel = CancellationToken::new();
+                let workers = {
+                    let mut guard = write_lock(&rerun_state);
+                    if guard.frozen.contains_key(&release) {
+                        return Err(AppError::bad_request("Data of archived releases is not regenerated."));
+                    }
+                    if entry.external {
+                        return Err(AppError::bad_request("Data of external releases is only imported."));
+                    }
+                    if !guard.checking.insert(release.clone()) {
+                        return Err(AppError::bad_request("This release is already being checked."));
+                    }
+                    guard.reruns.insert(release.clone(), cancel.clone());
+                    guard.workers.clone()
+                };
+
+                info!(
+                    "Rerun of {} was requested via the administrative API: {:?}",
+                    &release, &options
+                );
 
-            let body = {
-                #[derive(Serialize)]
-                struct ServiceStatus {
-                    last_refreshed: Option<String>,
-                }
+                // reruns are checked on the same threads as refresh cycles
+                let future = rerun(rerun_state.clone(), entry, options, cancel);
+                match workers {
+                    Some(workers) => workers.spawn(future),
+                    None => tokio::spawn(future),
+                };
+
+                Ok::<_, AppError>(text_response(format!("Started checking {}.", release)))
+            },
+        ),
+    );
+
+    let abort_state = state.clone();
+    let router = router.route(
+        "/admin/abort/*release",
+        post(move |headers: HeaderMap, Path(release): Path<String>| async move {
+            authorize(&abort_state, &headers)?;
 
-                let last_refreshed = value.map(|dt| dt.to_string());
+            match read_lock(&abort_state).reruns.get(&release) {
+                Some(cancel) => cancel.cancel(),
+                None => return Err(AppError::not_found("This release is not being rerun.")),
+            }
+
+            info!("Rerun of {} was aborted via the administrative API.", &release);
+            Ok::<_, AppError>(text_response(format!("Aborted checking {}.", release)))
+        }),
+    );
 
-                serde_json::to_string_pretty(&ServiceStatus { last_refreshed }).expect("Failed to serialize into JSON.")
+    let preview_state = state.clone();
+    let router = router.route(
+        "/admin/config/preview",
+        post(move |headers: HeaderMap, body: String| async move {
+            authorize(&preview_state, &headers)?;
+
+            let config: Config = match basic_toml::from_str(&body) {
+                Ok(config) => config,
+                Err(error) => return Err(AppError::bad_request(format!("Invalid configuration: {}", error))),
             };
 
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                CONTENT_TYPE,
-                "application/json"
-                    .parse()
-                    .expect("Failed to parse hardcoded header value."),
-            );
+            let new_matrix = match config.validate().and_then(|_| config.to_matrix()) {
+                Ok(matrix) => matrix,
+                Err(error) => return Err(AppError::bad_request(format!("Invalid configuration: {}", error))),
+            };
 
-            (StatusCode::OK, headers, body)
+            let old_matrix = read_lock(&preview_state)
+                .config
+                .to_matrix()
+                .map_err(|error| AppError::internal(format!("Invalid configuration: {}", error)))?;
+
+            json_response(&diff_matrix(&old_matrix, &new_matrix))
         }),
     );
 
-    // add custom 404 handler
-    let router = router.fallback(get(move || async move {
-        (
-            StatusCode::NOT_FOUND,
-            HeaderMap::new(),
-            String::from("This page does not exist."),
+    let snapshot_state = state.clone();
+    let router = router.route(
+        "/admin/snapshot",
+        get(move |headers: HeaderMap| async move {
+            authorize(&snapshot_state, &headers)?;
+
+            let snapshot = StateSnapshot::from_state(&read_lock(&snapshot_state)).map_err(AppError::internal)?;
+            let body = snapshot.encode().map_err(AppError::internal)?;
+
+            Ok::<_, AppError>(([(CONTENT_TYPE, HeaderValue::from_static("application/gzip"))], body).into_response())
+        }),
+    );
+
+    let restore_state = state.clone();
+    let router = router.route(
+        "/admin/restore",
+        post(move |headers: HeaderMap, body: Bytes| async move {
+            authorize(&restore_state, &headers)?;
+
+            let snapshot = StateSnapshot::decode(&body).map_err(AppError::bad_request)?;
+
+            // the data is persisted (and signed) before the state is replaced, without holding the lock
+            let config = read_lock(&restore_state).config.clone();
+            let restored = snapshot.persist(&config).await.map_err(AppError::internal)?;
+            snapshot.apply(&mut write_lock(&restore_state), &restored);
+            persist_schedule(&restore_state);
+
+            info!("Restored state for {} via the administrative API.", restored.join(", "));
+            json_response(&restored)
+        })
+        .layer(DefaultBodyLimit::max(max_body_bytes)),
+    );
+
+    let import_state = state.clone();
+    let router = router.route(
+        "/admin/import/*release",
+        post(
+            move |headers: HeaderMap, Path(release): Path<String>, body: Bytes| async move {
+                authorize(&import_state, &headers)?;
+
+                let items: Vec<BrokenItem> = match serde_json::from_slice(&body) {
+                    Ok(items) => items,
+                    Err(error) => return Err(AppError::bad_request(format!("Invalid data: {}", error))),
+                };
+
+                let entry = {
+                    let guard = read_lock(&import_state);
+                    match external_entry(&guard.config, &release) {
+                        Ok(entry) => entry,
+                        Err(error) => return Err(AppError::not_found(error)),
+                    }
+                };
+
+                validate_import(&entry, &items).map_err(AppError::bad_request)?;
+
+                // imported data is processed like the results of checking a release on this instance
+                let count = items.len();
+                store_results(&import_state, &entry, Uuid::new_v4(), items).await;
+                write_lock(&import_state).imported.insert(release.clone(), Utc::now());
+
+                info!(
+                    "Imported {} broken packages for {} via the administrative API.",
+                    count, &release
+                );
+
+                #[derive(Serialize)]
+                struct Imported {
+                    release: String,
+                    items: usize,
+                }
+
+                json_response(&Imported { release, items: count })
+            },
         )
-    }));
+        .layer(DefaultBodyLimit::max(max_body_bytes)),
+    );
+
+    let metrics_state = state.clone();
+    let router = router.route(
+        "/metrics",
+        get(move || async move {
+            let runs = read_lock(&metrics_state).runs.clone();
+            (
+                [(CONTENT_TYPE, HeaderValue::from_static("text/plain; version=0.0.4"))],
+                prometheus_metrics(&runs),
+            )
+                .into_response()
+        }),
+    );
+
+    // add custom 404 handler
+    router.fallback(get(
+        move || async move { AppError::not_found("This page does not exist.") },
+    ))
+}
+
+pub(crate) async fn server(state: GlobalState) {
+    let router = router(state);
 
     let address: SocketAddr = "127.0.0.1:3030".parse().expect("Failed to parse server address.");
     info!("Listening on http://{} ...", &address);
@@ -415,3 +3716,959 @@ pub(crate) async fn server(state: GlobalState) {
         .await
         .expect("Server failure.");
 }
+
+// the server is tested end-to-end against fixture state, which is part of the test backend
+#[cfg(all(test, feature = "test-backend"))]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    const CONFIG: &str = r#"
+[repochecker]
+interval = 4
+
+[[release]]
+name = "rawhide"
+type = "rawhide"
+arches = ["x86_64", "aarch64"]
+archived = false
+
+[[release]]
+name = "41"
+type = "stable"
+arches = ["x86_64", "aarch64"]
+archived = false
+
+[repos]
+stable = ["fedora"]
+updates = ["updates"]
+testing = ["updates-testing"]
+rawhide = ["rawhide"]
+
+[[product]]
+name = "epel"
+overrides = "overrides-epel.json"
+
+[product.repos]
+stable = ["epel"]
+
+[[product.release]]
+name = "9"
+type = "prerelease"
+arches = ["x86_64"]
+archived = false
+"#;
+
+    fn item(package: &str, admin: &str, arch: &str, broken: &[&str]) -> BrokenItem {
+        BrokenItem {
+            arch: arch.to_string(),
+            admin: admin.to_string(),
+            maintainers: vec![admin.to_string()],
+            repo_arch: arch.to_string(),
+            since: Some(Utc::now()),
+            ..crate::data::broken_item(package, broken)
+        }
+    }
+
+    /// Builds global state with fixture data for some releases, without querying any external services.
+    fn fixture_state() -> GlobalState {
+        let config: Config = basic_toml::from_str(CONFIG).unwrap();
+
+        let empty = || Overrides::from_values(HashMap::new());
+
+        let product_overrides = HashMap::from([(String::from("epel"), Arc::new(RwLock::new(empty())))]);
+
+        let mut state = State::init(config, empty(), product_overrides, HashMap::new(), HashMap::new(), true);
+
+        state.values.insert(
+            String::from("rawhide"),
+            Arc::new(vec![
+                item("foo", "alice", "x86_64", &["libbar.so.2()(64bit)"]),
+                item("baz", "bob", "aarch64", &["perl(Qux)"]),
+            ]),
+        );
+        state.values.insert(
+            String::from("41"),
+            Arc::new(vec![item("baz", "bob", "aarch64", &["perl(Qux)"])]),
+        );
+        state.values.insert(
+            String::from("epel/9"),
+            Arc::new(vec![item("foo", "alice", "x86_64", &["python3dist(bar)"])]),
+        );
+        state.excluded.insert(
+            String::from("rawhide"),
+            BTreeMap::from([
+                (String::from("x86_64"), BTreeSet::new()),
+                (String::from("aarch64"), BTreeSet::new()),
+            ]),
+        );
+
+        Arc::new(RwLock::new(state))
+    }
+
+    async fn request(uri: &str) -> (StatusCode, String) {
+        let response = router(fixture_state())
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    async fn request_json(uri: &str) -> serde_json::Value {
+        let (status, body) = request(uri).await;
+        assert_eq!(status, StatusCode::OK, "unexpected status for {}: {}", uri, body);
+        serde_json::from_str(&body).unwrap()
+    }
+
+    #[test]
+    fn annotate_results() {
+        let since = Utc::now() - chrono::Duration::days(3);
+        let old = vec![BrokenItem {
+            since: Some(since),
+            ..item("foo", "alice", "x86_64", &["libfoo.so.1()(64bit)"])
+        }];
+
+        let persisted = Utc::now() - chrono::Duration::days(7);
+        let bar = BrokenItem {
+            since: None,
+            ..item("bar", "bob", "x86_64", &["libbar.so.1()(64bit)"])
+        };
+        let since_index = SinceIndex::from([(bar.since_key(), persisted)]);
+
+        let mut items = vec![
+            BrokenItem {
+                since: None,
+                ..item("foo", "carol", "x86_64", &["libfoo.so.1()(64bit)"])
+            },
+            bar,
+        ];
+
+        let run = Uuid::new_v4();
+        let inactive = HashSet::from([String::from("bob")]);
+        let index = super::annotate_results(
+            &mut items,
+            Some(&old),
+            &since_index,
+            &UrlConfig::default(),
+            &inactive,
+            run,
+        );
+
+        // timestamps are carried over from the previous data, or from persisted timestamps
+        assert_eq!(items[0].since, Some(since));
+        assert_eq!(items[0].previous_admin.as_deref(), Some("alice"));
+        assert_eq!(items[1].since, Some(persisted));
+        assert!(items[1].admin_inactive);
+        assert!(items[0].distgit_url.is_some());
+        assert_eq!(items[0].generated_by_run, Some(run));
+        assert_eq!(index.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn fake_data_is_not_persisted() {
+        let state = fixture_state();
+        let config: Config = basic_toml::from_str(CONFIG).unwrap();
+
+        let mut entry = config.to_matrix().unwrap().remove(1);
+        entry.release = format!("fake-{}", Uuid::new_v4());
+        entry.archived = true;
+
+        let pretty = entry.key();
+        let json_path = get_json_path(None, &entry.release, false);
+        let frozen_path = get_frozen_path(None, &entry.release, false);
+
+        let items = vec![item("foo", "alice", "x86_64", &["libfoo.so.1()(64bit)"])];
+        assert!(store_results(&state, &entry, Uuid::new_v4(), items).await);
+        freeze(&state, &pretty, &json_path, &frozen_path);
+
+        let guard = read_lock(&state);
+        assert_eq!(guard.values[&pretty].len(), 1);
+        assert!(!guard.frozen.contains_key(&pretty));
+
+        assert!(!json_path.exists());
+        assert!(!frozen_path.exists());
+        assert!(!get_since_path(None, &entry.release, false).exists());
+    }
+
+    #[tokio::test]
+    async fn archived_lazy_loading() {
+        let path = std::env::temp_dir().join(format!("repochecker-{}.json.gz", Uuid::new_v4()));
+        let frozen = FrozenData {
+            frozen_at: Utc::now(),
+            items: vec![item("foo", "alice", "x86_64", &["libarchived.so.1()(64bit)"])],
+        };
+        write_frozen_to_file(&path, &frozen).unwrap();
+
+        let state = fixture_state();
+        write_lock(&state).archived.insert(
+            String::from("39"),
+            ArchivedRelease {
+                path: path.clone(),
+                items: 1,
+                critpath: 0,
+            },
+        );
+        assert!(!read_lock(&state).values.contains_key("39"));
+
+        let response = router(state.clone())
+            .oneshot(Request::builder().uri("/data/39").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read_lock(&state).values["39"].len(), 1);
+
+        // data that is dropped from memory right after it was loaded is still returned
+        {
+            let mut guard = write_lock(&state);
+            guard.config.repochecker.archived_cache_size = 0;
+            guard.values.remove("39");
+        }
+        let response = router(state.clone())
+            .oneshot(Request::builder().uri("/data/39").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let values: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(values.len(), 1);
+        assert!(!read_lock(&state).values.contains_key("39"));
+
+        // reports across all releases include archived releases whose data is not loaded
+        let response = router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/search?q=libarchived")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let results: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(results["total"], 1);
+        assert_eq!(results["results"][0]["broken"][0], "libarchived.so.1()(64bit)");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn confirm_items() {
+        let published = vec![item("foo", "alice", "x86_64", &["libfoo.so.1()(64bit)"])];
+        let items = vec![
+            item("foo", "alice", "x86_64", &["libfoo.so.1()(64bit)"]),
+            item("bar", "bob", "x86_64", &["libbar.so.1()(64bit)"]),
+        ];
+
+        let (confirmed, streaks) = super::confirm_items(&items, &published, &HashMap::new(), 2);
+        assert_eq!(confirmed.len(), 1);
+        assert_eq!(confirmed[0].package, "foo");
+
+        // bar is published once it was broken in two consecutive runs
+        let (confirmed, streaks) = super::confirm_items(&items, &confirmed, &streaks, 2);
+        assert_eq!(confirmed.len(), 2);
+        assert_eq!(streaks["bar/fedora/x86_64"], 2);
+    }
+
+    #[test]
+    fn progress_hook() {
+        let state = fixture_state();
+        let matrix = read_lock(&state).config.to_matrix().unwrap();
+        let entry = matrix.iter().find(|entry| entry.key() == "rawhide").unwrap();
+
+        let hook = super::progress_hook(state.clone(), entry);
+        hook(ProgressStage::Makecache, Some("x86_64"));
+        hook(ProgressStage::Closure, Some("x86_64"));
+
+        let progress = read_lock(&state).progress["rawhide"].clone();
+        assert_eq!(progress.stage, ProgressStage::Closure);
+        assert_eq!(progress.arch.as_deref(), Some("x86_64"));
+        assert_eq!((progress.step, progress.steps), (2, 7));
+
+        hook(ProgressStage::PostProcessing, None);
+        let progress = read_lock(&state).progress["rawhide"].clone();
+        assert_eq!(progress.step, progress.steps);
+    }
+
+    #[tokio::test]
+    async fn worker_skips_claimed_release() {
+        let state = fixture_state();
+        let matrix = read_lock(&state).config.to_matrix().unwrap();
+        let entry = matrix.into_iter().find(|entry| entry.key() == "rawhide").unwrap();
+
+        // a release that is claimed by a rerun is skipped by refresh cycles, and stays claimed
+        write_lock(&state).checking.insert(String::from("rawhide"));
+        super::worker(state.clone(), entry, Uuid::new_v4(), CancellationToken::new()).await;
+
+        let guard = read_lock(&state);
+        assert!(guard.checking.contains("rawhide"));
+        assert!(!guard.runs.contains_key("rawhide"));
+        assert!(!guard.progress.contains_key("rawhide"));
+    }
+
+    #[tokio::test]
+    async fn archived_releases() {
+        let state = fixture_state();
+        let find = || {
+            let matrix = read_lock(&state).config.to_matrix().unwrap();
+            matrix.into_iter().find(|entry| entry.key() == "41").unwrap()
+        };
+
+        // archived releases that could not be frozen are not checked again in refresh cycles
+        let mut archived = find();
+        archived.archived = true;
+        write_lock(&state).freeze_failed.insert(String::from("41"));
+        super::worker(state.clone(), archived, Uuid::new_v4(), CancellationToken::new()).await;
+
+        assert!(!read_lock(&state).runs.contains_key("41"));
+        assert!(!read_lock(&state).checking.contains("41"));
+
+        // releases that are no longer archived are unfrozen
+        {
+            let mut guard = write_lock(&state);
+            guard.frozen.insert(String::from("41"), Utc::now());
+            guard.archived.insert(
+                String::from("41"),
+                ArchivedRelease {
+                    path: PathBuf::new(),
+                    items: 0,
+                    critpath: 0,
+                },
+            );
+            guard.values.insert(String::from("41"), Arc::new(Vec::new()));
+        }
+
+        assert!(!super::load_cached(&state, &find()));
+
+        let guard = read_lock(&state);
+        assert!(!guard.frozen.contains_key("41"));
+        assert!(!guard.archived.contains_key("41"));
+        assert!(!guard.freeze_failed.contains("41"));
+        assert!(guard.values.contains_key("41"));
+    }
+
+    #[test]
+    fn find_data_files() {
+        let path = std::env::temp_dir().join(format!("repochecker-data-{}", Uuid::new_v4()));
+        for directory in ["epel", "since", "history/rawhide"] {
+            std::fs::create_dir_all(path.join(directory)).unwrap();
+        }
+        for file in [
+            "rawhide.json",
+            "40.json.gz",
+            "index.html",
+            "epel/9.json",
+            "since/rawhide.json",
+        ] {
+            std::fs::write(path.join(file), "[]").unwrap();
+        }
+
+        let files = super::find_data_files(&path);
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(
+            files,
+            vec![
+                path.join("40.json.gz"),
+                path.join("epel/9.json"),
+                path.join("rawhide.json")
+            ]
+        );
+    }
+
+    #[test]
+    fn evict_archived() {
+        let state = fixture_state();
+        let mut guard = write_lock(&state);
+        let now = Instant::now();
+
+        for (key, last_access) in [
+            ("38", now - Duration::from_secs(10)),
+            ("39", now),
+            ("40", now - Duration::from_secs(600)),
+        ] {
+            guard.values.insert(key.to_string(), Arc::new(Vec::new()));
+            guard.archived.insert(
+                key.to_string(),
+                ArchivedRelease {
+                    path: PathBuf::new(),
+                    items: 0,
+                    critpath: 0,
+                },
+            );
+            guard
+                .archived_access
+                .get_mut()
+                .unwrap()
+                .insert(key.to_string(), last_access);
+        }
+
+        // the least recently used release is dropped first
+        assert_eq!(guard.evict_archived(2, Duration::from_secs(3600), now), vec!["40"]);
+        // releases that were not requested recently are dropped regardless of the capacity
+        assert_eq!(guard.evict_archived(2, Duration::from_secs(5), now), vec!["38"]);
+
+        assert!(guard.values.contains_key("39"));
+        assert!(!guard.values.contains_key("40"));
+        assert!(guard.values.contains_key("rawhide"));
+    }
+
+    #[tokio::test]
+    async fn admin_disabled() {
+        let response = router(fixture_state())
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/admin/pause")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn admin_endpoints() {
+        let token_file = std::env::temp_dir().join(format!("repochecker-token-{}", Uuid::new_v4()));
+        std::fs::write(&token_file, "secret").unwrap();
+
+        let state = fixture_state();
+        write_lock(&state).config.admin = Some(crate::config::AdminConfig {
+            token_file: token_file.to_string_lossy().to_string(),
+            max_body_size: 512.0,
+        });
+
+        let post = |uri: &str, body: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("Authorization", "Bearer secret")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        for (uri, body, status) in [
+            ("/admin/rerun/40", "", StatusCode::NOT_FOUND),
+            ("/admin/rerun/41", "{\"arch\": \"ppc64le\"}", StatusCode::BAD_REQUEST),
+            ("/admin/rerun/epel/9", "{\"verbose\": 1}", StatusCode::BAD_REQUEST),
+            ("/admin/abort/epel/9", "", StatusCode::NOT_FOUND),
+        ] {
+            let response = router(state.clone()).oneshot(post(uri, body)).await.unwrap();
+            assert_eq!(response.status(), status, "unexpected status for {}", uri);
+        }
+
+        let response = router(state.clone()).oneshot(post("/admin/pause", "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(read_lock(&state).paused);
+
+        let response = router(state.clone()).oneshot(post("/admin/resume", "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!read_lock(&state).paused);
+
+        let response = router(state.clone())
+            .oneshot(post("/admin/config/preview", CONFIG))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = router(state.clone())
+            .oneshot(post("/admin/config/preview", "[repochecker"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri("/admin/snapshot")
+                    .header("Authorization", "Bearer secret")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/gzip");
+
+        // releases that are already being checked are not checked again at the same time
+        {
+            let mut guard = write_lock(&state);
+            guard.checking.insert(String::from("epel/9"));
+            guard.reruns.insert(String::from("epel/9"), CancellationToken::new());
+        }
+        let response = router(state.clone())
+            .oneshot(post("/admin/rerun/epel/9", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = router(state.clone())
+            .oneshot(post("/admin/abort/epel/9", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(read_lock(&state).reruns["epel/9"].is_cancelled());
+
+        // data can only be imported for external releases, and must match their configuration
+        let admin = read_lock(&state).config.admin.clone();
+        write_lock(&state).config = basic_toml::from_str(&format!(
+            "{}{}",
+            CONFIG,
+            r#"
+[[product]]
+name = "repochecker-import-test"
+overrides = "overrides-import-test.json"
+
+[product.repos]
+stable = ["external"]
+
+[[product.release]]
+name = "1"
+type = "prerelease"
+arches = ["x86_64"]
+archived = false
+external = true
+"#
+        ))
+        .unwrap();
+        write_lock(&state).config.admin = admin;
+
+        let data =
+            |arch: &str| serde_json::to_string(&[item("foo", "alice", arch, &["libbar.so.1()(64bit)"])]).unwrap();
+
+        for (uri, body, status) in [
+            ("/admin/import/40", data("x86_64"), StatusCode::NOT_FOUND),
+            ("/admin/import/rawhide", data("x86_64"), StatusCode::NOT_FOUND),
+            (
+                "/admin/import/repochecker-import-test/1",
+                String::from("{}"),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/admin/import/repochecker-import-test/1",
+                data("s390x"),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                "/admin/import/repochecker-import-test/1",
+                data("x86_64"),
+                StatusCode::OK,
+            ),
+        ] {
+            let response = router(state.clone()).oneshot(post(uri, &body)).await.unwrap();
+            assert_eq!(response.status(), status, "unexpected status for {}", uri);
+        }
+
+        {
+            let guard = read_lock(&state);
+            let values = &guard.values["repochecker-import-test/1"];
+            assert_eq!(values.len(), 1);
+            assert!(values[0].since.is_some());
+            assert!(guard.imported.contains_key("repochecker-import-test/1"));
+            assert!(guard.date_refreshed.is_none());
+        }
+
+        // imported data is only kept in memory when using fake data
+        assert!(!crate::utils::get_data_path().join("repochecker-import-test").exists());
+
+        // snapshots larger than the default limit for request bodies are accepted (and rejected as invalid here)
+        let response = router(state.clone())
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/admin/restore")
+                    .header("Authorization", "Bearer secret")
+                    .body(Body::from(vec![0u8; 4 * 1024 * 1024]))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        std::fs::remove_file(&token_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn routes() {
+        let (status, body) = request("/").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("rawhide"));
+
+        let data = request_json("/data/rawhide").await;
+        assert_eq!(data.as_array().unwrap().len(), 2);
+        assert!(data[0]["broken"].is_array());
+        assert!(data[0]["categories"].is_array());
+
+        let (status, body) = request("/data/rawhide?format=ndjson").await;
+        assert_eq!(status, StatusCode::OK);
+        let lines: Vec<serde_json::Value> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["package"], data[0]["package"]);
+
+        let (status, _) = request("/data/rawhide?format=xml").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // no quarantine is configured, so the unfiltered data is the published data
+        let raw = request_json("/data/rawhide?raw=true").await;
+        assert_eq!(raw.as_array().unwrap().len(), 2);
+
+        let schema = request_json("/schema/config").await;
+        assert_eq!(schema["title"], "Configuration");
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(
+            schema["$defs"]["RepoCheckerConfig"]["required"],
+            serde_json::json!(["interval"])
+        );
+        assert_eq!(schema["$defs"]["RepoCheckerConfig"]["additionalProperties"], false);
+
+        let schema = request_json("/schema/overrides").await;
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["$defs"]["AnnotatedOverride"]["additionalProperties"], false);
+
+        let history = request_json("/data/rawhide/history").await;
+        assert!(history.as_array().unwrap().is_empty());
+
+        let (status, _) = request("/data/rawhide/history/latest").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let dataset = request_json("/api/v1/data/rawhide").await;
+        assert_eq!(dataset["release"], "rawhide");
+        assert_eq!(dataset["version"], VERSION);
+        assert_eq!(dataset["items"].as_array().unwrap().len(), 2);
+
+        let dataset = request_json("/api/v1/products/epel/data/9").await;
+        assert_eq!(dataset["release"], "epel/9");
+
+        let product = request_json("/products/epel/data/9").await;
+        assert_eq!(product[0]["package"], "foo");
+
+        let (status, body) = request("/releases/rawhide?per_page=1&admin=&broken=perl").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("1 of 2 broken packages"));
+        assert!(body.contains("<td>baz</td>"));
+        assert!(!body.contains("<td>foo</td>"));
+
+        let (status, body) = request("/releases/rawhide?per_page=1&page=2").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("Page 2 of 2"));
+        assert!(body.contains("href=\"?page=1&amp;per_page=1\""));
+
+        let response = router(fixture_state())
+            .oneshot(Request::builder().uri("/go/rawhide/baz").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(response.headers()["location"], "/releases/rawhide?page=1#src-baz");
+
+        let (_, body) = request("/releases/rawhide").await;
+        assert!(body.contains("<tr id=\"src-baz\">"));
+
+        let diff = request_json("/data/rawhide/arch-diff").await;
+        assert_eq!(diff.as_array().unwrap().len(), 2);
+        assert!(diff[0]["broken_on"].is_array());
+        assert!(diff[0]["fine_on"].is_array());
+
+        let critpath = request_json("/data/rawhide/critpath").await;
+        assert!(critpath.as_array().unwrap().is_empty());
+
+        let combined = request_json("/data/41/combined").await;
+        assert_eq!(combined.as_array().unwrap().len(), 1);
+        assert_eq!(combined[0]["package"], "baz");
+        assert_eq!(combined[0]["testing_only"], false);
+
+        let excluded = request_json("/debug/rawhide/excluded").await;
+        assert!(excluded["x86_64"].is_array());
+
+        let feed = request_json("/feeds/maintainer/alice.json").await;
+        assert_eq!(feed["maintainer"], "alice");
+        assert_eq!(feed["items"].as_array().unwrap().len(), 2);
+        // email addresses are not included in public responses
+        assert!(feed.get("email").is_none());
+
+        let category = request_json("/reports/rawhide/category/perl").await;
+        assert_eq!(category.as_array().unwrap().len(), 1);
+        assert_eq!(category[0]["categories"][0], "perl");
+
+        let sonames = request_json("/reports/rawhide/soname-bumps").await;
+        assert_eq!(sonames[0]["library"], "libbar.so");
+
+        let regressions = request_json("/reports/regressions").await;
+        assert_eq!(regressions[0]["newer"], "rawhide");
+        assert_eq!(regressions[0]["older"], "41");
+        assert_eq!(regressions[0]["regressions"].as_array().unwrap().len(), 1);
+
+        // reports can also be streamed as JSON Lines
+        let (status, body) = request("/reports/regressions?format=ndjson").await;
+        assert_eq!(status, StatusCode::OK);
+        let lines: Vec<serde_json::Value> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), regressions.as_array().unwrap().len());
+        assert_eq!(lines[0]["newer"], "rawhide");
+
+        let testing = request_json("/reports/new-in-testing").await;
+        assert!(testing.as_array().unwrap().is_empty());
+
+        let provides = request_json("/provides/libbar.so.2()(64bit)?release=rawhide").await;
+        assert_eq!(provides[0]["release"], "rawhide");
+        assert_eq!(provides[0]["broken"][0]["package"], "foo");
+
+        let results = request_json("/search?q=libbar").await;
+        assert_eq!(results["total"], 1);
+        assert_eq!(results["results"][0]["package"], "foo");
+        assert_eq!(results["results"][0]["matched"][0], "broken");
+
+        let results = request_json("/search?q=o&limit=1&page=2").await;
+        assert_eq!(results["page"], 2);
+        assert_eq!(results["results"].as_array().unwrap().len(), 1);
+
+        let (status, _) = request("/search?q=").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let inactive = request_json("/reports/inactive").await;
+        assert!(inactive.as_array().unwrap().is_empty());
+
+        let (status, body) = request("/config").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("[repochecker]"));
+
+        assert!(request_json("/overrides").await.is_object());
+        assert!(request_json("/overrides/effective/41/x86_64").await.is_object());
+        assert!(request_json("/stats").await.is_array());
+        assert!(request_json("/stats/packages").await.is_array());
+
+        let version = request_json("/version").await;
+        assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+
+        let status = request_json("/status").await;
+        assert!(status["frozen"].is_array());
+        assert!(status["runs"].is_array());
+        assert!(status["failures"].is_array());
+        assert!(status["alerts"].is_array());
+
+        let status = request_json("/status/rawhide").await;
+        assert_eq!(status["release"], "rawhide");
+        assert_eq!(status["running"], false);
+        assert!(status["progress"].is_null());
+    }
+
+    #[tokio::test]
+    async fn product_routes() {
+        let (status, body) = request("/products/epel/releases/9").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<td>foo</td>"));
+
+        let response = router(fixture_state())
+            .oneshot(
+                Request::builder()
+                    .uri("/products/epel/go/9/foo")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(
+            response.headers()["location"],
+            "/products/epel/releases/9?page=1#src-foo"
+        );
+
+        let critpath = request_json("/products/epel/data/9/critpath").await;
+        assert!(critpath.as_array().unwrap().is_empty());
+
+        let history = request_json("/products/epel/data/9/history").await;
+        assert!(history.as_array().unwrap().is_empty());
+
+        let status = request_json("/products/epel/status/9").await;
+        assert_eq!(status["release"], "epel/9");
+
+        for uri in [
+            "/products/epel/data/9/history/6f1d1c1e-0d1a-4a7e-9a4b-2c3d4e5f6a7b",
+            "/products/epel/data/9/signature",
+            // no generations are kept, so changes can not be determined
+            "/products/epel/data/9/changes?since=2024-05-01T00:00Z",
+            "/products/epel/data/8/changes?since=2024-05-01",
+        ] {
+            let (status, _) = request(uri).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "unexpected status for {}", uri);
+        }
+
+        let (status, _) = request("/products/epel/data/9/changes?since=yesterday").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn effective_overrides() {
+        let state = fixture_state();
+        {
+            let guard = read_lock(&state);
+            *write_lock(&guard.overrides) =
+                Overrides::from_values(serde_json::from_str(r#"{"9": {"all": {"perl(Foo)": ["foo"]}}}"#).unwrap());
+            *write_lock(&guard.product_overrides["epel"]) =
+                Overrides::from_values(serde_json::from_str(r#"{"9": {"all": {"perl(Bar)": ["bar"]}}}"#).unwrap());
+        }
+
+        // overrides of products are used for releases of that product
+        for uri in [
+            "/overrides/effective/epel%2F9/x86_64",
+            "/products/epel/overrides/effective/9/x86_64",
+        ] {
+            let response = router(state.clone())
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "unexpected status for {}", uri);
+
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let effective: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert!(effective.get("perl(Foo)").is_none());
+            assert_eq!(effective["perl(Bar)"]["sections"][0], "9/all");
+        }
+
+        let (status, _) = request("/products/centos/overrides/effective/9/x86_64").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn report_routes() {
+        let state = fixture_state();
+        for key in ["rawhide", "epel/9"] {
+            write_lock(&state).install_checks.insert(
+                key.to_string(),
+                vec![InstallCheck {
+                    kind: InstallKind::Package,
+                    name: String::from("bash"),
+                    arch: String::from("x86_64"),
+                    installable: true,
+                    problems: Vec::new(),
+                }],
+            );
+        }
+        for uri in ["/data/rawhide/protected", "/products/epel/data/9/protected"] {
+            let response = router(state.clone())
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "unexpected status for {}", uri);
+        }
+
+        let arch = request_json("/reports/rawhide/arch/aarch64").await;
+        assert_eq!(arch.as_array().unwrap().len(), 1);
+        assert_eq!(arch[0]["package"], "baz");
+
+        // multilib architectures are checked as part of their primary architecture
+        let arch = request_json("/reports/rawhide/arch/i686").await;
+        assert!(arch.as_array().unwrap().is_empty());
+
+        for uri in ["/reports/rawhide/arch/s390x", "/reports/40/arch/x86_64"] {
+            let (status, _) = request(uri).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "unexpected status for {}", uri);
+        }
+
+        let group = request_json("/reports/rawhide/group/core").await;
+        assert!(group.as_array().unwrap().is_empty());
+
+        let (status, body) = request("/metrics").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!body.contains("rawhide"));
+    }
+
+    #[test]
+    fn cycle_releases() {
+        let config: Config = basic_toml::from_str(CONFIG).unwrap();
+        let carried_over = BTreeSet::from([String::from("epel/9")]);
+
+        let releases = super::cycle_releases(config.to_matrix().unwrap(), &carried_over, |key| key == "rawhide");
+        let releases: Vec<_> = releases
+            .iter()
+            .map(|(entry, carried)| (entry.key(), *carried))
+            .collect();
+
+        // releases that were carried over are checked even if they are not due, and are started first
+        assert_eq!(
+            releases,
+            vec![(String::from("epel/9"), true), (String::from("rawhide"), false)]
+        );
+    }
+
+    #[test]
+    fn persisted_schedule() {
+        let state = fixture_state();
+        let matrix = read_lock(&state).config.to_matrix().unwrap();
+        let path = std::env::temp_dir().join(format!("repochecker-schedule-{}/schedule.json", Uuid::new_v4()));
+
+        let schedule = Schedule {
+            interval: 2.0,
+            next_run: Utc::now(),
+        };
+        let persisted = PersistedSchedule {
+            schedule: HashMap::from([
+                (String::from("rawhide"), schedule.clone()),
+                (String::from("40"), schedule.clone()),
+            ]),
+            carried_over: BTreeSet::from([String::from("epel/9"), String::from("40")]),
+        };
+
+        write_schedule_to_file(&path, &persisted).unwrap();
+        let persisted: PersistedSchedule = read_schedule_from_file(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        // releases that are no longer configured are dropped
+        let mut guard = write_lock(&state);
+        restore_schedule(&mut guard, persisted, &matrix);
+        assert_eq!(guard.schedule.keys().collect::<Vec<_>>(), vec!["rawhide"]);
+        assert_eq!(guard.schedule["rawhide"].interval, 2.0);
+        assert_eq!(guard.carried_over, BTreeSet::from([String::from("epel/9")]));
+    }
+
+    #[test]
+    fn first_cycle_after() {
+        let next_cycle = Utc::now();
+        let hours = |hours: i64| next_cycle + chrono::Duration::hours(hours);
+
+        assert_eq!(super::first_cycle_after(next_cycle, hours(-3), 2.0), next_cycle);
+        assert_eq!(super::first_cycle_after(next_cycle, hours(3), 2.0), hours(4));
+        assert_eq!(super::first_cycle_after(next_cycle, hours(4), 2.0), hours(4));
+    }
+
+    #[tokio::test]
+    async fn matrix() {
+        let matrix = request_json("/matrix").await;
+        let entries = matrix.as_array().unwrap();
+
+        assert!(!entries.is_empty());
+        assert!(entries[0]["key"].is_string());
+        assert!(entries[0]["arches"].is_array());
+        assert_eq!(entries[0]["running"], false);
+    }
+
+    #[tokio::test]
+    async fn not_found() {
+        for uri in [
+            "/does-not-exist",
+            "/data/40",
+            "/data/40/arch-diff",
+            "/debug/41/excluded",
+            "/products/epel/data/8",
+            "/releases/40",
+            "/go/rawhide/bar",
+            "/status/40",
+            "/data/rawhide/signature",
+            "/data/40/history",
+            "/data/rawhide/history/6f1d1c1e-0d1a-4a7e-9a4b-2c3d4e5f6a7b",
+            "/feeds/maintainer/alice",
+            "/reports/40/soname-bumps",
+        ] {
+            let (status, _) = request(uri).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "unexpected status for {}", uri);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+
+use log::{debug, error};
+use tokio::process::Command;
+
+use crate::config::SigningConfig;
+use crate::data::BrokenItem;
+use crate::utils::write_json_to_file;
+
+/// Returns the path of the detached signature for the given data file.
+pub fn get_signature_path(path: &Path) -> PathBuf {
+    let mut signature = path.as_os_str().to_owned();
+    signature.push(".asc");
+    PathBuf::from(signature)
+}
+
+fn get_temporary_path(path: &Path) -> PathBuf {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    PathBuf::from(temporary)
+}
+
+/// Creates an ASCII-armored detached signature for the given data file with GnuPG.
+pub async fn sign_file(config: &SigningConfig, path: &Path) -> Result<PathBuf, String> {
+    let signature = get_signature_path(path);
+
+    let mut gpg = Command::new("gpg");
+
+    if let Some(homedir) = &config.homedir {
+        gpg.arg("--homedir").arg(homedir);
+    }
+
+    gpg.arg("--batch")
+        .arg("--yes")
+        .arg("--local-user")
+        .arg(&config.key)
+        .arg("--armor")
+        .arg("--detach-sign")
+        .arg("--output")
+        .arg(&signature)
+        .arg(path);
+
+    let output = gpg.output().await.map_err(|error| error.to_string())?;
+
+    if !output.status.success() {
+        debug!(
+            "{}",
+            match String::from_utf8(output.stderr) {
+                Ok(string) => string,
+                Err(error) => format!("Failed to decode gpg output: {}", error),
+            }
+        );
+
+        return Err(format!(
+            "gpg exited with an error code while signing {}.",
+            path.to_string_lossy()
+        ));
+    }
+
+    Ok(signature)
+}
+
+/// Writes the data of a release to the data directory, together with its detached signature (if signing is
+/// configured), and returns the path of the signature.
+///
+/// The data is written and signed under temporary names first, and both files are only moved into place once the
+/// signature was created, so a signature is never served for data it does not belong to. For frozen releases (whose
+/// data is stored in compressed form), only the signature of the uncompressed data is kept.
+pub async fn sign_release(
+    config: Option<&SigningConfig>,
+    json_path: &Path,
+    items: &[BrokenItem],
+    frozen: bool,
+) -> Result<Option<PathBuf>, String> {
+    let signature_path = get_signature_path(json_path);
+
+    if frozen && config.is_none() {
+        return remove_signature(&signature_path);
+    }
+
+    let temporary = get_temporary_path(json_path);
+    write_json_to_file(&temporary, items)?;
+
+    let signature = match config {
+        Some(config) => match sign_file(config, &temporary).await {
+            Ok(signature) => Some(signature),
+            Err(error) => {
+                error!("Failed to sign {}: {}", json_path.to_string_lossy(), error);
+                None
+            },
+        },
+        None => None,
+    };
+
+    if frozen {
+        std::fs::remove_file(&temporary).map_err(|error| format!("Failed to remove temporary data: {}", error))?;
+    } else {
+        std::fs::rename(&temporary, json_path).map_err(|error| format!("Failed to write data to disk: {}", error))?;
+    }
+
+    match signature {
+        Some(signature) => {
+            std::fs::rename(&signature, &signature_path)
+                .map_err(|error| format!("Failed to write signature to disk: {}", error))?;
+            Ok(Some(signature_path))
+        },
+        // signatures of previous data no longer match
+        None => remove_signature(&signature_path),
+    }
+}
+
+fn remove_signature(path: &Path) -> Result<Option<PathBuf>, String> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(None),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(format!("Failed to remove outdated signature: {}", error)),
+    }
+}
@@ -0,0 +1,352 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{get_config, Config};
+use crate::data::{BrokenItem, FrozenData, SinceIndex};
+use crate::overrides::{OverrideStats, Overrides};
+use crate::server::{RunInfo, Schedule, State};
+use crate::signing::sign_release;
+use crate::utils::{
+    get_frozen_path, get_json_path, get_since_path, read_frozen_from_file, read_json_from_file, read_lock,
+    read_since_from_file, write_frozen_to_file, write_lock, write_since_to_file,
+};
+
+/// Version of the snapshot format, which is incremented for any incompatible change.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Complete state of the service, which can be restored on another host (for example, when migrating the service).
+///
+/// Snapshots that are created from the data directory (instead of a running service) only contain persisted data, so
+/// usage counts of overrides and refresh metadata are empty.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StateSnapshot {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub date_refreshed: Option<DateTime<Utc>>,
+    // broken packages per release (including frozen releases)
+    pub values: BTreeMap<String, Vec<BrokenItem>>,
+    // persisted times since which packages have been broken, per release
+    pub since: BTreeMap<String, SinceIndex>,
+    pub frozen: BTreeMap<String, DateTime<Utc>>,
+    #[serde(default)]
+    pub overrides: OverrideUsage,
+    #[serde(default)]
+    pub product_overrides: BTreeMap<String, OverrideUsage>,
+    #[serde(default)]
+    pub runs: BTreeMap<String, RunInfo>,
+    #[serde(default)]
+    pub schedule: BTreeMap<String, Schedule>,
+    // releases that were cancelled when the most recent refresh cycle exceeded its maximum duration
+    #[serde(default)]
+    pub carried_over: BTreeSet<String>,
+}
+
+/// Usage counts of the entries of an overrides file.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct OverrideUsage {
+    pub stats: OverrideStats,
+    pub package_stats: OverrideStats,
+}
+
+impl OverrideUsage {
+    fn from_overrides(overrides: &Overrides) -> Self {
+        OverrideUsage {
+            stats: overrides.stats.clone(),
+            package_stats: overrides.package_stats.clone(),
+        }
+    }
+
+    /// Restores usage counts, skipping overrides that are not present in the overrides file on this host.
+    fn apply(self, overrides: &mut Overrides) {
+        for (path, count) in self.stats {
+            if let Some(current) = overrides.stats.get_mut(&path) {
+                *current = count;
+            }
+        }
+
+        overrides.package_stats.extend(self.package_stats);
+    }
+}
+
+impl StateSnapshot {
+    fn empty() -> Self {
+        StateSnapshot {
+            version: SNAPSHOT_VERSION,
+            created_at: Utc::now(),
+            date_refreshed: None,
+            values: BTreeMap::new(),
+            since: BTreeMap::new(),
+            frozen: BTreeMap::new(),
+            overrides: OverrideUsage::default(),
+            product_overrides: BTreeMap::new(),
+            runs: BTreeMap::new(),
+            schedule: BTreeMap::new(),
+            carried_over: BTreeSet::new(),
+        }
+    }
+
+    /// Collects persisted data of all releases in the configuration from the data directory.
+    fn from_disk(config: &Config) -> Result<Self, String> {
+        let mut snapshot = Self::empty();
+
+        for entry in config.to_matrix()? {
+            let key = entry.key();
+
+            let json_path = get_json_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+            let frozen_path = get_frozen_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+            let since_path = get_since_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+
+            if json_path.exists() {
+                snapshot.values.insert(key.clone(), read_json_from_file(&json_path)?);
+            } else if frozen_path.exists() {
+                let frozen = read_frozen_from_file(&frozen_path)?;
+                snapshot.values.insert(key.clone(), frozen.items);
+                snapshot.frozen.insert(key.clone(), frozen.frozen_at);
+            } else {
+                info!("No data found for {}, skipping it.", &key);
+            }
+
+            if let Ok(since) = read_since_from_file(&since_path) {
+                snapshot.since.insert(key, since);
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Captures the state of the running service, including the persisted "since" data of all releases.
+    pub fn from_state(state: &State) -> Result<Self, String> {
+        let mut snapshot = Self::empty();
+
+        snapshot.date_refreshed = state.date_refreshed;
+        snapshot.values = state
+            .values
+            .iter()
+            .map(|(key, values)| (key.clone(), values.to_vec()))
+            .collect();
+        snapshot.frozen = state.frozen.iter().map(|(key, at)| (key.clone(), *at)).collect();
+
+        // data of archived releases is not necessarily loaded into memory
+        for (key, archived) in &state.archived {
+            if !snapshot.values.contains_key(key) {
+                snapshot
+                    .values
+                    .insert(key.clone(), read_frozen_from_file(&archived.path)?.items);
+            }
+        }
+        snapshot.runs = state.runs.iter().map(|(key, run)| (key.clone(), run.clone())).collect();
+        snapshot.schedule = state
+            .schedule
+            .iter()
+            .map(|(key, schedule)| (key.clone(), schedule.clone()))
+            .collect();
+        snapshot.carried_over = state.carried_over.clone();
+
+        for entry in state.config.to_matrix()? {
+            let since_path = get_since_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+            if let Ok(since) = read_since_from_file(&since_path) {
+                snapshot.since.insert(entry.key(), since);
+            }
+        }
+
+        snapshot.overrides = OverrideUsage::from_overrides(&read_lock(&state.overrides));
+        snapshot.product_overrides = state
+            .product_overrides
+            .iter()
+            .map(|(product, overrides)| (product.clone(), OverrideUsage::from_overrides(&read_lock(overrides))))
+            .collect();
+
+        Ok(snapshot)
+    }
+
+    /// Writes data of all releases that are present in the configuration to the data directory (and signs it, if
+    /// signing is configured), and returns their keys.
+    pub async fn persist(&self, config: &Config) -> Result<Vec<String>, String> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(format!(
+                "Unsupported snapshot version: {} (expected {})",
+                self.version, SNAPSHOT_VERSION
+            ));
+        }
+
+        let matrix = config.to_matrix()?;
+        let mut restored = Vec::new();
+
+        for entry in &matrix {
+            let key = entry.key();
+
+            let values = match self.values.get(&key) {
+                Some(values) => values,
+                None => continue,
+            };
+
+            let json_path = get_json_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+            match self.frozen.get(&key) {
+                Some(frozen_at) => {
+                    let frozen_path = get_frozen_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+                    let frozen = FrozenData {
+                        frozen_at: *frozen_at,
+                        items: values.clone(),
+                    };
+                    write_frozen_to_file(&frozen_path, &frozen)?;
+                    sign_release(config.signing.as_ref(), &json_path, values, true).await?;
+                },
+                None => {
+                    sign_release(config.signing.as_ref(), &json_path, values, false).await?;
+                },
+            }
+
+            if let Some(since) = self.since.get(&key) {
+                let since_path = get_since_path(entry.product.as_deref(), &entry.release, entry.with_testing);
+                write_since_to_file(&since_path, since)?;
+            }
+
+            restored.push(key);
+        }
+
+        for key in self.values.keys() {
+            if !restored.contains(key) {
+                warn!("Release {} is not present in the configuration, skipping it.", key);
+            }
+        }
+
+        Ok(restored)
+    }
+
+    /// Replaces the state of the running service with the contents of the snapshot, after the data of the `restored`
+    /// releases was persisted.
+    pub fn apply(self, state: &mut State, restored: &[String]) {
+        for key in restored {
+            if let Some(values) = self.values.get(key) {
+                state.values.insert(key.clone(), Arc::new(values.clone()));
+            }
+
+            if let Some(frozen_at) = self.frozen.get(key) {
+                state.frozen.insert(key.clone(), *frozen_at);
+            }
+
+            if let Some(run) = self.runs.get(key) {
+                state.runs.insert(key.clone(), run.clone());
+            }
+
+            if let Some(schedule) = self.schedule.get(key) {
+                state.schedule.insert(key.clone(), schedule.clone());
+            }
+
+            if self.carried_over.contains(key) {
+                state.carried_over.insert(key.clone());
+            }
+        }
+
+        if self.date_refreshed.is_some() {
+            state.date_refreshed = self.date_refreshed;
+        }
+
+        self.overrides.apply(&mut write_lock(&state.overrides));
+
+        for (product, usage) in self.product_overrides {
+            if let Some(overrides) = state.product_overrides.get(&product) {
+                usage.apply(&mut write_lock(overrides));
+            }
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let json = match serde_json::to_vec(self) {
+            Ok(json) => json,
+            Err(error) => return Err(format!("Failed to serialize snapshot: {}", error)),
+        };
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        match encoder.write_all(&json).and_then(|_| encoder.finish()) {
+            Ok(compressed) => Ok(compressed),
+            Err(error) => Err(format!("Failed to compress snapshot: {}", error)),
+        }
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let mut string = String::new();
+        if let Err(error) = GzDecoder::new(bytes).read_to_string(&mut string) {
+            return Err(format!("Failed to decompress snapshot: {}", error));
+        }
+
+        match serde_json::from_str(&string) {
+            Ok(snapshot) => Ok(snapshot),
+            Err(error) => Err(format!("Failed to deserialize snapshot: {}", error)),
+        }
+    }
+}
+
+/// Writes a snapshot of the persisted data of all configured releases to a file.
+pub fn snapshot(path: &Path) -> Result<(), String> {
+    let config = get_config()?;
+    let snapshot = StateSnapshot::from_disk(&config)?;
+
+    if let Err(error) = std::fs::write(path, snapshot.encode()?) {
+        return Err(format!(
+            "Failed to write snapshot to {}: {}",
+            path.to_string_lossy(),
+            error
+        ));
+    }
+
+    info!(
+        "Wrote snapshot with data for {} releases to {}.",
+        snapshot.values.len(),
+        path.to_string_lossy()
+    );
+    Ok(())
+}
+
+/// Restores persisted data of all configured releases from a snapshot file (while the service is not running).
+pub async fn restore(path: &Path) -> Result<(), String> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            return Err(format!(
+                "Failed to read snapshot from {}: {}",
+                path.to_string_lossy(),
+                error
+            ))
+        },
+    };
+
+    let config = get_config()?;
+    let restored = StateSnapshot::decode(&bytes)?.persist(&config).await?;
+
+    info!("Restored data for: {}", restored.join(", "));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn encode_decode() {
+        let mut snapshot = StateSnapshot::empty();
+        snapshot.values.insert(
+            String::from("rawhide"),
+            vec![BrokenItem {
+                package: String::from("foo"),
+                ..Default::default()
+            }],
+        );
+
+        let decoded = StateSnapshot::decode(&snapshot.encode().unwrap()).unwrap();
+
+        assert_eq!(decoded.version, SNAPSHOT_VERSION);
+        assert_eq!(decoded.values["rawhide"][0].package, "foo");
+    }
+}
@@ -1,19 +1,589 @@
+use std::collections::HashSet;
+use std::path::Path;
+
 use askama::Template;
+use chrono::{DateTime, Utc};
+use minijinja::{path_loader, Environment};
+use serde::Serialize;
+
+use crate::config::{BrandingConfig, TemplateConfig};
+use crate::data::BrokenItem;
+use crate::reports::{paginate, TableQuery};
+
+/// Data that is available for one release (identified by its key, for example `41-testing` or `epel/9`).
+pub(crate) struct IndexEntry {
+    pub key: String,
+    pub refreshed: Option<DateTime<Utc>>,
+    pub frozen_at: Option<DateTime<Utc>>,
+    // number of broken packages that are part of the critical path
+    pub critpath: usize,
+    // number of protected packages and groups that are not installable (on any architecture)
+    pub uninstallable: usize,
+}
+
+/// Active release, with the stable and testing variants (if any) listed next to each other.
+struct ActiveRelease {
+    product: Option<String>,
+    release: String,
+    name: String,
+    // time when data was last refreshed (if data for the variant is available)
+    stable: Option<String>,
+    testing: Option<String>,
+}
+
+struct ArchivedRelease {
+    product: Option<String>,
+    release: String,
+    name: String,
+    frozen_at: String,
+}
+
+const TESTING_SUFFIX: &str = "-testing";
+
+fn page_link(live: bool, path: &str) -> String {
+    if live {
+        format!("/{}", path)
+    } else {
+        format!("{}.json", path)
+    }
+}
+
+fn format_date(date: Option<DateTime<Utc>>) -> String {
+    match date {
+        Some(date) => date.format("%Y-%m-%d %H:%M UTC").to_string(),
+        None => String::from("unknown"),
+    }
+}
 
 #[derive(Template)]
 #[template(path = "index.html")]
 pub(crate) struct Index {
-    releases: Vec<String>,
+    releases: Vec<ActiveRelease>,
+    products: Vec<ActiveRelease>,
+    archived: Vec<ArchivedRelease>,
+    critpath: Vec<(String, usize)>,
+    uninstallable: Vec<(String, usize)>,
     stats: Vec<(String, usize)>,
     date_refreshed: String,
+    // whether the page is served by the running service (or is part of a static export)
+    live: bool,
+    branding: BrandingConfig,
 }
 
 impl Index {
-    pub fn new(releases: Vec<String>, stats: Vec<(String, usize)>, date_refreshed: String) -> Self {
+    pub fn new(
+        entries: Vec<IndexEntry>,
+        stats: Vec<(String, usize)>,
+        date_refreshed: String,
+        live: bool,
+        branding: BrandingConfig,
+    ) -> Self {
+        let mut active: Vec<ActiveRelease> = Vec::new();
+        let mut archived: Vec<ArchivedRelease> = Vec::new();
+
+        let mut critpath: Vec<(String, usize)> = entries
+            .iter()
+            .filter(|entry| entry.frozen_at.is_none() && entry.critpath > 0)
+            .map(|entry| (entry.key.clone(), entry.critpath))
+            .collect();
+        critpath.sort();
+        critpath.reverse();
+
+        let mut uninstallable: Vec<(String, usize)> = entries
+            .iter()
+            .filter(|entry| entry.uninstallable > 0)
+            .map(|entry| (entry.key.clone(), entry.uninstallable))
+            .collect();
+        uninstallable.sort();
+        uninstallable.reverse();
+
+        for entry in entries {
+            let (product, release) = match entry.key.split_once('/') {
+                Some((product, release)) => (Some(product.to_string()), release.to_string()),
+                None => (None, entry.key.clone()),
+            };
+
+            if let Some(frozen_at) = entry.frozen_at {
+                archived.push(ArchivedRelease {
+                    name: display_name(&branding, product.as_deref(), &release),
+                    product,
+                    release,
+                    frozen_at: format_date(Some(frozen_at)),
+                });
+                continue;
+            }
+
+            let (release, testing) = match release.strip_suffix(TESTING_SUFFIX) {
+                Some(release) => (release.to_string(), true),
+                None => (release, false),
+            };
+
+            let position = active
+                .iter()
+                .position(|other| other.product == product && other.release == release);
+            let row = match position {
+                Some(position) => &mut active[position],
+                None => {
+                    active.push(ActiveRelease {
+                        name: display_name(&branding, product.as_deref(), &release),
+                        product,
+                        release,
+                        stable: None,
+                        testing: None,
+                    });
+                    active.last_mut().expect("Just pushed an element.")
+                },
+            };
+
+            let refreshed = Some(format_date(entry.refreshed));
+            if testing {
+                row.testing = refreshed;
+            } else {
+                row.stable = refreshed;
+            }
+        }
+
+        // newest releases first, products grouped by name
+        active.sort_by(|a, b| (&a.product, &b.release).cmp(&(&b.product, &a.release)));
+        archived.sort_by(|a, b| (&a.product, &b.release).cmp(&(&b.product, &a.release)));
+
+        let (products, releases) = active.into_iter().partition(|release| release.product.is_some());
+
         Index {
             releases,
+            products,
+            archived,
+            critpath,
+            uninstallable,
             stats,
             date_refreshed,
+            live,
+            branding,
+        }
+    }
+
+    /// Returns the link to a page, either as a route of the running service, or as a relative path to a JSON file
+    /// in a static export.
+    fn link(&self, path: &str) -> String {
+        page_link(self.live, path)
+    }
+
+    fn data_link(&self, product: Option<&str>, release: &str) -> String {
+        match product {
+            // static exports use the same layout as the data directory
+            Some(product) if self.live => format!("/products/{}/data/{}", product, release),
+            Some(product) => format!("data/{}/{}.json", product, release),
+            None => self.link(&format!("data/{}", release)),
+        }
+    }
+
+    /// Returns the link to a page below the data of a release (for example, "critpath").
+    fn release_link(&self, key: &str, page: &str) -> String {
+        match key.split_once('/') {
+            Some((product, release)) if self.live => format!("/products/{}/data/{}/{}", product, release, page),
+            Some((product, release)) => format!("data/{}/{}/{}.json", product, release, page),
+            None => self.link(&format!("data/{}/{}", key, page)),
+        }
+    }
+
+    fn stable_link(&self, row: &ActiveRelease) -> String {
+        self.data_link(row.product.as_deref(), &row.release)
+    }
+
+    fn testing_link(&self, row: &ActiveRelease) -> String {
+        self.data_link(row.product.as_deref(), &format!("{}{}", row.release, TESTING_SUFFIX))
+    }
+
+    fn archived_link(&self, row: &ArchivedRelease) -> String {
+        self.data_link(row.product.as_deref(), &row.release)
+    }
+}
+
+fn display_name(branding: &BrandingConfig, product: Option<&str>, release: &str) -> String {
+    match product {
+        Some(product) => format!("{} {}", product, release),
+        None => format!("{} {}", branding.distribution, release),
+    }
+}
+
+/// Context that runtime-loaded templates are rendered with, with all names and links already resolved.
+#[derive(Serialize)]
+struct IndexContext {
+    releases: Vec<ReleaseContext>,
+    products: Vec<ReleaseContext>,
+    archived: Vec<ArchivedContext>,
+    critpath: Vec<CountContext>,
+    uninstallable: Vec<CountContext>,
+    stats: Vec<CountContext>,
+    date_refreshed: String,
+    live: bool,
+    branding: BrandingConfig,
+}
+
+#[derive(Serialize)]
+struct ReleaseContext {
+    name: String,
+    stable: Option<VariantContext>,
+    testing: Option<VariantContext>,
+}
+
+#[derive(Serialize)]
+struct VariantContext {
+    link: String,
+    refreshed: String,
+}
+
+#[derive(Serialize)]
+struct ArchivedContext {
+    name: String,
+    link: String,
+    frozen_at: String,
+}
+
+#[derive(Serialize)]
+struct CountContext {
+    key: String,
+    number: usize,
+    // link to the corresponding list of packages (if there is one)
+    link: Option<String>,
+}
+
+impl Index {
+    fn release_context(&self, row: &ActiveRelease) -> ReleaseContext {
+        ReleaseContext {
+            name: row.name.clone(),
+            stable: row.stable.as_ref().map(|refreshed| VariantContext {
+                link: self.stable_link(row),
+                refreshed: refreshed.clone(),
+            }),
+            testing: row.testing.as_ref().map(|refreshed| VariantContext {
+                link: self.testing_link(row),
+                refreshed: refreshed.clone(),
+            }),
+        }
+    }
+
+    fn context(&self) -> IndexContext {
+        let counts = |counts: &[(String, usize)], page: Option<&str>| -> Vec<CountContext> {
+            counts
+                .iter()
+                .map(|(key, number)| CountContext {
+                    key: key.clone(),
+                    number: *number,
+                    link: page.map(|page| self.release_link(key, page)),
+                })
+                .collect()
+        };
+
+        IndexContext {
+            releases: self.releases.iter().map(|row| self.release_context(row)).collect(),
+            products: self.products.iter().map(|row| self.release_context(row)).collect(),
+            archived: self
+                .archived
+                .iter()
+                .map(|row| ArchivedContext {
+                    name: row.name.clone(),
+                    link: self.archived_link(row),
+                    frozen_at: row.frozen_at.clone(),
+                })
+                .collect(),
+            critpath: counts(&self.critpath, Some("critpath")),
+            uninstallable: counts(&self.uninstallable, Some("protected")),
+            stats: counts(&self.stats, None),
+            date_refreshed: self.date_refreshed.clone(),
+            live: self.live,
+            branding: self.branding.clone(),
         }
     }
+
+    pub fn render_page(&self, templates: Option<&TemplateConfig>) -> Result<String, String> {
+        render_page(self, "index.html", templates, self.live, || self.context())
+    }
+}
+
+/// Column of the table of broken packages that can be filtered, with the current value of the filter.
+#[derive(Serialize)]
+struct ColumnFilter {
+    name: &'static str,
+    label: &'static str,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct TableRow {
+    // stable anchor of the first row of every source package on the page (for example, "src-foo")
+    anchor: Option<String>,
+    // link that redirects to the page and row of the source package
+    permalink: String,
+    source: String,
+    package: String,
+    evr: String,
+    arch: String,
+    repo: String,
+    admin: String,
+    co_maintainers: Vec<String>,
+    broken: Vec<String>,
+    since: String,
+}
+
+/// One page of the table of broken packages of a release.
+#[derive(Serialize, Template)]
+#[template(path = "release.html")]
+pub(crate) struct ReleaseTable {
+    name: String,
+    rows: Vec<TableRow>,
+    filters: Vec<ColumnFilter>,
+    page: usize,
+    pages: usize,
+    per_page: usize,
+    // number of broken packages that match the filters, and number of all broken packages of the release
+    matching: usize,
+    total: usize,
+    // links to the previous and next pages (if any), which keep the current filters
+    previous: Option<String>,
+    next: Option<String>,
+    // whether co-maintainers are listed next to the main admin
+    co_maintainers: bool,
+    // whether the page is served by the running service (or is part of a static export, which can not be filtered)
+    live: bool,
+    branding: BrandingConfig,
+}
+
+impl ReleaseTable {
+    pub fn new(
+        key: &str,
+        values: &[BrokenItem],
+        query: TableQuery,
+        co_maintainers: bool,
+        live: bool,
+        branding: BrandingConfig,
+    ) -> Self {
+        let table = paginate(values, &query);
+
+        // pages of static exports are stored next to each other, see `table_export_path`
+        let page_link = |page: usize| -> Option<String> {
+            if !live {
+                return Some(format!("{}.html", page));
+            }
+
+            let query = TableQuery {
+                page: Some(page),
+                ..query.clone()
+            };
+            serde_urlencoded::to_string(query)
+                .ok()
+                .map(|query| format!("?{}", query))
+        };
+
+        let filters = vec![
+            ("source", "Source", &query.source),
+            ("package", "Package", &query.package),
+            ("arch", "Architecture", &query.arch),
+            ("repo", "Repository", &query.repo),
+            ("admin", "Maintainer", &query.admin),
+            ("broken", "Unresolved dependency", &query.broken),
+        ]
+        .into_iter()
+        .map(|(name, label, value)| ColumnFilter {
+            name,
+            label,
+            value: value.clone().unwrap_or_default(),
+        })
+        .collect();
+
+        let name = match key.split_once('/') {
+            Some((product, release)) => display_name(&branding, Some(product), release),
+            None => display_name(&branding, None, key),
+        };
+
+        let mut sources = HashSet::new();
+        let permalink = |source: &str| match key.split_once('/') {
+            // the first row of every source package on a page of a static export has an anchor
+            _ if !live => format!("#{}", source_anchor(source)),
+            Some((product, release)) => format!("/products/{}/go/{}/{}", product, release, source),
+            None => format!("/go/{}/{}", key, source),
+        };
+
+        ReleaseTable {
+            name,
+            rows: table
+                .items
+                .iter()
+                .map(|item| TableRow {
+                    anchor: sources
+                        .insert(item.source.as_str())
+                        .then(|| source_anchor(&item.source)),
+                    permalink: permalink(&item.source),
+                    source: item.source.clone(),
+                    package: item.package.clone(),
+                    evr: item.evr(),
+                    arch: item.arch.clone(),
+                    repo: format!("{} ({})", item.repo, item.repo_arch),
+                    admin: item.admin.clone(),
+                    co_maintainers: item.co_maintainers(),
+                    broken: item.broken.clone(),
+                    since: format_date(item.since),
+                })
+                .collect(),
+            filters,
+            page: table.page,
+            pages: table.pages,
+            per_page: query.per_page(),
+            matching: table.matching,
+            total: values.len(),
+            previous: (table.page > 1).then(|| page_link(table.page - 1)).flatten(),
+            next: (table.page < table.pages).then(|| page_link(table.page + 1)).flatten(),
+            co_maintainers,
+            live,
+            branding,
+        }
+    }
+
+    /// Returns the number of pages of the table (with the filters of the query it was created with).
+    pub fn pages(&self) -> usize {
+        self.pages
+    }
+
+    pub fn render_page(&self, templates: Option<&TemplateConfig>) -> Result<String, String> {
+        render_page(self, "release.html", templates, self.live, || self)
+    }
+}
+
+/// Returns the path of a page of the table of broken packages of a release in static exports, relative to the root
+/// of the export (for example, `releases/epel/9/2.html`).
+pub(crate) fn table_export_path(key: &str, page: usize) -> String {
+    format!("releases/{}/{}.html", key, page)
+}
+
+/// Returns the anchor of the rows of a source package in the table of broken packages of a release.
+pub(crate) fn source_anchor(source: &str) -> String {
+    format!("src-{}", source)
+}
+
+/// Renders a page, with the template from the configured template directory if there is one (and with the built-in
+/// template otherwise).
+fn render_page<T: Template, C: Serialize>(
+    page: &T,
+    name: &str,
+    templates: Option<&TemplateConfig>,
+    live: bool,
+    context: impl FnOnce() -> C,
+) -> Result<String, String> {
+    if let Some(templates) = templates {
+        if let Some(body) = render_runtime(Path::new(&templates.directory), name, live, context())? {
+            return Ok(body);
+        }
+    }
+
+    page.render()
+        .map_err(|error| format!("Failed to render template: {}", error))
+}
+
+/// Renders a page with the template of the given name from the given directory, or returns `None` if the directory
+/// does not contain this template.
+///
+/// In addition to the context, templates can use the `link` function for resolving links to other pages (which
+/// differ between the running service and static exports), and include other templates from the same directory.
+fn render_runtime<C: Serialize>(
+    directory: &Path,
+    name: &str,
+    live: bool,
+    context: C,
+) -> Result<Option<String>, String> {
+    if !directory.join(name).exists() {
+        return Ok(None);
+    }
+
+    let mut env = Environment::new();
+    env.set_loader(path_loader(directory));
+    env.add_function("link", move |path: String| page_link(live, &path));
+
+    let template = match env.get_template(name) {
+        Ok(template) => template,
+        Err(error) => return Err(format!("Failed to load template: {}", error)),
+    };
+
+    match template.render(context) {
+        Ok(body) => Ok(Some(body)),
+        Err(error) => Err(format!("Failed to render template: {}", error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn render_runtime() {
+        let directory = std::env::temp_dir().join(format!("repochecker-templates-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let entries = vec![IndexEntry {
+            key: String::from("41"),
+            refreshed: None,
+            frozen_at: None,
+            critpath: 2,
+            uninstallable: 0,
+        }];
+        let index = Index::new(
+            entries,
+            vec![(String::from("41"), 3)],
+            String::from("now"),
+            true,
+            BrandingConfig::default(),
+        );
+
+        // the built-in template is used if the directory does not contain one
+        assert!(super::render_runtime(&directory, "index.html", true, index.context())
+            .unwrap()
+            .is_none());
+
+        std::fs::write(directory.join("footer.html"), "<footer>{{ link(\"config\") }}</footer>").unwrap();
+        std::fs::write(
+            directory.join("index.html"),
+            "{% for row in releases %}{{ row.name }}: {{ row.stable.link }}{% endfor %}\n\
+             {% for row in critpath %}{{ row.key }}: {{ row.link }}{% endfor %}\n\
+             {% include \"footer.html\" %}",
+        )
+        .unwrap();
+
+        let body = super::render_runtime(&directory, "index.html", true, index.context())
+            .unwrap()
+            .unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        // output is HTML-escaped, like with the built-in templates
+        assert_eq!(
+            body.replace("&#x2f;", "/"),
+            "Fedora 41: /data/41\n41: /data/41/critpath\n<footer>/config</footer>"
+        );
+    }
+
+    #[test]
+    fn static_table() {
+        let values: Vec<BrokenItem> = ["foo", "bar", "baz"]
+            .into_iter()
+            .map(|source| BrokenItem {
+                source: String::from(source),
+                package: String::from(source),
+                ..Default::default()
+            })
+            .collect();
+        let query = TableQuery {
+            page: Some(2),
+            per_page: Some(1),
+            ..Default::default()
+        };
+
+        // pages of static exports link to each other and to anchors on the same page instead of routes
+        let table = ReleaseTable::new("epel/9", &values, query, false, false, BrandingConfig::default());
+        assert_eq!(table.pages(), 3);
+        assert_eq!(table.previous.as_deref(), Some("1.html"));
+        assert_eq!(table.next.as_deref(), Some("3.html"));
+        assert_eq!(table.rows[0].permalink, "#src-bar");
+        assert_eq!(table_export_path("epel/9", 2), "releases/epel/9/2.html");
+        assert!(!table.render_page(None).unwrap().contains("<form"));
+    }
 }
@@ -1,16 +1,82 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use crate::data::BrokenItem;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::error;
 
-fn get_data_path() -> PathBuf {
+use schemars::generate::SchemaSettings;
+use schemars::transform::RecursiveTransform;
+use schemars::{JsonSchema, Schema};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::data::{BrokenItem, FrozenData, FrozenSummary, ProvideIndex, Provider, SinceIndex};
+
+/// Generates a JSON Schema for a type that is read from a file.
+///
+/// Unknown properties are rejected by the schema (but ignored when reading the file), so typos are caught when files
+/// are validated against it.
+pub fn schema_for<T: JsonSchema>(title: &str, comment: &str) -> Value {
+    let generator = SchemaSettings::draft2020_12()
+        .with_transform(RecursiveTransform(|schema: &mut Schema| {
+            if schema.get("properties").is_some() {
+                schema.insert(String::from("additionalProperties"), Value::Bool(false));
+            }
+        }))
+        .into_generator();
+
+    let mut schema = generator.into_root_schema_for::<T>();
+    schema.insert(String::from("title"), Value::from(title));
+    schema.insert(String::from("$comment"), Value::from(comment));
+    schema.to_value()
+}
+
+/// Acquires a read lock, recovering the inner data if the lock was poisoned by a panicking thread.
+pub fn read_lock<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|poisoned| {
+        error!("Recovering from a poisoned lock after a panic in another thread.");
+        lock.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+/// Acquires a write lock, recovering the inner data if the lock was poisoned by a panicking thread.
+pub fn write_lock<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|poisoned| {
+        error!("Recovering from a poisoned lock after a panic in another thread.");
+        lock.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+// subdirectories of the data directory that do not contain data for additional products
+pub const DATA_DIRECTORIES: [&str; 6] = ["history", "provides", "releases", "retired", "schedule", "since"];
+
+pub fn get_data_path() -> PathBuf {
     let mut path = PathBuf::new();
     path.push(std::env::current_dir().expect("Unable to determine current directory."));
     path.push("data/");
     path
 }
 
-pub fn get_json_path(release: &str, testing: bool) -> PathBuf {
+pub fn get_index_export_path() -> PathBuf {
+    let mut path = get_data_path();
+    path.push("index.html");
+    path
+}
+
+pub fn get_json_path(product: Option<&str>, release: &str, testing: bool) -> PathBuf {
     let mut path = get_data_path();
+    if let Some(product) = product {
+        path.push(product);
+    }
+
     if !testing {
         path.push(format!("{}.json", release));
     } else {
@@ -20,16 +86,59 @@ pub fn get_json_path(release: &str, testing: bool) -> PathBuf {
     path
 }
 
+#[cfg(feature = "test-backend")]
+pub fn get_fixture_path(product: Option<&str>, release: &str, testing: bool) -> PathBuf {
+    let mut path = PathBuf::new();
+    path.push(std::env::current_dir().expect("Unable to determine current directory."));
+    path.push("fixtures/");
+    if let Some(product) = product {
+        path.push(product);
+    }
+
+    if !testing {
+        path.push(format!("{}.json", release));
+    } else {
+        path.push(format!("{}-testing.json", release));
+    }
+
+    path
+}
+
+pub fn get_frozen_path(product: Option<&str>, release: &str, testing: bool) -> PathBuf {
+    let mut path = get_json_path(product, release, testing);
+    path.set_extension("json.gz");
+    path
+}
+
+/// Moves a data file into the "retired" subdirectory of the data directory.
+pub fn archive_json_file(path: &Path) -> Result<PathBuf, String> {
+    let data_path = get_data_path();
+
+    let relative = path
+        .strip_prefix(&data_path)
+        .map_err(|_| format!("Not a data file: {}", path.to_string_lossy()))?;
+
+    let mut target = data_path.clone();
+    target.push("retired/");
+    target.push(relative);
+
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|error| format!("Failed to create archive directory: {}", error))?;
+    }
+
+    std::fs::rename(path, &target).map_err(|error| format!("Failed to archive data file: {}", error))?;
+
+    Ok(target)
+}
+
 pub fn write_json_to_file(path: &Path, broken: &[BrokenItem]) -> Result<(), String> {
     let json = match serde_json::to_string_pretty(&broken) {
         Ok(json) => json,
         Err(_) => return Err(String::from("Failed to serialize broken dependencies into JSON.")),
     };
 
-    let data_path = get_data_path();
-
-    if !data_path.exists() {
-        std::fs::create_dir_all(data_path).expect("Failed to create data directory.");
+    if let Some(data_path) = path.parent() {
+        std::fs::create_dir_all(data_path).map_err(|error| format!("Failed to create data directory: {}", error))?;
     }
 
     if std::fs::write(path, json).is_err() {
@@ -39,20 +148,227 @@ pub fn write_json_to_file(path: &Path, broken: &[BrokenItem]) -> Result<(), Stri
     Ok(())
 }
 
-pub fn read_json_from_file(path: &Path) -> Result<Vec<BrokenItem>, String> {
-    if !path.exists() {
-        return Err(String::from("Data has not been generated yet."));
+/// Opens a file for reading. Files that do not exist are reported with the `missing` message (instead of checking
+/// whether they exist first, which could change before they are opened).
+fn open_file(path: &Path, missing: &str, failed: &str) -> Result<File, String> {
+    match File::open(path) {
+        Ok(file) => Ok(file),
+        Err(error) if error.kind() == ErrorKind::NotFound => Err(missing.to_string()),
+        Err(_) => Err(failed.to_string()),
     }
+}
 
-    let string = match std::fs::read_to_string(path) {
-        Ok(string) => string,
-        Err(_) => return Err(String::from("Failed to read cached JSON data.")),
-    };
+pub fn read_json_from_file(path: &Path) -> Result<Vec<BrokenItem>, String> {
+    let file = open_file(
+        path,
+        "Data has not been generated yet.",
+        "Failed to read cached JSON data.",
+    )?;
 
-    let values: Vec<BrokenItem> = match serde_json::from_str(&string) {
+    // deserialize while reading, instead of reading large files into memory first
+    let values: Vec<BrokenItem> = match serde_json::from_reader(BufReader::new(file)) {
         Ok(values) => values,
         Err(_) => return Err(String::from("Failed to deserialize cached JSON data.")),
     };
 
     Ok(values)
 }
+
+pub fn write_compressed_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), String> {
+    let json = match serde_json::to_vec(value) {
+        Ok(json) => json,
+        Err(_) => return Err(String::from("Failed to serialize data into JSON.")),
+    };
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    let compressed = match encoder.write_all(&json).and_then(|_| encoder.finish()) {
+        Ok(compressed) => compressed,
+        Err(error) => return Err(format!("Failed to compress data: {}", error)),
+    };
+
+    if let Some(data_path) = path.parent() {
+        std::fs::create_dir_all(data_path).map_err(|error| format!("Failed to create data directory: {}", error))?;
+    }
+
+    if std::fs::write(path, compressed).is_err() {
+        return Err(format!("Failed to write data to disk: {}", &path.to_string_lossy()));
+    }
+
+    Ok(())
+}
+
+pub fn read_compressed_json<T: DeserializeOwned>(path: &Path, missing: &str) -> Result<T, String> {
+    let file = open_file(path, missing, "Failed to read compressed JSON data.")?;
+
+    match serde_json::from_reader(BufReader::new(GzDecoder::new(file))) {
+        Ok(value) => Ok(value),
+        Err(error) if error.is_io() => Err(String::from("Failed to decompress JSON data.")),
+        Err(_) => Err(String::from("Failed to deserialize compressed JSON data.")),
+    }
+}
+
+pub fn write_frozen_to_file(path: &Path, frozen: &FrozenData) -> Result<(), String> {
+    write_compressed_json(path, frozen)
+}
+
+pub fn read_frozen_from_file(path: &Path) -> Result<FrozenData, String> {
+    read_compressed_json(path, "Release has not been frozen yet.")
+}
+
+/// Reads only the summary of frozen data, which is much cheaper than reading the full data.
+pub fn read_frozen_summary(path: &Path) -> Result<FrozenSummary, String> {
+    read_compressed_json(path, "Release has not been frozen yet.")
+}
+
+pub fn get_provides_path(product: Option<&str>, release: &str, testing: bool, arch: &str) -> PathBuf {
+    let mut path = get_data_path();
+    if let Some(product) = product {
+        path.push(product);
+    }
+
+    path.push("provides/");
+    if !testing {
+        path.push(format!("{}-{}.json.gz", release, arch));
+    } else {
+        path.push(format!("{}-testing-{}.json.gz", release, arch));
+    }
+
+    path
+}
+
+pub fn write_provides_to_file(path: &Path, provides: &ProvideIndex) -> Result<(), String> {
+    write_compressed_json(path, provides)
+}
+
+pub fn read_provides_from_file(path: &Path) -> Result<ProvideIndex, String> {
+    // indices that were written before all providers were recorded have a single provider per capability
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Providers {
+        All(Vec<Provider>),
+        Single(Provider),
+    }
+
+    let index: HashMap<String, Providers> =
+        read_compressed_json(path, "No provides from a previous run are available.")?;
+
+    Ok(index
+        .into_iter()
+        .map(|(provide, providers)| match providers {
+            Providers::All(providers) => (provide, providers),
+            Providers::Single(provider) => (provide, vec![provider]),
+        })
+        .collect())
+}
+
+pub fn get_since_path(product: Option<&str>, release: &str, testing: bool) -> PathBuf {
+    let mut path = get_data_path();
+    if let Some(product) = product {
+        path.push(product);
+    }
+
+    path.push("since/");
+    if !testing {
+        path.push(format!("{}.json", release));
+    } else {
+        path.push(format!("{}-testing.json", release));
+    }
+
+    path
+}
+
+/// Writes JSON to a temporary file first and then moves it into place, so the previous contents are kept intact if
+/// writing fails.
+fn replace_json_file<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), String> {
+    let json = match serde_json::to_string_pretty(value) {
+        Ok(json) => json,
+        Err(_) => return Err(String::from("Failed to serialize data into JSON.")),
+    };
+
+    if let Some(data_path) = path.parent() {
+        std::fs::create_dir_all(data_path).map_err(|error| format!("Failed to create data directory: {}", error))?;
+    }
+
+    let temporary = path.with_extension("json.tmp");
+    if std::fs::write(&temporary, json).is_err() {
+        return Err(format!(
+            "Failed to write data to disk: {}",
+            &temporary.to_string_lossy()
+        ));
+    }
+
+    std::fs::rename(&temporary, path)
+        .map_err(|error| format!("Failed to replace {}: {}", path.to_string_lossy(), error))
+}
+
+pub fn write_since_to_file(path: &Path, since: &SinceIndex) -> Result<(), String> {
+    replace_json_file(path, since)
+}
+
+pub fn read_since_from_file(path: &Path) -> Result<SinceIndex, String> {
+    let file = open_file(
+        path,
+        "No timestamps from a previous run are available.",
+        "Failed to read persisted timestamps.",
+    )?;
+
+    match serde_json::from_reader(BufReader::new(file)) {
+        Ok(since) => Ok(since),
+        Err(_) => Err(String::from("Failed to deserialize persisted timestamps.")),
+    }
+}
+
+/// Path of the persisted refresh schedule (which is shared by all releases).
+pub fn get_schedule_path() -> PathBuf {
+    let mut path = get_data_path();
+    path.push("schedule/schedule.json");
+    path
+}
+
+pub fn write_schedule_to_file<T: Serialize>(path: &Path, schedule: &T) -> Result<(), String> {
+    replace_json_file(path, schedule)
+}
+
+pub fn read_schedule_from_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let file = open_file(
+        path,
+        "No refresh schedule from a previous run is available.",
+        "Failed to read persisted refresh schedule.",
+    )?;
+
+    match serde_json::from_reader(BufReader::new(file)) {
+        Ok(schedule) => Ok(schedule),
+        Err(_) => Err(String::from("Failed to deserialize persisted refresh schedule.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn missing_files() {
+        let directory = std::env::temp_dir().join(format!("repochecker-missing-{}", uuid::Uuid::new_v4()));
+
+        assert_eq!(
+            read_json_from_file(&directory.join("41.json")).unwrap_err(),
+            "Data has not been generated yet."
+        );
+        assert_eq!(
+            read_since_from_file(&directory.join("since/41.json")).unwrap_err(),
+            "No timestamps from a previous run are available."
+        );
+        assert_eq!(
+            read_frozen_summary(&directory.join("39.json.gz")).unwrap_err(),
+            "Release has not been frozen yet."
+        );
+
+        // files that exist but can not be read are reported differently
+        std::fs::create_dir_all(directory.join("40.json")).unwrap();
+        let error = read_json_from_file(&directory.join("40.json")).unwrap_err();
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_ne!(error, "Data has not been generated yet.");
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::process::Command;
+
+/// Version of repochecker itself.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// set by the build script (empty if it could not be determined)
+const GIT_COMMIT: &str = env!("REPOCHECKER_GIT_COMMIT");
+const BUILD_TIMESTAMP: &str = env!("REPOCHECKER_BUILD_TIMESTAMP");
+
+// version of dnf, determined on first use (which is the self-check on startup)
+static DNF_VERSION: OnceLock<Option<String>> = OnceLock::new();
+
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub commit: Option<&'static str>,
+    pub build_date: Option<DateTime<Utc>>,
+    pub dnf: Option<String>,
+}
+
+/// Returns the version of the dnf binary that is used for generating data, if it is available.
+///
+/// dnf is only run the first time this is called, and the result is cached for the lifetime of the service.
+pub async fn get_dnf_version() -> Option<String> {
+    if let Some(version) = DNF_VERSION.get() {
+        return version.clone();
+    }
+
+    let version = query_dnf_version().await;
+    DNF_VERSION.get_or_init(|| version).clone()
+}
+
+async fn query_dnf_version() -> Option<String> {
+    let output = Command::new("dnf").arg("--version").output().await.ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let string = String::from_utf8(output.stdout).ok()?;
+    string.lines().next().map(|line| line.trim().to_string())
+}
+
+/// Collects version and build information of the running service.
+pub async fn get_version_info() -> VersionInfo {
+    let commit = Some(GIT_COMMIT).filter(|commit| !commit.is_empty());
+
+    let build_date = BUILD_TIMESTAMP
+        .parse::<i64>()
+        .ok()
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0));
+
+    VersionInfo {
+        version: VERSION,
+        commit,
+        build_date,
+        dnf: get_dnf_version().await,
+    }
+}